@@ -0,0 +1,186 @@
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Calls left in the current rate-limit window, when the API says.
+const REMAINING: &str = "x-ratelimit-remaining";
+
+/// At most one decrease per this long, so a burst of 429s from the same
+/// window halves the limit once rather than collapsing it to 1.
+const COOLDOWN: Duration = Duration::from_secs(1);
+
+/// A concurrency limit that tunes itself from responses, AIMD-style: each
+/// success raises it by `1/limit` (about one per round of requests), while a
+/// 429 or an `X-RateLimit-Remaining` of 0 halves it. A remaining count below
+/// the limit caps it there. Workers hold a [`Permit`] per call, so no more
+/// than the current limit run at once; it always stays within `1..=max`.
+#[derive(Debug)]
+pub struct AdaptiveLimit {
+    max: usize,
+    state: Mutex<State>,
+    released: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    limit: f64,
+    in_flight: usize,
+    last_decrease: Option<Instant>,
+}
+
+impl AdaptiveLimit {
+    pub fn new(initial: usize, max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            max,
+            state: Mutex::new(State {
+                limit: initial.clamp(1, max) as f64,
+                in_flight: 0,
+                last_decrease: None,
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Calls allowed at once right now.
+    pub fn limit(&self) -> usize {
+        self.lock().limit as usize
+    }
+
+    /// Waits until fewer than the current limit are in flight.
+    pub fn acquire(&self) -> Permit<'_> {
+        let state = self.lock();
+        let mut state = self
+            .released
+            .wait_while(state, |s| s.in_flight >= s.limit as usize)
+            .unwrap_or_else(PoisonError::into_inner);
+        state.in_flight += 1;
+        Permit(self)
+    }
+
+    /// Adjusts the limit from one response (retries included).
+    pub fn observe(&self, status: StatusCode, headers: &HeaderMap) {
+        let remaining = headers
+            .get(REMAINING)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+        let mut state = self.lock();
+        let before = state.limit as usize;
+        if status == StatusCode::TOO_MANY_REQUESTS || remaining == Some(0) {
+            if state
+                .last_decrease
+                .is_none_or(|at| at.elapsed() >= COOLDOWN)
+            {
+                state.limit = (state.limit / 2.0).max(1.0);
+                state.last_decrease = Some(Instant::now());
+            }
+        } else if let Some(remaining) = remaining.filter(|r| (*r as f64) < state.limit) {
+            state.limit = (remaining as f64).max(1.0);
+        } else if status.is_success() {
+            state.limit = (state.limit + 1.0 / state.limit).min(self.max as f64);
+        }
+        let after = state.limit as usize;
+        drop(state);
+        if after != before {
+            log::debug!("adaptive concurrency {before} -> {after} (after {status})");
+            self.released.notify_all();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A slot under an [`AdaptiveLimit`]; released on drop.
+#[derive(Debug)]
+pub struct Permit<'a>(&'a AdaptiveLimit);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.0.lock().in_flight -= 1;
+        self.0.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn remaining(n: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(REMAINING, HeaderValue::from_str(n).unwrap());
+        headers
+    }
+
+    #[test]
+    fn successes_raise_the_limit_up_to_max() {
+        let limit = AdaptiveLimit::new(1, 3);
+        limit.observe(StatusCode::OK, &HeaderMap::new());
+        assert_eq!(limit.limit(), 2);
+        // About one more per round of `limit` successes: 2.5, 2.9, 3.24.
+        limit.observe(StatusCode::OK, &HeaderMap::new());
+        limit.observe(StatusCode::OK, &HeaderMap::new());
+        assert_eq!(limit.limit(), 2);
+        limit.observe(StatusCode::OK, &HeaderMap::new());
+        assert_eq!(limit.limit(), 3);
+        for _ in 0..10 {
+            limit.observe(StatusCode::OK, &HeaderMap::new());
+        }
+        assert_eq!(limit.limit(), 3);
+    }
+
+    #[test]
+    fn a_429_halves_the_limit_once_per_cooldown() {
+        let limit = AdaptiveLimit::new(8, 8);
+        limit.observe(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new());
+        assert_eq!(limit.limit(), 4);
+        limit.observe(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new());
+        assert_eq!(limit.limit(), 4);
+        limit.lock().last_decrease = Some(Instant::now() - COOLDOWN);
+        limit.observe(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new());
+        assert_eq!(limit.limit(), 2);
+    }
+
+    #[test]
+    fn rate_limit_headers_lower_the_limit() {
+        let limit = AdaptiveLimit::new(8, 8);
+        limit.observe(StatusCode::OK, &remaining("3"));
+        assert_eq!(limit.limit(), 3);
+        // A remaining count above the limit doesn't hold back growth.
+        limit.observe(StatusCode::OK, &remaining("100"));
+        assert_eq!(limit.limit(), 3);
+        limit.observe(StatusCode::OK, &remaining("0"));
+        assert_eq!(limit.limit(), 1);
+        limit.lock().last_decrease = None;
+        limit.observe(StatusCode::OK, &remaining("0"));
+        assert_eq!(limit.limit(), 1);
+    }
+
+    #[test]
+    fn errors_other_than_429_leave_the_limit_alone() {
+        let limit = AdaptiveLimit::new(2, 8);
+        limit.observe(StatusCode::INTERNAL_SERVER_ERROR, &HeaderMap::new());
+        limit.observe(StatusCode::BAD_REQUEST, &remaining("junk"));
+        assert_eq!(limit.limit(), 2);
+    }
+
+    #[test]
+    fn permits_wait_for_a_free_slot() {
+        let limit = AdaptiveLimit::new(1, 4);
+        let first = limit.acquire();
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| {
+                let _second = limit.acquire();
+                Instant::now()
+            });
+            std::thread::sleep(Duration::from_millis(50));
+            let released = Instant::now();
+            drop(first);
+            assert!(waiter.join().unwrap() >= released);
+        });
+        assert_eq!(limit.lock().in_flight, 0);
+    }
+}
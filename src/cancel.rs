@@ -0,0 +1,24 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Exit code used when a run is interrupted (128 + SIGINT).
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// First Ctrl-C asks long loops to stop and return what they have; a second
+/// one exits immediately.
+pub fn install() {
+    let result = ctrlc::set_handler(|| {
+        if CANCELLED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        eprintln!("interrupted; finishing the current request (Ctrl-C again to abort)");
+    });
+    if let Err(err) = result {
+        log::debug!("ctrl-c handler not installed: {err}");
+    }
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
@@ -1,8 +1,35 @@
 use anyhow::{Context, Result, anyhow};
-use reqwest::blocking::{Client, RequestBuilder};
-use reqwest::header::{AUTHORIZATION, HeaderValue};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, ClientBuilder, RequestBuilder};
+use reqwest::header::{
+    ACCEPT, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue,
+    LOCATION,
+};
+use reqwest::redirect::Policy;
 use serde_json::Value;
-use std::time::Duration;
+use std::fs::File;
+use std::io::Read;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::adaptive::AdaptiveLimit;
+use crate::cancel;
+use crate::coalesce::InFlight;
+use crate::error::{AuthFailure, CliError};
+use crate::fixtures::{Fixtures, Tee};
+use crate::json_stream;
+use crate::metrics::Metrics;
+use crate::query;
+use crate::request_log::RequestLog;
+use crate::response_cache::ResponseCache;
+use crate::retry::{self, RetryBudget, RetryPolicy};
+use crate::sources::SourceFile;
+
+const MAX_SAFE_URL_LEN: usize = 8 * 1024;
 
 #[derive(Debug, Clone)]
 pub enum Auth {
@@ -10,37 +37,507 @@ pub enum Auth {
     Basic { username: String, password: String },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Body {
     Json(Value),
     Form(Vec<(String, String)>),
+    /// A JSON document streamed from disk as-is, never parsed into a `Value`.
+    JsonFile(Arc<SourceFile>),
+}
+
+/// HTTP protocol negotiation, shared by the API client and the auxiliary
+/// download/upload clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// HTTP/2 via ALPN when the server offers it, else HTTP/1.1.
+    #[default]
+    Auto,
+    /// Never attempt HTTP/2 (for middleboxes that break on it).
+    Http1,
+    /// Speak HTTP/2 without negotiation.
+    Http2PriorKnowledge,
+}
+
+impl HttpVersion {
+    pub fn apply(self, builder: ClientBuilder) -> ClientBuilder {
+        match self {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1 => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        }
+    }
+}
+
+/// Connection pool tuning; `None` keeps reqwest's defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolOptions {
+    pub max_idle_per_host: Option<usize>,
+    pub idle_timeout: Option<Duration>,
+}
+
+impl PoolOptions {
+    pub fn apply(self, mut builder: ClientBuilder) -> ClientBuilder {
+        if let Some(max) = self.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = self.idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        builder
+    }
 }
 
+/// Retries for rate-limited (429) responses unless overridden.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Header carrying [`PinterestClientBuilder::correlation_id`].
+pub const CORRELATION_HEADER: &str = "x-request-id";
+
+/// Retries successful responses whose body matches any predicate (e.g. a
+/// `status=PROCESSING` job), with backoff, up to `max` times.
+#[derive(Debug, Clone, Default)]
+pub struct RetryIf {
+    pub predicates: Vec<query::ClientFilter>,
+    pub max: u32,
+    /// Retry any method, not just those in the policy's `--retry-methods`.
+    pub any_method: bool,
+}
+
+impl RetryIf {
+    fn matches(&self, text: &str) -> bool {
+        if self.predicates.is_empty() {
+            return false;
+        }
+        let Ok(value) = serde_json::from_str::<Value>(text) else {
+            return false;
+        };
+        self.predicates.iter().any(|p| p.matches(&value))
+    }
+}
+
+/// A decoded response with its status and headers.
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+#[derive(Clone)]
 pub struct PinterestClient {
     client: Client,
+    base_url: Url,
+    retry: RetryPolicy,
+    not_found: RetryPolicy,
+    retry_if: RetryIf,
+    retry_if_policy: RetryPolicy,
+    retry_budget: Option<Arc<RetryBudget>>,
+    param_headers: HeaderMap,
+    required_scopes: Vec<String>,
+    headers: HeaderMap,
+    http_version: HttpVersion,
+    request_log: Option<Arc<RequestLog>>,
+    metrics: Option<Arc<Metrics>>,
+    in_flight: Option<Arc<InFlight<ApiResponse>>>,
+    cache: Option<Arc<ResponseCache>>,
+    fixtures: Option<Arc<Fixtures>>,
+    reauth: Option<Arc<Reauth>>,
+    adaptive: Option<Arc<AdaptiveLimit>>,
+    request_timeout: Option<Duration>,
+    gzip_body: bool,
+    /// Off in [`PinterestClient::without_auth`] copies.
+    send_auth: bool,
+}
+
+/// Renews a bearer token the API rejected; see [`PinterestClient::with_reauthorize`].
+pub type Renew = dyn Fn() -> Result<String> + Send + Sync;
+
+struct Reauth {
+    /// The token this renews; other bearer tokens (e.g. a conversion token) are left alone.
+    token: String,
+    renew: Box<Renew>,
+    /// `None` until tried, then the new token (`None` if renewing failed).
+    renewed: Mutex<Option<Option<String>>>,
+}
+
+/// Networking options for [`PinterestClient`]; start from
+/// [`PinterestClient::builder`] and finish with [`build`](Self::build).
+#[derive(Clone)]
+pub struct PinterestClientBuilder {
     base_url: String,
+    timeout: Option<Duration>,
+    http_version: HttpVersion,
+    pool: PoolOptions,
+    user_agent: String,
+    max_redirects: Option<usize>,
+    retry: RetryPolicy,
+    retry_budget: Option<Arc<RetryBudget>>,
+    not_found_retries: u32,
+    retry_if: RetryIf,
+    headers: HeaderMap,
+    request_log: Option<Arc<RequestLog>>,
+    metrics: Option<Arc<Metrics>>,
+    dedupe: bool,
+    cache: Option<Arc<ResponseCache>>,
+    fixtures: Option<Arc<Fixtures>>,
+    gzip_body: bool,
+    correlation_id: Option<String>,
 }
 
-impl PinterestClient {
-    pub fn new(base_url: String, timeout: Option<u64>) -> Result<Self> {
-        let mut builder = Client::builder().user_agent("pinterest-ads-cli/0.1.0");
-        if let Some(seconds) = timeout {
-            builder = builder.timeout(Duration::from_secs(seconds));
+impl PinterestClientBuilder {
+    /// Whole-request timeout; none by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn http_version(mut self, http_version: HttpVersion) -> Self {
+        self.http_version = http_version;
+        self
+    }
+
+    pub fn pool(mut self, pool: PoolOptions) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Redirect hops to follow (reqwest's default of 10 when unset). With `0`
+    /// a 3xx is returned as `{"status", "location"}` instead of being chased.
+    pub fn max_redirects(mut self, max: usize) -> Self {
+        self.max_redirects = Some(max);
+        self
+    }
+
+    /// Retries for 429 responses (honoring `Retry-After`). Its max delay and
+    /// jitter also apply to the 404 and `retry_if` retries.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Caps retries of every kind across all requests of this client (and its
+    /// clones), so a degraded API can't stretch a bulk run out indefinitely.
+    pub fn retry_budget(mut self, retries: u64) -> Self {
+        self.retry_budget = Some(Arc::new(RetryBudget::new(retries)));
+        self
+    }
+
+    /// Retries GETs that return 404 up to `retries` times with backoff, for
+    /// reads that race an eventually-consistent create.
+    pub fn not_found_retries(mut self, retries: u32) -> Self {
+        self.not_found_retries = retries;
+        self
+    }
+
+    pub fn retry_if(mut self, retry_if: RetryIf) -> Self {
+        self.retry_if = retry_if;
+        self
+    }
+
+    /// Extra headers sent with every request. They are applied last, so they
+    /// replace any same-named header the client computed (including auth).
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Records every request (including retries) to a JSON-lines audit log.
+    pub fn request_log(mut self, log: Arc<RequestLog>) -> Self {
+        self.request_log = Some(log);
+        self
+    }
+
+    /// Counts every request (including retries) by outcome, for `--stats`.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Lets identical GETs (same URL, query, auth and headers) issued at the
+    /// same time from clones of this client share one response, e.g. when
+    /// several threads fetch the same child listing. Failures aren't shared.
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Serves GETs from an on-disk cache while fresh; writes through this
+    /// client invalidate the resource. See [`ResponseCache`].
+    pub fn cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Records every response to [`Fixtures`], or answers every request from
+    /// them without touching the network.
+    pub fn fixtures(mut self, fixtures: Arc<Fixtures>) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    /// Gzips JSON bodies and sends them with `Content-Encoding: gzip`, for
+    /// large payloads to endpoints that accept it. Form and streamed
+    /// (`Body::JsonFile`) bodies are sent as-is.
+    pub fn gzip_body(mut self, gzip: bool) -> Self {
+        self.gzip_body = gzip;
+        self
+    }
+
+    /// Sends `id` as [`CORRELATION_HEADER`] on every request, retries and
+    /// pages included, so one run can be traced through server logs. It is
+    /// not part of what identifies a request for caching or deduping, and
+    /// [`headers`](Self::headers) can still replace it.
+    pub fn correlation_id(mut self, id: impl Into<String>) -> Self {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    pub fn build(self) -> Result<PinterestClient> {
+        // Responses are always decoded as JSON; say so, whatever the method.
+        let mut defaults = HeaderMap::new();
+        defaults.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        if let Some(id) = &self.correlation_id {
+            let value = HeaderValue::from_str(id)
+                .map_err(|err| anyhow!("invalid correlation id {id:?}: {err}"))?;
+            defaults.insert(CORRELATION_HEADER, value);
+        }
+        let mut builder = self.pool.apply(
+            self.http_version.apply(
+                Client::builder()
+                    .user_agent(self.user_agent)
+                    .default_headers(defaults),
+            ),
+        );
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        match self.max_redirects {
+            Some(0) => builder = builder.redirect(Policy::none()),
+            Some(max) => builder = builder.redirect(Policy::limited(max)),
+            None => {}
         }
         let client = builder.build().context("build http client")?;
-        Ok(Self { client, base_url })
+        let mut base_url = Url::parse(&self.base_url)
+            .with_context(|| format!("invalid base url: {}", self.base_url))?;
+        // A trailing slash makes Url::join append to the base path instead of replacing
+        // its last segment (e.g. keeps "/v5" in "https://api.pinterest.com/v5").
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        Ok(PinterestClient {
+            client,
+            base_url,
+            retry: self.retry,
+            not_found: self
+                .retry
+                .derive(self.not_found_retries, Duration::from_millis(500)),
+            retry_if_policy: self.retry.derive(self.retry_if.max, Duration::from_secs(1)),
+            retry_if: self.retry_if,
+            retry_budget: self.retry_budget,
+            param_headers: HeaderMap::new(),
+            required_scopes: Vec::new(),
+            headers: self.headers,
+            http_version: self.http_version,
+            request_log: self.request_log,
+            metrics: self.metrics,
+            in_flight: self.dedupe.then(|| Arc::new(InFlight::new())),
+            cache: self.cache,
+            fixtures: self.fixtures,
+            reauth: None,
+            adaptive: None,
+            request_timeout: None,
+            gzip_body: self.gzip_body,
+            send_auth: true,
+        })
+    }
+}
+
+impl PinterestClient {
+    /// A client with default options; see [`builder`](Self::builder) for the rest.
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::builder(base_url).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> PinterestClientBuilder {
+        PinterestClientBuilder {
+            base_url: base_url.into(),
+            timeout: None,
+            http_version: HttpVersion::default(),
+            pool: PoolOptions::default(),
+            user_agent: "pinterest-ads-cli/0.1.0".to_string(),
+            max_redirects: None,
+            retry: RetryPolicy::new(DEFAULT_MAX_RETRIES, Duration::from_secs(1)),
+            retry_budget: None,
+            not_found_retries: 0,
+            retry_if: RetryIf::default(),
+            headers: HeaderMap::new(),
+            request_log: None,
+            metrics: None,
+            dedupe: false,
+            cache: None,
+            fixtures: None,
+            gzip_body: false,
+            correlation_id: None,
+        }
+    }
+
+    /// A copy of this client that also sends an operation's header params
+    /// (`in: header`); `--header` values still take precedence.
+    pub fn with_param_headers(&self, headers: HeaderMap) -> Self {
+        Self {
+            param_headers: headers,
+            ..self.clone()
+        }
+    }
+
+    /// A copy of this client whose API errors name the OAuth `scopes` the
+    /// operation needs, so a scope refusal can say which grant is missing.
+    pub fn with_required_scopes(&self, scopes: Vec<String>) -> Self {
+        Self {
+            required_scopes: scopes,
+            ..self.clone()
+        }
+    }
+
+    /// A copy of this client that reports every response to `limit`, so
+    /// callers gating their workers on it follow the API's rate limits.
+    pub fn with_adaptive_limit(&self, limit: Arc<AdaptiveLimit>) -> Self {
+        Self {
+            adaptive: Some(limit),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of this client whose requests time out after `timeout` instead
+    /// of the builder's timeout, for operations known to be slow (or fast).
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            request_timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of this client that, the first time `token` is refused with a
+    /// 401, calls `renew` for a new one and retries with it. Later requests
+    /// still carrying `token` use the new one directly. Renewal is tried once
+    /// per client (and its clones), and not for scope errors.
+    pub fn with_reauthorize(
+        &self,
+        token: String,
+        renew: impl Fn() -> Result<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            reauth: Some(Arc::new(Reauth {
+                token,
+                renew: Box::new(renew),
+                renewed: Mutex::new(None),
+            })),
+            ..self.clone()
+        }
     }
 
-    pub fn build_url(&self, path: &str) -> String {
+    /// The renewed token if `auth` is the one being replaced; with
+    /// `renew_now`, renews it first if that hasn't been tried yet.
+    fn renewed_auth(&self, auth: &Auth, renew_now: bool) -> Option<Auth> {
+        let reauth = self.reauth.as_ref()?;
+        if !matches!(auth, Auth::Bearer(token) if *token == reauth.token) {
+            return None;
+        }
+        let mut renewed = reauth
+            .renewed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if renewed.is_none() && renew_now {
+            *renewed = Some(match (reauth.renew)() {
+                Ok(token) => {
+                    log::info!("access token rejected; renewed it and retrying");
+                    Some(token)
+                }
+                Err(err) => {
+                    log::warn!("access token rejected and renewing it failed: {err:#}");
+                    None
+                }
+            });
+        }
+        renewed.clone().flatten().map(Auth::Bearer)
+    }
+
+    /// A copy of this client that sends no credentials: neither the `auth` of
+    /// a request nor an `Authorization` header, for URLs off the API's origin.
+    pub fn without_auth(&self) -> Self {
+        let mut headers = self.headers.clone();
+        headers.remove(AUTHORIZATION);
+        let mut param_headers = self.param_headers.clone();
+        param_headers.remove(AUTHORIZATION);
+        Self {
+            headers,
+            param_headers,
+            reauth: None,
+            send_auth: false,
+            ..self.clone()
+        }
+    }
+
+    /// Whether `url` has the scheme, host and port of the base URL.
+    pub fn same_origin(&self, url: &str) -> bool {
+        Url::parse(url).is_ok_and(|url| url.origin() == self.base_url.origin())
+    }
+
+    /// A copy of this client that always goes to the network, for reads that
+    /// must be current (e.g. checking state right before a write).
+    pub fn uncached(&self) -> Self {
+        Self {
+            cache: None,
+            ..self.clone()
+        }
+    }
+
+    /// Whether JSON bodies go out gzipped (see [`PinterestClientBuilder::gzip_body`]).
+    pub fn gzips_body(&self) -> bool {
+        self.gzip_body
+    }
+
+    /// Header params plus `--header` values, as they'll be sent (the latter
+    /// replacing same-named header params).
+    pub fn request_headers(&self) -> HeaderMap {
+        let mut merged = self.param_headers.clone();
+        for name in self.headers.keys() {
+            merged.remove(name);
+        }
+        for (name, value) in &self.headers {
+            merged.append(name, value.clone());
+        }
+        merged
+    }
+
+    pub fn http_version(&self) -> HttpVersion {
+        self.http_version
+    }
+
+    /// The 429 retry policy, for callers that poll with their own loops.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    pub fn build_url(&self, path: &str) -> Result<String> {
         if path.starts_with("http://") || path.starts_with("https://") {
-            return path.to_string();
+            return Ok(path.to_string());
         }
-        let base = self.base_url.trim_end_matches('/');
         let path = path.trim_start_matches('/');
         if path.is_empty() {
-            return base.to_string();
+            return Ok(self.base_url.as_str().trim_end_matches('/').to_string());
         }
-        format!("{}/{}", base, path)
+        // "./" keeps a leading segment like "a:b" from being read as a URL scheme.
+        let url = self
+            .base_url
+            .join(&format!("./{path}"))
+            .with_context(|| format!("invalid request path: {path}"))?;
+        Ok(url.into())
     }
 
     pub fn request(
@@ -51,45 +548,417 @@ impl PinterestClient {
         query: &[(String, String)],
         body: Option<Body>,
     ) -> Result<Value> {
+        self.request_with_headers(method, url, auth, query, body)
+            .map(|(value, _)| value)
+    }
+
+    /// Like [`request`](Self::request), also returning the response headers
+    /// (e.g. `Link` for header-based pagination).
+    pub fn request_with_headers(
+        &self,
+        method: &str,
+        url: &str,
+        auth: &Auth,
+        query: &[(String, String)],
+        body: Option<Body>,
+    ) -> Result<(Value, HeaderMap)> {
+        self.request_response(method, url, auth, query, body)
+            .map(|resp| (resp.body, resp.headers))
+    }
+
+    /// Like [`request`](Self::request), returning the status and headers too.
+    pub fn request_response(
+        &self,
+        method: &str,
+        url: &str,
+        auth: &Auth,
+        query: &[(String, String)],
+        body: Option<Body>,
+    ) -> Result<ApiResponse> {
+        let Some(cache) = &self.cache else {
+            return self.shared_fetch(method, url, auth, query, body);
+        };
+        if method == "HEAD" {
+            // Neither a cacheable read nor a write.
+            return self.shared_fetch(method, url, auth, query, body);
+        }
+        if method != "GET" || body.is_some() {
+            // Even a failed write may have landed.
+            cache.invalidate(url);
+            return self.shared_fetch(method, url, auth, query, body);
+        }
+        let full_url = query::append_to_url(url, query);
+        let key = ResponseCache::key(&[
+            method,
+            &full_url,
+            &format!("{auth:?}"),
+            &format!("{:?}", self.request_headers()),
+        ]);
+        if let Some(resp) = cache.get(&key, &full_url) {
+            return Ok(resp);
+        }
+        let resp = self.shared_fetch(method, url, auth, query, None)?;
+        cache.put(&key, &full_url, &resp);
+        Ok(resp)
+    }
+
+    /// A GET whose response is never held in memory whole: each element of
+    /// its top-level array (or `items[]`) goes to `on_item` as it is parsed,
+    /// see [`json_stream::for_each_item`]. Errors and retries are handled as
+    /// for [`request`](Self::request) until the body starts arriving; the
+    /// cache and deduping are bypassed. Returns the number of items.
+    pub fn stream_items(
+        &self,
+        url: &str,
+        auth: &Auth,
+        query: &[(String, String)],
+        mut on_item: impl FnMut(Value) -> Result<()>,
+    ) -> Result<u64> {
+        let mut seen = 0;
+        let mut read = |status: StatusCode, body: &mut dyn Read| {
+            if !status.is_success() {
+                return read_text(status, body);
+            }
+            seen = json_stream::for_each_item(body, &mut on_item)?;
+            Ok(String::new())
+        };
+        self.fetch("GET", url, auth, query, None, &mut read)?;
+        Ok(seen)
+    }
+
+    /// [`fetch`](Self::fetch), coalesced with identical in-flight GETs when
+    /// deduping is on.
+    fn shared_fetch(
+        &self,
+        method: &str,
+        url: &str,
+        auth: &Auth,
+        query: &[(String, String)],
+        body: Option<Body>,
+    ) -> Result<ApiResponse> {
+        let Some(in_flight) = self
+            .in_flight
+            .as_ref()
+            .filter(|_| method == "GET" && body.is_none())
+        else {
+            return self.fetch(method, url, auth, query, body, &mut read_text);
+        };
+        let full_url = query::append_to_url(url, query);
+        let key = format!("{full_url}\n{auth:?}\n{:?}", self.request_headers());
+        let mut ran = false;
+        let resp = in_flight.run(key, || {
+            ran = true;
+            self.fetch(method, url, auth, query, None, &mut read_text)
+        })?;
+        if !ran {
+            log::debug!("GET {full_url}: shared an identical in-flight response");
+        }
+        Ok(resp)
+    }
+
+    fn fetch(
+        &self,
+        method: &str,
+        url: &str,
+        auth: &Auth,
+        query: &[(String, String)],
+        body: Option<Body>,
+        read: &mut dyn FnMut(StatusCode, &mut dyn Read) -> Result<String>,
+    ) -> Result<ApiResponse> {
+        let mut rate_limited = 0;
+        let mut not_found = 0;
+        let mut gateway = 0;
+        let mut body_retries = 0;
+        let retries_method = self.retry.methods.contains(method);
+        let mut auth = self
+            .renewed_auth(auth, false)
+            .unwrap_or_else(|| auth.clone());
+        let (status, headers, text) = loop {
+            let started = Instant::now();
+            let sent = self.send(method, url, &auth, query, body.as_ref(), read);
+            let elapsed = started.elapsed();
+            let outcome = || sent.as_ref().map(|(status, _, _)| *status);
+            match outcome() {
+                Ok(status) => log::debug!("{method} {url}: {status} in {elapsed:.1?}"),
+                Err(_) => log::debug!("{method} {url}: no response after {elapsed:.1?}"),
+            }
+            if let Some(log) = &self.request_log {
+                log.record(method, url, query, outcome(), elapsed);
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.record(outcome(), elapsed);
+            }
+            let (status, headers, text) = sent?;
+            if let Some(adaptive) = &self.adaptive {
+                adaptive.observe(status, &headers);
+            }
+            if status == StatusCode::UNAUTHORIZED
+                && serde_json::from_str(&text)
+                    .ok()
+                    .and_then(|body| AuthFailure::classify(status.as_u16(), &body))
+                    .is_none_or(AuthFailure::refreshable)
+                && let Some(renewed) = self.renewed_auth(&auth, true)
+            {
+                auth = renewed;
+                continue;
+            }
+            // Each kind of retry keeps its own count; the first that applies wins.
+            // A 429 was refused before any work, so it is safe to resend
+            // whatever the method; the rest only for `--retry-methods`.
+            let retry = if status == StatusCode::TOO_MANY_REQUESTS {
+                rate_limited += 1;
+                Some(("429", rate_limited, self.retry))
+            } else if matches!(status.as_u16(), 502..=504) && retries_method {
+                gateway += 1;
+                Some((status.as_str(), gateway, self.retry))
+            } else if status == StatusCode::NOT_FOUND && retries_method {
+                not_found += 1;
+                Some(("404", not_found, self.not_found))
+            } else if status.is_success()
+                && (retries_method || self.retry_if.any_method)
+                && self.retry_if.matches(&text)
+            {
+                body_retries += 1;
+                Some(("--retry-if matched", body_retries, self.retry_if_policy))
+            } else {
+                None
+            };
+            if let Some((reason, count, policy)) = retry
+                && policy.allows(count)
+                && !cancel::is_cancelled()
+                && self.retry_budget.as_ref().is_none_or(|b| b.try_spend())
+            {
+                let delay = policy.delay(count, retry::retry_after(&headers));
+                log::debug!(
+                    "{reason} for {url}; retry {count}/{} in {delay:?}",
+                    policy.max_retries
+                );
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_retry(delay);
+                }
+                sleep(delay);
+                continue;
+            }
+            if retry.is_some() && body_retries > self.retry_if.max {
+                log::warn!(
+                    "--retry-if still matched for {url} after {} retries",
+                    self.retry_if.max
+                );
+            }
+            break (status, headers, text);
+        };
+
+        if status == StatusCode::URI_TOO_LONG {
+            let len = query::append_to_url(url, query).len();
+            return Err(anyhow!(
+                "http {status}: request URL was {len} bytes; shrink the filters or, if the endpoint accepts POST, retry with --params-in-body"
+            ));
+        }
+        if status.is_redirection() {
+            // Only reached when redirects aren't followed (--max-redirects 0).
+            let location = headers.get(LOCATION).and_then(|v| v.to_str().ok());
+            let body = serde_json::json!({ "status": status.as_u16(), "location": location });
+            return Ok(ApiResponse {
+                status,
+                headers,
+                body,
+            });
+        }
+        if method == "HEAD" {
+            return head_response(status, headers);
+        }
+        if text.trim().is_empty() {
+            if status.is_success() {
+                return Ok(ApiResponse {
+                    status,
+                    headers,
+                    body: Value::Null,
+                });
+            }
+            return Err(anyhow!("http {}: empty response", status));
+        }
+        let value: Value = serde_json::from_str(&text).context("decode json")?;
+        if !status.is_success() {
+            log::debug!("error response body: {text}");
+            return Err(CliError::Api {
+                status: status.as_u16(),
+                reason: status.canonical_reason().unwrap_or("").to_string(),
+                body: value,
+                required_scopes: self.required_scopes.clone(),
+            }
+            .into());
+        }
+        Ok(ApiResponse {
+            status,
+            headers,
+            body: value,
+        })
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        url: &str,
+        auth: &Auth,
+        query: &[(String, String)],
+        body: Option<&Body>,
+        read: &mut dyn FnMut(StatusCode, &mut dyn Read) -> Result<String>,
+    ) -> Result<(StatusCode, HeaderMap, String)> {
+        let full_url = query::append_to_url(url, query);
+        if full_url.len() > MAX_SAFE_URL_LEN {
+            log::warn!(
+                "request URL is {} bytes; servers often reject URLs over ~8KB (HTTP 414), consider --params-in-body",
+                full_url.len()
+            );
+        }
         let mut request = match method {
-            "GET" => self.client.get(url),
-            "POST" => self.client.post(url),
-            "PATCH" => self.client.patch(url),
-            "PUT" => self.client.put(url),
-            "DELETE" => self.client.delete(url),
+            "GET" => self.client.get(&full_url),
+            "POST" => self.client.post(&full_url),
+            "PATCH" => self.client.patch(&full_url),
+            "PUT" => self.client.put(&full_url),
+            "DELETE" => self.client.delete(&full_url),
+            "HEAD" => self.client.head(&full_url),
             other => return Err(anyhow!("unsupported method {other}")),
         };
+        if let Some(timeout) = self.request_timeout {
+            request = request.timeout(timeout);
+        }
 
-        request = apply_auth(request, auth)?;
-        if !query.is_empty() {
-            request = request.query(query);
+        if !self.send_auth {
+            // without_auth: nothing to attach.
+        } else if self.headers.contains_key(AUTHORIZATION) {
+            log::debug!("using --header Authorization instead of computed auth");
+        } else {
+            request = apply_auth(request, auth)?;
         }
 
         request = match (method, body) {
-            ("GET" | "DELETE", Some(_)) => {
+            ("GET" | "DELETE" | "HEAD", Some(_)) => {
                 return Err(anyhow!("request body not supported for {method}"));
             }
             (_, None) => request,
-            (_, Some(Body::Json(value))) => request.json(&value),
-            (_, Some(Body::Form(fields))) => request.form(&fields),
+            (_, Some(Body::Json(value))) if self.gzip_body => request
+                .header(CONTENT_TYPE, "application/json")
+                .header(CONTENT_ENCODING, "gzip")
+                .body(gzip_json(value)?),
+            (_, Some(Body::Json(value))) => request.json(value),
+            (_, Some(Body::Form(fields))) => request.form(fields),
+            (_, Some(Body::JsonFile(file))) => {
+                // Reopened per attempt so retries resend the whole file. The
+                // size is set up front so it goes out with Content-Length,
+                // never chunked, which some endpoints reject.
+                let f = File::open(&file.path)
+                    .with_context(|| format!("open {}", file.path.display()))?;
+                request
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(reqwest::blocking::Body::sized(f, file.size()?))
+            }
         };
 
+        if !self.param_headers.is_empty() {
+            request = request.headers(self.param_headers.clone());
+        }
+        if !self.headers.is_empty() {
+            request = request.headers(self.headers.clone());
+        }
+
+        if let Some(fixtures) = self.fixtures.as_ref().filter(|f| f.replaying()) {
+            let (status, headers, body) = fixtures.response(method, &full_url)?;
+            let text = read(status, &mut body.as_slice())?;
+            return Ok((status, headers, text));
+        }
         log::debug!("request {} {}", method, url);
-        let resp = request.send().context("send request")?;
+        let mut resp = request
+            .send()
+            .map_err(|err| anyhow!("send request: {err}"))?;
         let status = resp.status();
-        let text = resp.text().context("read response body")?;
-        if text.trim().is_empty() {
-            if status.is_success() {
-                return Ok(Value::Null);
+        let headers = resp.headers().clone();
+        let text = match &self.fixtures {
+            Some(fixtures) => {
+                let mut tee = Tee::new(resp);
+                let text = read(status, &mut tee)?;
+                if let Some(copy) = tee.into_copy() {
+                    fixtures.save(method, &full_url, status, &headers, copy);
+                }
+                text
+            }
+            None => read(status, &mut resp)?,
+        };
+        Ok((status, headers, text))
+    }
+}
+
+/// The whole response body, for [`PinterestClient::fetch`]'s `read`.
+fn read_text(_status: StatusCode, body: &mut dyn Read) -> Result<String> {
+    let mut bytes = Vec::new();
+    body.read_to_end(&mut bytes).context("read response body")?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// A HEAD response has no body, so its result is `{status, headers}` (header
+/// names lower-case, repeated ones joined with `, `). A non-2xx is still an
+/// error, so `raw HEAD` works as an existence check.
+fn head_response(status: StatusCode, headers: HeaderMap) -> Result<ApiResponse> {
+    let mut fields = serde_json::Map::new();
+    for (name, value) in &headers {
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        match fields.get_mut(name.as_str()) {
+            Some(Value::String(existing)) => {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            }
+            _ => {
+                fields.insert(name.to_string(), Value::String(value));
             }
-            return Err(anyhow!("http {}: empty response", status));
         }
-        let value: Value = serde_json::from_str(&text).context("decode json")?;
-        if !status.is_success() {
-            return Err(anyhow!("http {}: {}", status, value));
+    }
+    if !status.is_success() {
+        return Err(CliError::Api {
+            status: status.as_u16(),
+            reason: status.canonical_reason().unwrap_or("").to_string(),
+            body: Value::Null,
+            required_scopes: Vec::new(),
         }
-        Ok(value)
+        .into());
+    }
+    let body = serde_json::json!({ "status": status.as_u16(), "headers": fields });
+    Ok(ApiResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+/// `value` serialized and gzipped, for [`PinterestClientBuilder::gzip_body`].
+fn gzip_json(value: &Value) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, value).context("serialize body")?;
+    encoder.finish().context("gzip body")
+}
+
+/// A form body as it goes on the wire (`a=1&b=x+y`), the same encoding
+/// `Body::Form` is sent with.
+pub fn encode_form(fields: &[(String, String)]) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(fields)
+        .finish()
+}
+
+/// Parses repeated `Name: value` arguments. A name given more than once is
+/// sent as multiple header lines, which HTTP treats as a comma-joined list.
+pub fn parse_headers<'a>(values: impl IntoIterator<Item = &'a String>) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for raw in values {
+        let (name, value) = raw
+            .split_once(':')
+            .ok_or_else(|| anyhow!("invalid header {raw:?}; expected 'Name: value'"))?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .with_context(|| format!("invalid header name in {raw:?}"))?;
+        let value = HeaderValue::from_str(value.trim())
+            .with_context(|| format!("invalid header value in {raw:?}"))?;
+        headers.append(name, value);
     }
+    Ok(headers)
 }
 
 fn apply_auth(mut req: RequestBuilder, auth: &Auth) -> Result<RequestBuilder> {
@@ -103,3 +972,230 @@ fn apply_auth(mut req: RequestBuilder, auth: &Auth) -> Result<RequestBuilder> {
         Auth::Basic { username, password } => Ok(req.basic_auth(username, Some(password))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+    use serde_json::json;
+
+    #[test]
+    fn build_url_joins_paths_under_the_base_path() {
+        for base in ["https://api.example.com/v5", "https://api.example.com/v5/"] {
+            let client = PinterestClient::new(base).unwrap();
+            for path in ["/ad_accounts/1", "ad_accounts/1", "//ad_accounts/1"] {
+                assert_eq!(
+                    client.build_url(path).unwrap(),
+                    "https://api.example.com/v5/ad_accounts/1",
+                    "{base} + {path}"
+                );
+            }
+            assert_eq!(client.build_url("/").unwrap(), "https://api.example.com/v5");
+            assert_eq!(client.build_url("").unwrap(), "https://api.example.com/v5");
+        }
+    }
+
+    #[test]
+    fn build_url_keeps_deep_base_paths_and_trailing_slashes() {
+        let client = PinterestClient::new("http://localhost:8080/proxy/pinterest/v5//").unwrap();
+        assert_eq!(
+            client.build_url("/boards/").unwrap(),
+            "http://localhost:8080/proxy/pinterest/v5//boards/"
+        );
+        let client = PinterestClient::new("http://localhost:8080").unwrap();
+        assert_eq!(
+            client.build_url("/user_account").unwrap(),
+            "http://localhost:8080/user_account"
+        );
+        assert_eq!(client.build_url("/").unwrap(), "http://localhost:8080");
+    }
+
+    #[test]
+    fn build_url_reads_colons_and_queries_as_part_of_the_path() {
+        let client = PinterestClient::new("https://api.example.com/v5").unwrap();
+        assert_eq!(
+            client.build_url("/catalogs:items?x=1").unwrap(),
+            "https://api.example.com/v5/catalogs:items?x=1"
+        );
+        assert_eq!(
+            client.build_url("urn:li:1").unwrap(),
+            "https://api.example.com/v5/urn:li:1"
+        );
+    }
+
+    #[test]
+    fn build_url_passes_absolute_urls_through() {
+        let client = PinterestClient::new("https://api.example.com/v5").unwrap();
+        for url in [
+            "https://api.example.com/v5/next?bookmark=abc",
+            "https://uploads.example.net/media/1",
+            "http://localhost:9000/x",
+        ] {
+            assert_eq!(client.build_url(url).unwrap(), url);
+        }
+    }
+
+    #[test]
+    fn gzip_body_sends_a_compressed_json_body() {
+        let server = TestServer::start(|_| Response::json(200, &json!({"ok": true})));
+        let client = PinterestClient::builder(server.url())
+            .gzip_body(true)
+            .build()
+            .unwrap();
+        let body = json!({"data": [{"event_name": "checkout", "value": 1.5}]});
+        let url = client.build_url("/events").unwrap();
+        let response = client
+            .request(
+                "POST",
+                &url,
+                &Auth::Bearer("t".into()),
+                &[],
+                Some(Body::Json(body.clone())),
+            )
+            .unwrap();
+        assert_eq!(response, json!({"ok": true}));
+
+        let [request] = server.requests().try_into().unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/events")
+        );
+        assert_eq!(request.header("content-encoding"), Some("gzip"));
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        let mut sent = String::new();
+        flate2::read::GzDecoder::new(request.body.as_slice())
+            .read_to_string(&mut sent)
+            .unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&sent).unwrap(), body);
+    }
+
+    #[test]
+    fn json_file_bodies_go_out_sized_not_chunked() {
+        let server = TestServer::start(|_| Response::json(200, &json!({})));
+        let client = PinterestClient::new(server.url()).unwrap();
+        let mut temp = tempfile::NamedTempFile::new().unwrap();
+        let text = format!("[{}]", vec!["{\"id\":\"1\"}"; 5000].join(","));
+        std::io::Write::write_all(&mut temp, text.as_bytes()).unwrap();
+        let file = crate::sources::resolve_source(
+            temp.path().to_str().unwrap(),
+            &crate::sources::SourceOptions::default(),
+        )
+        .unwrap();
+        let url = client.build_url("/bulk").unwrap();
+        let body = Body::JsonFile(Arc::new(file));
+        client
+            .request("POST", &url, &Auth::Bearer("t".into()), &[], Some(body))
+            .unwrap();
+
+        let [request] = server.requests().try_into().unwrap();
+        assert_eq!(
+            request.header("content-length"),
+            Some(text.len().to_string().as_str())
+        );
+        assert_eq!(request.header("transfer-encoding"), None);
+        assert_eq!(request.body, text.as_bytes());
+    }
+
+    fn header_args(values: &[&str]) -> HeaderMap {
+        let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        parse_headers(&values).unwrap()
+    }
+
+    fn sent_values(request: &crate::test_server::Request, name: &str) -> Vec<String> {
+        request
+            .headers
+            .iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
+            .collect()
+    }
+
+    #[test]
+    fn parse_headers_appends_repeated_names() {
+        let headers = header_args(&["X-Tag: a", "x-tag:b ", "Accept-Language: fr"]);
+        let tags: Vec<_> = headers.get_all("x-tag").iter().collect();
+        assert_eq!(tags, ["a", "b"]);
+        assert_eq!(headers.len(), 3);
+        assert!(parse_headers(&["no colon".to_string()]).is_err());
+        assert!(parse_headers(&["Bad Name: x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn repeated_headers_are_sent_once_per_value() {
+        let server = TestServer::start(|_| Response::json(200, &json!({})));
+        let client = PinterestClient::builder(server.url())
+            .headers(header_args(&["X-Tag: a", "X-Tag: b"]))
+            .build()
+            .unwrap();
+        let url = client.build_url("/x").unwrap();
+        client
+            .request("GET", &url, &Auth::Bearer("t".into()), &[], None)
+            .unwrap();
+        let [request] = server.requests().try_into().unwrap();
+        assert_eq!(sent_values(&request, "x-tag"), ["a", "b"]);
+    }
+
+    #[test]
+    fn header_flags_replace_header_params_of_the_same_name() {
+        let server = TestServer::start(|_| Response::json(200, &json!({})));
+        let client = PinterestClient::builder(server.url())
+            .headers(header_args(&["X-Mode: flag"]))
+            .build()
+            .unwrap()
+            .with_param_headers(header_args(&["X-Mode: param", "X-Other: kept"]));
+        assert_eq!(
+            client.request_headers(),
+            header_args(&["X-Other: kept", "X-Mode: flag"])
+        );
+        let url = client.build_url("/x").unwrap();
+        client
+            .request("GET", &url, &Auth::Bearer("t".into()), &[], None)
+            .unwrap();
+        let [request] = server.requests().try_into().unwrap();
+        assert_eq!(sent_values(&request, "x-mode"), ["flag"]);
+        assert_eq!(sent_values(&request, "x-other"), ["kept"]);
+    }
+
+    #[test]
+    fn an_explicit_authorization_header_replaces_computed_auth() {
+        let server = TestServer::start(|_| Response::json(200, &json!({})));
+        let client = PinterestClient::builder(server.url())
+            .headers(header_args(&["Authorization: Basic cHJveHk6cw=="]))
+            .build()
+            .unwrap();
+        let url = client.build_url("/x").unwrap();
+        for auth in [
+            Auth::Bearer("t".into()),
+            Auth::Basic {
+                username: "u".into(),
+                password: "p".into(),
+            },
+        ] {
+            client.request("GET", &url, &auth, &[], None).unwrap();
+        }
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        for request in requests {
+            assert_eq!(
+                sent_values(&request, "authorization"),
+                ["Basic cHJveHk6cw=="]
+            );
+        }
+    }
+
+    #[test]
+    fn large_numeric_ids_come_back_digit_for_digit() {
+        let text = r#"{"id":5493217700000000123,"above_u64":123456789012345678901234567890,"spend":1.10,"tiny":1e-7}"#;
+        let server = TestServer::start(move |_| {
+            Response::new(200, text).header("Content-Type", "application/json")
+        });
+        let client = PinterestClient::new(server.url()).unwrap();
+        let url = client.build_url("/ads/5493217700000000123").unwrap();
+        let value = client
+            .request("GET", &url, &Auth::Bearer("t".into()), &[], None)
+            .unwrap();
+        assert_eq!(value.to_string(), text);
+        assert_eq!(value["id"].to_string(), "5493217700000000123");
+        assert_eq!(value["id"].as_u64(), Some(5493217700000000123));
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+
+/// Coalesces identical calls made at the same time: the first caller for a key
+/// runs it and later callers wait for and share its result. Only successes
+/// are shared; when the first call fails each waiter runs its own, so an
+/// error is always reported by the call that hit it. Nothing is cached once
+/// the call completes.
+#[derive(Debug)]
+pub struct InFlight<T> {
+    calls: Mutex<HashMap<String, Arc<Call<T>>>>,
+}
+
+#[derive(Debug)]
+struct Call<T> {
+    /// `None` while running; then `Some(None)` when the call failed.
+    result: Mutex<Option<Option<T>>>,
+    done: Condvar,
+}
+
+impl<T: Clone> InFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn run(&self, key: String, call: impl FnOnce() -> Result<T>) -> Result<T> {
+        let (shared, leader) = {
+            let mut calls = self.calls.lock().unwrap_or_else(PoisonError::into_inner);
+            match calls.get(&key) {
+                Some(shared) => (Arc::clone(shared), false),
+                None => {
+                    let shared = Arc::new(Call {
+                        result: Mutex::new(None),
+                        done: Condvar::new(),
+                    });
+                    calls.insert(key.clone(), Arc::clone(&shared));
+                    (shared, true)
+                }
+            }
+        };
+
+        if !leader {
+            let result = shared.result.lock().unwrap_or_else(PoisonError::into_inner);
+            let result = shared
+                .done
+                .wait_while(result, |r| r.is_none())
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some(Some(value)) = result.as_ref() {
+                return Ok(value.clone());
+            }
+            drop(result);
+            return call();
+        }
+
+        // Publishes on drop so waiters are released even if `call` panics.
+        let mut guard = Publish {
+            owner: self,
+            key,
+            shared,
+            value: None,
+        };
+        let result = call();
+        guard.value = result.as_ref().ok().cloned();
+        result
+    }
+}
+
+impl<T: Clone> Default for InFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Publish<'a, T> {
+    owner: &'a InFlight<T>,
+    key: String,
+    shared: Arc<Call<T>>,
+    value: Option<T>,
+}
+
+impl<T> Drop for Publish<'_, T> {
+    fn drop(&mut self) {
+        self.owner
+            .calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.key);
+        let mut result = self
+            .shared
+            .result
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *result = Some(self.value.take());
+        self.shared.done.notify_all();
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::BTreeMap;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -8,6 +9,9 @@ pub struct CommandTree {
     pub api_version: String,
     pub base_url: String,
     pub resources: Vec<Resource>,
+    /// OpenAPI component schemas referenced (via `$ref`) by request and response bodies.
+    #[serde(default)]
+    pub schemas: BTreeMap<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -26,9 +30,36 @@ pub struct Operation {
     pub summary: Option<String>,
     pub tags: Vec<String>,
     pub paginated: bool,
+    /// Response property holding the result list (`items`, `data`), if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_key: Option<String>,
+    #[serde(default)]
+    pub deprecated: bool,
+    /// Suggested replacement (`resource op`) for a deprecated operation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    /// Set for non-DELETE ops that still need confirmation (e.g. archiving PATCHes).
+    #[serde(default)]
+    pub destructive: bool,
     pub security: Vec<BTreeMap<String, Vec<String>>>,
     pub params: Vec<ParamDef>,
     pub request_body: Option<RequestBodyDef>,
+    /// Default request timeout in seconds for slow endpoints (media, reports);
+    /// takes precedence over the client-wide timeout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Property names of the result (of each list item for list ops) in
+    /// response schema order; used as `--csv` columns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_fields: Option<Vec<String>>,
+    /// The 2xx statuses the spec documents; another 2xx gets a warning (an
+    /// error under `--strict`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success_codes: Option<Vec<u16>>,
+    /// The 2xx response body schema (`$ref`s into [`CommandTree::schemas`]),
+    /// for `--validate-response`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -43,6 +74,17 @@ pub struct ParamDef {
     pub explode: Option<bool>,
     pub schema_type: String,
     pub items_type: Option<String>,
+    #[serde(default)]
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub minimum: Option<f64>,
+    #[serde(default)]
+    pub maximum: Option<f64>,
+    #[serde(default)]
+    pub example: Option<Value>,
+    /// Known top-level keys of a deepObject param; `None` when unknown or open-ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -50,9 +92,20 @@ pub struct ParamDef {
 pub struct RequestBodyDef {
     pub required: bool,
     pub content_types: Vec<String>,
+    #[serde(default)]
+    pub schema: Option<Value>,
 }
 
 pub fn load_command_tree() -> CommandTree {
     let raw = include_str!("../schemas/command_tree.json");
     serde_json::from_str(raw).expect("invalid schemas/command_tree.json")
 }
+
+/// Loads a command tree generated by `tools/gen_command_tree.py` from disk,
+/// e.g. one built from a newer or beta spec.
+pub fn load_command_tree_from(path: &std::path::Path) -> anyhow::Result<CommandTree> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("read command tree {}: {err}", path.display()))?;
+    serde_json::from_str(&raw)
+        .map_err(|err| anyhow::anyhow!("invalid command tree {}: {err}", path.display()))
+}
@@ -0,0 +1,653 @@
+use anyhow::{Context, Result, anyhow};
+use directories::ProjectDirs;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use pinterest_ads::client::{HttpVersion, PoolOptions};
+use pinterest_ads::command_tree::{self, CommandTree};
+use pinterest_ads::s3::{self, S3Options};
+use pinterest_ads::sources::{self, InputFormat, SourceOptions};
+
+use crate::session::{self, Session};
+
+pub struct Config {
+    pub base_url: String,
+    pub access_token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub conversion_token: Option<String>,
+    pub ad_account_id: Option<String>,
+    /// Path param values used when the flag is absent, by param name.
+    pub path_defaults: BTreeMap<String, String>,
+    pub default_raw_output: bool,
+    /// External command tree path; `None` means the embedded tree.
+    pub command_tree: Option<String>,
+    /// Directory of `resource_op.json` request body templates.
+    pub template_dir: Option<String>,
+    pub timeout: Option<u64>,
+    pub http_version: HttpVersion,
+    pub pool: PoolOptions,
+    pub sources: SourceOptions,
+    pub policy: OperationPolicy,
+    /// The `auth login` session the access token came from, if it did.
+    pub session: Option<(PathBuf, Session)>,
+    /// Where each credential/setting key was resolved from (`None` when unset).
+    pub origins: Vec<(&'static str, Option<String>)>,
+}
+
+/// Settings read from the config file; every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub base_url: Option<String>,
+    pub access_token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub conversion_token: Option<String>,
+    pub ad_account_id: Option<String>,
+    /// `[path_defaults]`: values for path params (e.g. `campaign_id`) used when
+    /// their flag is absent. Merged key by key across layers.
+    #[serde(default)]
+    pub path_defaults: BTreeMap<String, String>,
+    /// `[environments]`: base URLs selectable with `--env NAME`, added to (or
+    /// replacing) the builtin `production` and `sandbox`.
+    #[serde(default)]
+    pub environments: BTreeMap<String, String>,
+    /// Print full responses by default, as if `--raw` were always passed.
+    pub default_raw_output: Option<bool>,
+    /// Only these operations may run: `resource op`, a whole `resource`, or an
+    /// HTTP method such as `GET`.
+    pub allowed_operations: Option<Vec<String>>,
+    /// Operations that may never run (same forms); checked before the allowlist.
+    pub denied_operations: Option<Vec<String>>,
+    /// Generated command tree JSON to build the CLI from instead of the embedded one.
+    /// Relative paths resolve against the config file's directory.
+    pub command_tree: Option<String>,
+    /// Directory of per-operation body templates (`resource_op.json`).
+    /// Relative paths resolve against the config file's directory.
+    pub template_dir: Option<String>,
+    /// Named `[profiles.NAME]` tables selected with `--profile`; their keys
+    /// override the top-level ones.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, FileConfig>,
+}
+
+/// Which operations the config lets this install run. Entries are
+/// `resource op` (e.g. `campaigns delete`), a resource name (every op of it,
+/// `raw` for raw requests) or an upper-case HTTP method.
+#[derive(Debug, Clone, Default)]
+pub struct OperationPolicy {
+    pub allowed: Option<(Vec<String>, String)>,
+    pub denied: Option<(Vec<String>, String)>,
+}
+
+impl OperationPolicy {
+    pub fn check(&self, resource: &str, op: &str, method: &str) -> Result<()> {
+        let matches = |entry: &String| {
+            let entry = entry.trim();
+            match entry.split_once(char::is_whitespace) {
+                Some((res, name)) => res == resource && name.trim() == op,
+                None => entry == resource || entry == method,
+            }
+        };
+        if let Some((denied, origin)) = &self.denied
+            && denied.iter().any(matches)
+        {
+            return Err(anyhow!(
+                "{resource} {op} ({method}) is blocked by denied_operations ({origin})"
+            ));
+        }
+        if let Some((allowed, origin)) = &self.allowed
+            && !allowed.iter().any(matches)
+        {
+            return Err(anyhow!(
+                "{resource} {op} ({method}) is not in allowed_operations ({origin})"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Per-OS default: `$XDG_CONFIG_HOME/pinterest-ads/config.toml` on Linux,
+/// `~/Library/Application Support/pinterest-ads/config.toml` on macOS and
+/// `%APPDATA%\pinterest-ads\config\config.toml` on Windows.
+pub fn default_config_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "pinterest-ads").map(|dirs| dirs.config_dir().join("config.toml"))
+}
+
+/// The config file, the selected profile within it and the `--config-stdin`
+/// object; later layers override earlier ones key by key.
+#[derive(Debug, Default)]
+pub struct FileLayers {
+    pub stdin: FileConfig,
+    pub profile: Option<(String, FileConfig)>,
+    pub file: FileConfig,
+    pub path: Option<PathBuf>,
+}
+
+impl FileLayers {
+    fn get<T: Clone>(&self, key: fn(&FileConfig) -> &Option<T>) -> Option<(T, String)> {
+        if let Some(value) = key(&self.stdin) {
+            return Some((value.clone(), "--config-stdin".to_string()));
+        }
+        let path = self.path.as_ref().map(|p| p.display().to_string());
+        let path = path.unwrap_or_default();
+        if let Some((name, profile)) = &self.profile
+            && let Some(value) = key(profile)
+        {
+            return Some((
+                value.clone(),
+                format!("profile {name} in config file {path}"),
+            ));
+        }
+        let value = key(&self.file).clone()?;
+        Some((value, format!("config file {path}")))
+    }
+
+    /// A table such as `path_defaults` merged key by key (stdin over profile
+    /// over file), each value with its origin.
+    fn merged(
+        &self,
+        table: fn(&FileConfig) -> &BTreeMap<String, String>,
+    ) -> BTreeMap<String, (String, String)> {
+        let path = self.path.as_ref().map(|p| p.display().to_string());
+        let path = path.unwrap_or_default();
+        let mut layers = vec![(&self.file, format!("config file {path}"))];
+        if let Some((name, profile)) = &self.profile {
+            layers.push((profile, format!("profile {name} in config file {path}")));
+        }
+        layers.push((&self.stdin, "--config-stdin".to_string()));
+        let mut out = BTreeMap::new();
+        for (layer, origin) in layers {
+            for (key, value) in table(layer) {
+                out.insert(key.clone(), (value.clone(), origin.clone()));
+            }
+        }
+        out
+    }
+
+    /// Resolves a path-valued key, relative to the config file's directory.
+    fn get_path(&self, key: fn(&FileConfig) -> &Option<String>) -> Option<(String, String)> {
+        let (value, origin) = self.get(key)?;
+        let dir = self.path.as_deref().and_then(Path::parent);
+        let value = match dir {
+            Some(dir) if Path::new(&value).is_relative() && !origin.starts_with("--") => {
+                dir.join(&value).display().to_string()
+            }
+            _ => value,
+        };
+        Some((value, origin))
+    }
+}
+
+/// Reads the file config, the selected profile and the `--config-stdin` object, if given.
+pub fn load_file_config(matches: &clap::ArgMatches) -> Result<FileLayers> {
+    let explicit = matches
+        .get_one::<String>("config")
+        .cloned()
+        .or_else(|| env_value(matches, "PINTEREST_CONFIG"));
+    let profile = matches
+        .get_one::<String>("profile")
+        .cloned()
+        .or_else(|| env_value(matches, "PINTEREST_PROFILE"));
+    let mut layers = read_file_layers(explicit, profile)?;
+    if !matches.get_flag("config_stdin") {
+        return Ok(layers);
+    }
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|err| anyhow!("read --config-stdin: {err}"))?;
+    // YAML is a superset of JSON, so one parser covers both.
+    let stdin: FileConfig = serde_yaml::from_str(&text)
+        .map_err(|err| anyhow!("invalid --config-stdin object: {err}"))?;
+    // The command tree is loaded before stdin is read, so these only work from the file.
+    if stdin.command_tree.is_some() || !stdin.profiles.is_empty() {
+        return Err(anyhow!(
+            "command_tree and profiles must be set in the config file, not --config-stdin"
+        ));
+    }
+    layers.stdin = stdin;
+    Ok(layers)
+}
+
+/// Reads the config file and picks out `profile`, which must exist when named.
+fn read_file_layers(explicit: Option<String>, profile: Option<String>) -> Result<FileLayers> {
+    let (mut file, path) = read_config_file(explicit.map(PathBuf::from))?;
+    let profile = match profile {
+        Some(name) => {
+            let selected = file.profiles.remove(&name).ok_or_else(|| match &path {
+                Some(path) => anyhow!("profile {name} not found in {}", path.display()),
+                None => anyhow!("profile {name} requires a config file"),
+            })?;
+            if !selected.profiles.is_empty() {
+                return Err(anyhow!("profile {name} cannot define nested profiles"));
+            }
+            Some((name, selected))
+        }
+        None => None,
+    };
+    Ok(FileLayers {
+        profile,
+        file,
+        path,
+        ..FileLayers::default()
+    })
+}
+
+/// Reads `--config`/`PINTEREST_CONFIG` (which must exist) or the default path (optional).
+fn read_config_file(explicit: Option<PathBuf>) -> Result<(FileConfig, Option<PathBuf>)> {
+    let path = match explicit {
+        Some(path) => {
+            if !path.exists() {
+                return Err(anyhow!("config file not found: {}", path.display()));
+            }
+            path
+        }
+        None => match default_config_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok((FileConfig::default(), None)),
+        },
+    };
+
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("read config {}", path.display()))?;
+    let file =
+        toml::from_str(&text).with_context(|| format!("invalid config {}", path.display()))?;
+    Ok((file, Some(path)))
+}
+
+/// An environment variable's value, unless `--no-env` says to ignore them.
+pub fn env_value(matches: &clap::ArgMatches, name: &str) -> Option<String> {
+    if matches.get_flag("no_env") {
+        return None;
+    }
+    env::var(name).ok()
+}
+
+/// Resolves a setting: flag, then env var, then config file. Returns the value
+/// and where it came from.
+fn setting(
+    matches: &clap::ArgMatches,
+    arg: &str,
+    env_var: &str,
+    file: Option<(String, String)>,
+) -> Option<(String, String)> {
+    if let Some(value) = matches.get_one::<String>(arg) {
+        return Some((value.clone(), format!("flag --{}", arg.replace('_', "-"))));
+    }
+    if let Some(value) = env_value(matches, env_var) {
+        return Some((value, format!("env {env_var}")));
+    }
+    file
+}
+
+/// Like [`setting`] for secrets, also accepting `<arg>_file` / `<ENV>_FILE`
+/// pointing at a file that holds the value (e.g. a mounted k8s secret).
+/// Order: flag, file flag, env var, file env var, config file.
+fn secret_setting(
+    matches: &clap::ArgMatches,
+    arg: &str,
+    env_var: &str,
+    file: Option<(String, String)>,
+) -> Result<Option<(String, String)>> {
+    let flag = format!("--{}", arg.replace('_', "-"));
+    if let Some(value) = matches.get_one::<String>(arg) {
+        return Ok(Some((value.clone(), format!("flag {flag}"))));
+    }
+    if let Some(path) = matches.get_one::<String>(&format!("{arg}_file")) {
+        let value = read_secret_file(path)?;
+        return Ok(Some((value, format!("flag {flag}-file"))));
+    }
+    if let Some(value) = env_value(matches, env_var) {
+        return Ok(Some((value, format!("env {env_var}"))));
+    }
+    if let Some(path) = env_value(matches, &format!("{env_var}_FILE")) {
+        let value = read_secret_file(&path)?;
+        return Ok(Some((value, format!("env {env_var}_FILE"))));
+    }
+    Ok(file)
+}
+
+/// Loads the command tree the CLI is built from. Runs before argument parsing,
+/// so `--config`, `--profile` and `--command-tree` are picked out of the raw args.
+pub fn bootstrap_command_tree(args: &[String]) -> Result<CommandTree> {
+    let no_env = args.iter().any(|arg| arg == "--no-env");
+    let env_value = |name: &str| env::var(name).ok().filter(|_| !no_env);
+    let path = match raw_flag(args, "command-tree").or_else(|| env_value("PINTEREST_COMMAND_TREE"))
+    {
+        Some(path) => Some(path),
+        None => {
+            let explicit = raw_flag(args, "config").or_else(|| env_value("PINTEREST_CONFIG"));
+            let profile = raw_flag(args, "profile").or_else(|| env_value("PINTEREST_PROFILE"));
+            read_file_layers(explicit, profile)?
+                .get_path(|f| &f.command_tree)
+                .map(|(path, _)| path)
+        }
+    };
+    match path {
+        Some(path) => command_tree::load_command_tree_from(Path::new(&path)),
+        None => Ok(command_tree::load_command_tree()),
+    }
+}
+
+/// Host of the API's sandbox, which mirrors production's paths.
+const SANDBOX_HOST: &str = "api-sandbox.pinterest.com";
+
+/// Base URL of a named environment: `[environments]` in the config, else the
+/// builtin `production` (the command tree's URL) or `sandbox` (the same path
+/// on the sandbox host).
+fn environment_url(name: &str, tree: &CommandTree, file: &FileLayers) -> Result<String> {
+    let configured = file.merged(|f| &f.environments);
+    if let Some((url, _)) = configured.get(name) {
+        return Ok(url.clone());
+    }
+    match name {
+        "production" => Ok(tree.base_url.clone()),
+        "sandbox" => {
+            let mut url = url::Url::parse(&tree.base_url)
+                .with_context(|| format!("invalid base url: {}", tree.base_url))?;
+            url.set_host(Some(SANDBOX_HOST))
+                .map_err(|err| anyhow!("sandbox url: {err}"))?;
+            Ok(url.to_string().trim_end_matches('/').to_string())
+        }
+        _ => {
+            let mut known: Vec<&str> = vec!["production", "sandbox"];
+            known.extend(configured.keys().map(String::as_str));
+            known.sort_unstable();
+            known.dedup();
+            Err(anyhow!(
+                "unknown environment {name}; known: {}",
+                known.join(", ")
+            ))
+        }
+    }
+}
+
+/// Value of `--<long> VALUE` / `--<long>=VALUE` before any `--` separator.
+fn raw_flag(args: &[String], long: &str) -> Option<String> {
+    let flag = format!("--{long}");
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        if *arg == flag {
+            return iter.next().cloned();
+        }
+        if let Some(value) = arg.strip_prefix(&flag).and_then(|v| v.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" => Some(true),
+        "0" | "false" | "no" | "" => Some(false),
+        _ => None,
+    }
+}
+
+/// Trims the whitespace pasted tokens tend to carry (a trailing newline from
+/// `$(cat ...)`), then rejects what can't go in a header. The value itself is
+/// never echoed.
+fn credential(key: &str, resolved: Option<(String, String)>) -> Result<Option<(String, String)>> {
+    let Some((value, origin)) = resolved else {
+        return Ok(None);
+    };
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Err(anyhow!("{key} from {origin} is empty"));
+    }
+    if let Some(pos) = trimmed.find(|c: char| c.is_control()) {
+        return Err(anyhow!(
+            "{key} from {origin} contains a control character at position {pos}; check for a pasted line break"
+        ));
+    }
+    if let Some(pos) = trimmed.find(|c: char| !c.is_ascii()) {
+        return Err(anyhow!(
+            "{key} from {origin} contains a non-ASCII character at position {pos}"
+        ));
+    }
+    Ok(Some((trimmed.to_string(), origin)))
+}
+
+fn read_secret_file(path: &str) -> Result<String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|err| anyhow!("read secret file {path}: {err}"))?;
+    let value = text.trim();
+    if value.is_empty() {
+        return Err(anyhow!("secret file is empty: {path}"));
+    }
+    Ok(value.to_string())
+}
+
+pub fn load_config(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<Config> {
+    let file = load_file_config(matches)?;
+
+    let mut origins = Vec::new();
+    let mut record = |key: &'static str, resolved: Option<(String, String)>| {
+        let (value, origin) = resolved.unzip();
+        origins.push((key, origin));
+        value
+    };
+
+    // An explicit URL beats a named environment from the same place (flag or env).
+    let environment = |name: &str, origin: &str| {
+        environment_url(name, tree, &file).map(|url| (url, format!("{origin} {name}")))
+    };
+    let base_url = if let Some(url) = matches.get_one::<String>("base_url") {
+        Some((url.clone(), "flag --base-url".to_string()))
+    } else if let Some(name) = matches.get_one::<String>("env") {
+        Some(environment(name, "flag --env")?)
+    } else if let Some(url) = env_value(matches, "PINTEREST_BASE_URL") {
+        Some((url, "env PINTEREST_BASE_URL".to_string()))
+    } else if let Some(name) = env_value(matches, "PINTEREST_ENV") {
+        Some(environment(&name, "env PINTEREST_ENV")?)
+    } else {
+        file.get(|f| &f.base_url)
+    };
+    let base_url = record("base_url", base_url).unwrap_or_else(|| tree.base_url.clone());
+    let command_tree = record(
+        "command_tree",
+        setting(
+            matches,
+            "command_tree",
+            "PINTEREST_COMMAND_TREE",
+            file.get_path(|f| &f.command_tree),
+        ),
+    );
+    let template_dir = record(
+        "template_dir",
+        setting(
+            matches,
+            "template_dir",
+            "PINTEREST_TEMPLATE_DIR",
+            file.get_path(|f| &f.template_dir),
+        ),
+    );
+    let mut session = None;
+    let mut access_token = secret_setting(
+        matches,
+        "access_token",
+        "PINTEREST_ACCESS_TOKEN",
+        file.get(|f| &f.access_token),
+    )?;
+    if access_token.is_none()
+        && let Some(path) = session::session_path(file.profile.as_ref().map(|(n, _)| n.as_str()))
+        && let Some(saved) = session::load(&path)?
+    {
+        access_token = Some((
+            saved.access_token.clone(),
+            format!("session {}", path.display()),
+        ));
+        session = Some((path, saved));
+    }
+    let access_token = record("access_token", credential("access_token", access_token)?);
+    let client_id = record(
+        "client_id",
+        credential(
+            "client_id",
+            setting(
+                matches,
+                "client_id",
+                "PINTEREST_CLIENT_ID",
+                file.get(|f| &f.client_id),
+            ),
+        )?,
+    );
+    let client_secret = record(
+        "client_secret",
+        credential(
+            "client_secret",
+            secret_setting(
+                matches,
+                "client_secret",
+                "PINTEREST_CLIENT_SECRET",
+                file.get(|f| &f.client_secret),
+            )?,
+        )?,
+    );
+    let conversion_token = record(
+        "conversion_token",
+        credential(
+            "conversion_token",
+            secret_setting(
+                matches,
+                "conversion_token",
+                "PINTEREST_CONVERSION_TOKEN",
+                file.get(|f| &f.conversion_token),
+            )?,
+        )?,
+    );
+    let ad_account_id = record(
+        "ad_account_id",
+        setting(
+            matches,
+            "ad_account_id",
+            "PINTEREST_AD_ACCOUNT_ID",
+            file.get(|f| &f.ad_account_id),
+        ),
+    );
+    let path_defaults = file.merged(|f| &f.path_defaults);
+    let mut path_default_origins: Vec<String> = Vec::new();
+    for (_, origin) in path_defaults.values() {
+        if !path_default_origins.contains(origin) {
+            path_default_origins.push(origin.clone());
+        }
+    }
+    origins.push((
+        "path_defaults",
+        (!path_default_origins.is_empty()).then(|| path_default_origins.join("; ")),
+    ));
+    let path_defaults = path_defaults
+        .into_iter()
+        .map(|(param, (value, _))| (param, value))
+        .collect();
+    let raw_output = match env_value(matches, "PINTEREST_DEFAULT_RAW_OUTPUT") {
+        Some(value) => Some((
+            parse_bool(&value).ok_or_else(|| {
+                anyhow!("PINTEREST_DEFAULT_RAW_OUTPUT must be true or false, got {value:?}")
+            })?,
+            "env PINTEREST_DEFAULT_RAW_OUTPUT".to_string(),
+        )),
+        None => file.get(|f| &f.default_raw_output),
+    };
+    origins.push((
+        "default_raw_output",
+        raw_output.as_ref().map(|(_, o)| o.clone()),
+    ));
+    let default_raw_output = raw_output.is_some_and(|(value, _)| value);
+
+    let policy = OperationPolicy {
+        allowed: file.get(|f| &f.allowed_operations),
+        denied: file.get(|f| &f.denied_operations),
+    };
+    origins.push((
+        "allowed_operations",
+        policy.allowed.as_ref().map(|(_, o)| o.clone()),
+    ));
+    origins.push((
+        "denied_operations",
+        policy.denied.as_ref().map(|(_, o)| o.clone()),
+    ));
+
+    let timeout = matches.get_one::<u64>("timeout").copied();
+    let http_version = if matches.get_flag("http1") {
+        HttpVersion::Http1
+    } else if matches.get_flag("http2_prior_knowledge") {
+        HttpVersion::Http2PriorKnowledge
+    } else {
+        HttpVersion::Auto
+    };
+
+    let pool = PoolOptions {
+        max_idle_per_host: matches.get_one::<usize>("pool_max_idle_per_host").copied(),
+        idle_timeout: matches
+            .get_one::<u64>("pool_idle_timeout")
+            .map(|s| std::time::Duration::from_secs(*s)),
+    };
+
+    let sources = SourceOptions {
+        auth_header: matches
+            .get_one::<String>("source_auth_header")
+            .cloned()
+            .or_else(|| env_value(matches, "PINTEREST_SOURCE_AUTH_HEADER")),
+        max_redirects: matches
+            .get_one::<usize>("source_max_redirects")
+            .copied()
+            .unwrap_or(sources::DEFAULT_MAX_REDIRECTS),
+        http_version,
+        decompress: !matches.get_flag("no_decompress"),
+        input_format: match matches
+            .get_one::<String>("input_format")
+            .map(String::as_str)
+        {
+            Some("yaml") => InputFormat::Yaml,
+            _ => InputFormat::Json,
+        },
+        s3: S3Options {
+            role_arn: matches
+                .get_one::<String>("s3_role_arn")
+                .cloned()
+                .or_else(|| env_value(matches, "PINTEREST_S3_ROLE_ARN")),
+            external_id: matches
+                .get_one::<String>("s3_external_id")
+                .cloned()
+                .or_else(|| env_value(matches, "PINTEREST_S3_EXTERNAL_ID")),
+            max_attempts: matches
+                .get_one::<u32>("s3_max_attempts")
+                .copied()
+                .unwrap_or(s3::DEFAULT_MAX_ATTEMPTS),
+            timeout: matches
+                .get_one::<u64>("s3_timeout")
+                .map(|s| std::time::Duration::from_secs(*s))
+                .unwrap_or(s3::DEFAULT_TIMEOUT),
+        },
+    };
+
+    Ok(Config {
+        base_url,
+        access_token,
+        client_id,
+        client_secret,
+        conversion_token,
+        ad_account_id,
+        path_defaults,
+        default_raw_output,
+        command_tree,
+        template_dir,
+        timeout,
+        http_version,
+        pool,
+        sources,
+        policy,
+        session,
+        origins,
+    })
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::io::BufRead;
+
+use crate::output::write_stdout_line;
+use pinterest_ads::cancel;
+use pinterest_ads::client::{Auth, Body, PinterestClient};
+
+/// Events accepted per `POST /ad_accounts/{id}/events` call.
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+/// `user_data` fields Pinterest expects as lowercase SHA-256 hex.
+const HASHED_FIELDS: &[&str] = &[
+    "em",
+    "hashed_maids",
+    "ph",
+    "ge",
+    "db",
+    "ln",
+    "fn",
+    "ct",
+    "st",
+    "zp",
+    "country",
+    "external_id",
+];
+
+#[derive(Debug, Default)]
+pub struct SendSummary {
+    pub batches: u64,
+    pub sent: u64,
+    pub failed: u64,
+}
+
+/// Reads NDJSON events line by line, hashes plain-text `user_data`, and POSTs
+/// them in batches of `batch_size`. Each batch result is written to stdout as
+/// one NDJSON line, so memory stays bounded by a single batch.
+pub fn send_events(
+    api: &PinterestClient,
+    auth: &Auth,
+    ad_account_id: &str,
+    input: impl BufRead,
+    batch_size: usize,
+) -> Result<SendSummary> {
+    let url = api.build_url(&format!(
+        "/ad_accounts/{}/events",
+        urlencoding::encode(ad_account_id)
+    ))?;
+    let mut summary = SendSummary::default();
+    let mut batch: Vec<Value> = Vec::with_capacity(batch_size);
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.context("read events")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut event: Value = serde_json::from_str(&line)
+            .with_context(|| format!("invalid JSON on events line {}", index + 1))?;
+        hash_user_data(&mut event);
+        batch.push(event);
+        if batch.len() >= batch_size {
+            send_batch(api, auth, &url, &mut batch, &mut summary)?;
+            if cancel::is_cancelled() {
+                log::warn!("interrupted; stopping after {} batches", summary.batches);
+                return Ok(summary);
+            }
+        }
+    }
+    if !batch.is_empty() {
+        send_batch(api, auth, &url, &mut batch, &mut summary)?;
+    }
+    Ok(summary)
+}
+
+fn send_batch(
+    api: &PinterestClient,
+    auth: &Auth,
+    url: &str,
+    batch: &mut Vec<Value>,
+    summary: &mut SendSummary,
+) -> Result<()> {
+    summary.batches += 1;
+    let count = batch.len() as u64;
+    let body = Body::Json(json!({ "data": std::mem::take(batch) }));
+    let line = match api.request("POST", url, auth, &[], Some(body)) {
+        Ok(result) => {
+            summary.sent += count;
+            json!({ "batch": summary.batches, "events": count, "ok": true, "result": result })
+        }
+        Err(err) => {
+            summary.failed += count;
+            json!({
+                "batch": summary.batches,
+                "events": count,
+                "ok": false,
+                "error": pinterest_ads::error::error_json(&err),
+            })
+        }
+    };
+    write_stdout_line(&line.to_string())
+}
+
+/// Hashes plain-text identifiers in `user_data`; values that already look
+/// like SHA-256 hex are left alone.
+pub fn hash_user_data(event: &mut Value) {
+    let Some(user_data) = event.get_mut("user_data").and_then(|v| v.as_object_mut()) else {
+        return;
+    };
+    for field in HASHED_FIELDS {
+        let Some(value) = user_data.get_mut(*field) else {
+            continue;
+        };
+        match value {
+            Value::Array(items) => {
+                for item in items.iter_mut() {
+                    if let Value::String(s) = item {
+                        *s = hash_value(field, s);
+                    }
+                }
+            }
+            Value::String(s) => *s = hash_value(field, s),
+            _ => {}
+        }
+    }
+}
+
+fn hash_value(field: &str, value: &str) -> String {
+    if value.len() == 64
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    {
+        return value.to_string();
+    }
+    let normalized = if field == "ph" {
+        value.chars().filter(|c| c.is_ascii_digit()).collect()
+    } else {
+        value.trim().to_lowercase()
+    };
+    Sha256::digest(normalized.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+pub fn check_batch_size(size: usize) -> Result<usize> {
+    if size == 0 || size > MAX_BATCH_SIZE {
+        return Err(anyhow!(
+            "--batch-size must be between 1 and {MAX_BATCH_SIZE}"
+        ));
+    }
+    Ok(size)
+}
@@ -0,0 +1,182 @@
+use pinterest_ads::client::{self, Auth, Body, PinterestClient};
+use pinterest_ads::query;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+
+/// Terminates the heredoc a gzipped body is piped from.
+const HEREDOC_END: &str = "JSON";
+
+/// Renders the request the client would send as a `curl` command line.
+/// Credentials print as `REDACTED` unless `reveal_secrets` is set.
+pub fn render(
+    client: &PinterestClient,
+    method: &str,
+    url: &str,
+    auth: &Auth,
+    query: &[(String, String)],
+    body: Option<&Body>,
+    reveal_secrets: bool,
+) -> String {
+    let secret = |value: &str| {
+        if reveal_secrets {
+            value.to_string()
+        } else {
+            "REDACTED".to_string()
+        }
+    };
+
+    // `-X HEAD` would make curl wait for a body that never comes.
+    let method = match method {
+        "HEAD" => "--head".to_string(),
+        other => format!("-X {other}"),
+    };
+    let mut parts = vec![format!(
+        "curl {method} {}",
+        shell_quote(&query::append_to_url(url, query))
+    )];
+    parts.push(format!("-H {}", shell_quote("Accept: application/json")));
+
+    let headers = client.request_headers();
+    if !headers.contains_key(AUTHORIZATION) {
+        match auth {
+            Auth::Bearer(token) => parts.push(format!(
+                "-H {}",
+                shell_quote(&format!("Authorization: Bearer {}", secret(token)))
+            )),
+            Auth::Basic { username, password } => parts.push(format!(
+                "-u {}",
+                shell_quote(&format!("{username}:{}", secret(password)))
+            )),
+        }
+    }
+    for (name, value) in &headers {
+        if name == ACCEPT {
+            continue;
+        }
+        let value = String::from_utf8_lossy(value.as_bytes());
+        let value = if name == AUTHORIZATION {
+            secret(&value)
+        } else {
+            value.into_owned()
+        };
+        parts.push(format!("-H {}", shell_quote(&format!("{name}: {value}"))));
+    }
+
+    // A body fed to the command on stdin, after the command line.
+    let mut heredoc = None;
+    match body {
+        None => {}
+        Some(Body::Json(value)) if client.gzips_body() => {
+            // curl can't gzip a request body itself, so pipe it through gzip.
+            // A quoted heredoc keeps the body out of argv (no ARG_MAX limit)
+            // and unexpanded; compact JSON is one line, so never `JSON` alone.
+            parts[0] = format!("gzip <<'{HEREDOC_END}' | {}", parts[0]);
+            heredoc = Some(value.to_string());
+            parts.push(format!(
+                "-H {}",
+                shell_quote("Content-Type: application/json")
+            ));
+            parts.push(format!("-H {}", shell_quote("Content-Encoding: gzip")));
+            parts.push("--data-binary @-".to_string());
+        }
+        Some(Body::Json(value)) => {
+            parts.push(format!(
+                "-H {}",
+                shell_quote("Content-Type: application/json")
+            ));
+            parts.push(format!("--data-raw {}", shell_quote(&value.to_string())));
+        }
+        Some(Body::JsonFile(file)) => {
+            parts.push(format!(
+                "-H {}",
+                shell_quote("Content-Type: application/json")
+            ));
+            let path = format!("@{}", file.path.display());
+            parts.push(format!("--data-binary {}", shell_quote(&path)));
+        }
+        Some(Body::Form(fields)) => {
+            // Pre-encoded, so the output shows exactly what is sent.
+            parts.push(format!(
+                "-H {}",
+                shell_quote("Content-Type: application/x-www-form-urlencoded")
+            ));
+            let encoded = client::encode_form(fields);
+            parts.push(format!("--data-raw {}", shell_quote(&encoded)));
+        }
+    }
+    let mut command = parts.join(" \\\n  ");
+    if let Some(body) = heredoc {
+        command.push_str(&format!("\n{body}\n{HEREDOC_END}"));
+    }
+    command
+}
+
+/// Quotes a value for POSIX shells, leaving plain words bare.
+pub fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.,:/@=+".contains(c));
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn gzipped_body_goes_through_a_heredoc() {
+        let client = PinterestClient::builder("https://api.example.com/v5")
+            .gzip_body(true)
+            .build()
+            .unwrap();
+        let body = Body::Json(json!({"note": "it's $HOME `x`\nJSON"}));
+        let command = render(
+            &client,
+            "POST",
+            "https://api.example.com/v5/events",
+            &Auth::Bearer("t".into()),
+            &[],
+            Some(&body),
+            false,
+        );
+        let (line, rest) = command.split_once('\n').unwrap();
+        assert_eq!(
+            line,
+            "gzip <<'JSON' | curl -X POST https://api.example.com/v5/events \\"
+        );
+        assert!(rest.ends_with("--data-binary @-\n{\"note\":\"it's $HOME `x`\\nJSON\"}\nJSON"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn gzipped_body_round_trips_through_the_shell() {
+        let client = PinterestClient::builder("https://api.example.com/v5")
+            .gzip_body(true)
+            .build()
+            .unwrap();
+        let value = json!({"items": ["a'b", "$(echo no)", "\\\\"]});
+        let command = render(
+            &client,
+            "PUT",
+            "https://api.example.com/v5/x",
+            &Auth::Bearer("t".into()),
+            &[],
+            Some(&Body::Json(value.clone())),
+            false,
+        );
+        // Stand in for curl: decompress whatever it would have sent.
+        let script = format!("curl() {{ gzip -dc; }}\n{command}");
+        let output = std::process::Command::new("sh")
+            .args(["-c", &script])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        let sent: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(sent, value);
+    }
+}
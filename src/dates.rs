@@ -0,0 +1,42 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{Days, NaiveDate, Utc};
+
+/// Start/end query params that bound a date window, in lookup order.
+pub const WINDOW_PARAMS: &[(&str, &str)] = &[
+    ("start_date", "end_date"),
+    ("start_due_date", "end_due_date"),
+];
+
+/// Parses `YYYY-MM-DD`, `today`, `yesterday`, or a relative `Nd`/`Nw` (N days or
+/// weeks before today, optionally written `-Nd`). Dates are UTC, like the API's.
+pub fn parse_date_expr(raw: &str) -> Result<NaiveDate> {
+    let today = Utc::now().date_naive();
+    let value = raw.trim().to_ascii_lowercase();
+    match value.as_str() {
+        "today" => return Ok(today),
+        "yesterday" => return days_before(today, 1, raw),
+        _ => {}
+    }
+
+    let relative = value.strip_prefix('-').unwrap_or(&value);
+    if let Some(n) = relative.strip_suffix('d')
+        && let Ok(n) = n.parse::<u64>()
+    {
+        return days_before(today, n, raw);
+    }
+    if let Some(n) = relative.strip_suffix('w')
+        && let Ok(n) = n.parse::<u64>()
+    {
+        return days_before(today, n * 7, raw);
+    }
+
+    NaiveDate::parse_from_str(&value, "%Y-%m-%d").with_context(|| {
+        format!("invalid date {raw:?} (expected YYYY-MM-DD, today, yesterday, Nd or Nw)")
+    })
+}
+
+fn days_before(today: NaiveDate, n: u64, raw: &str) -> Result<NaiveDate> {
+    today
+        .checked_sub_days(Days::new(n))
+        .ok_or_else(|| anyhow!("date out of range: {raw}"))
+}
@@ -0,0 +1,122 @@
+use anyhow::{Result, anyhow};
+use serde_json::{Map, Value, json};
+
+/// Compares two results of the same read, e.g. a saved export and a fresh one.
+///
+/// Lists (an array, or an object with `items[]`) are matched item by item on
+/// the value at the dotted `key` path and reported as
+/// `{added, removed, changed, unchanged}`; each changed entry lists its
+/// `changes` as `{path, before, after}`. Anything else is compared field by
+/// field and reported as `{changes}`. Arrays inside items are compared whole.
+pub fn diff(before: &Value, after: &Value, key: &str) -> Result<Value> {
+    match (items(before), items(after)) {
+        (Some(before), Some(after)) => diff_items(before, after, key),
+        (None, None) => {
+            let mut out = Vec::new();
+            changes("", before, after, &mut out);
+            Ok(json!({ "changes": out }))
+        }
+        _ => Err(anyhow!(
+            "can't compare a list with a single object; both sides must be the same kind of result"
+        )),
+    }
+}
+
+fn items(value: &Value) -> Option<&Vec<Value>> {
+    match value {
+        Value::Array(items) => Some(items),
+        Value::Object(map) => map.get("items")?.as_array(),
+        _ => None,
+    }
+}
+
+fn diff_items(before: &[Value], after: &[Value], key: &str) -> Result<Value> {
+    let before = keyed(before, key, "baseline")?;
+    let after = keyed(after, key, "current")?;
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut unchanged = 0u64;
+    for (id, item) in &after {
+        match before.iter().find(|(k, _)| k == id) {
+            None => added.push((*item).clone()),
+            Some((_, old)) => {
+                let mut out = Vec::new();
+                changes("", old, item, &mut out);
+                if out.is_empty() {
+                    unchanged += 1;
+                } else {
+                    changed.push(json!({ "key": id, "changes": out }));
+                }
+            }
+        }
+    }
+    let removed: Vec<Value> = before
+        .iter()
+        .filter(|(id, _)| !after.iter().any(|(k, _)| k == id))
+        .map(|(_, item)| (*item).clone())
+        .collect();
+    Ok(json!({
+        "added": added,
+        "removed": removed,
+        "changed": changed,
+        "unchanged": unchanged,
+    }))
+}
+
+/// Items paired with their key, as text so `"1"` and `1` match.
+fn keyed<'a>(items: &'a [Value], key: &str, side: &str) -> Result<Vec<(String, &'a Value)>> {
+    let mut out: Vec<(String, &Value)> = Vec::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+        let id = match lookup(item, key) {
+            Some(Value::String(s)) => s.clone(),
+            Some(v @ (Value::Number(_) | Value::Bool(_))) => v.to_string(),
+            _ => {
+                return Err(anyhow!(
+                    "{side} item {index} has no scalar {key:?}; pick another --key"
+                ));
+            }
+        };
+        if out.iter().any(|(k, _)| *k == id) {
+            return Err(anyhow!(
+                "{side} has two items with {key} {id}; --key must be unique"
+            ));
+        }
+        out.push((id, item));
+    }
+    Ok(out)
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |v, part| v.get(part))
+}
+
+fn changes(path: &str, before: &Value, after: &Value, out: &mut Vec<Value>) {
+    if before == after {
+        return;
+    }
+    let (Value::Object(a), Value::Object(b)) = (before, after) else {
+        out.push(change(path, before.clone(), after.clone()));
+        return;
+    };
+    let child = |key: &str| match path {
+        "" => key.to_string(),
+        _ => format!("{path}.{key}"),
+    };
+    for (key, old) in a {
+        match b.get(key) {
+            Some(new) => changes(&child(key), old, new, out),
+            None => out.push(change(&child(key), old.clone(), Value::Null)),
+        }
+    }
+    for (key, new) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+        out.push(change(&child(key), Value::Null, new.clone()));
+    }
+}
+
+fn change(path: &str, before: Value, after: Value) -> Value {
+    let mut entry = Map::new();
+    entry.insert("path".into(), json!(path));
+    entry.insert("before".into(), before);
+    entry.insert("after".into(), after);
+    Value::Object(entry)
+}
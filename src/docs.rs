@@ -0,0 +1,92 @@
+use pinterest_ads::command_tree::{CommandTree, Operation};
+
+/// Renders the command tree as a Markdown reference (same data as `describe`).
+pub fn render_markdown(tree: &CommandTree) -> String {
+    let mut out = String::new();
+    out.push_str("# pinterest-ads command reference\n\n");
+    out.push_str(&format!(
+        "Generated from Pinterest API {} (`{}`).\n\n",
+        tree.api_version, tree.base_url
+    ));
+
+    out.push_str("## Resources\n\n");
+    for res in &tree.resources {
+        out.push_str(&format!("- [{}](#{})\n", res.name, res.name));
+    }
+    out.push('\n');
+
+    for res in &tree.resources {
+        out.push_str(&format!("## {}\n\n", res.name));
+        out.push_str("| Command | Method | Path | Summary |\n");
+        out.push_str("| --- | --- | --- | --- |\n");
+        for op in &res.ops {
+            out.push_str(&format!(
+                "| `{} {}` | {} | `{}` | {} |\n",
+                res.name,
+                op.name,
+                op.method,
+                op.path,
+                escape_cell(op.summary.as_deref().unwrap_or(""))
+            ));
+        }
+        out.push('\n');
+
+        for op in &res.ops {
+            render_op(&mut out, &res.name, op);
+        }
+    }
+    out
+}
+
+fn render_op(out: &mut String, resource: &str, op: &Operation) {
+    out.push_str(&format!("### `pinterest-ads {} {}`\n\n", resource, op.name));
+    if let Some(summary) = &op.summary {
+        out.push_str(&format!("{}\n\n", summary));
+    }
+    if op.deprecated {
+        out.push_str("**Deprecated.**\n\n");
+    }
+    out.push_str(&format!("- Method: `{}`\n", op.method));
+    out.push_str(&format!("- Path: `{}`\n", op.path));
+    out.push_str(&format!("- Paginated: {}\n", op.paginated));
+
+    let schemes: Vec<String> = op
+        .security
+        .iter()
+        .flat_map(|req| req.keys().cloned().collect::<Vec<_>>())
+        .collect();
+    if !schemes.is_empty() {
+        out.push_str(&format!("- Auth: {}\n", schemes.join(" | ")));
+    }
+    if let Some(rb) = &op.request_body {
+        out.push_str(&format!(
+            "- Body: {} ({})\n",
+            rb.content_types.join(", "),
+            if rb.required { "required" } else { "optional" }
+        ));
+    }
+    out.push('\n');
+
+    if !op.params.is_empty() {
+        out.push_str("| Flag | In | Type | Required |\n");
+        out.push_str("| --- | --- | --- | --- |\n");
+        for param in &op.params {
+            let schema_type = match &param.items_type {
+                Some(items) => format!("{}[]", items),
+                None => param.schema_type.clone(),
+            };
+            out.push_str(&format!(
+                "| `--{}` | {} | {} | {} |\n",
+                param.flag,
+                param.location,
+                schema_type,
+                if param.required { "yes" } else { "no" }
+            ));
+        }
+        out.push('\n');
+    }
+}
+
+fn escape_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,236 @@
+use serde_json::{Value, json};
+use std::fmt;
+
+/// Errors that carry machine-readable detail for `--error-format json`.
+/// Anything else is reported with its message only.
+#[derive(Debug)]
+pub enum CliError {
+    MissingPathParam {
+        op: String,
+        path: String,
+        param: String,
+        flag: String,
+    },
+    /// A non-2xx response. Pinterest's envelope is `{code, message}`, sometimes
+    /// with `details`; other bodies are kept as-is (`null` when there was none).
+    Api {
+        status: u16,
+        reason: String,
+        body: Value,
+        /// OAuth scopes the operation needs, when known; empty otherwise.
+        required_scopes: Vec<String>,
+    },
+}
+
+/// Why a request was refused authorization, as far as the error body tells,
+/// so the message can point at the right fix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthFailure {
+    Expired,
+    /// Revoked, or never valid.
+    Revoked,
+    InsufficientScope,
+    /// A 401 the body doesn't explain.
+    Unauthorized,
+}
+
+impl AuthFailure {
+    /// Classifies a 401 (or a scope-related 403) from its status and body.
+    pub fn classify(status: u16, body: &Value) -> Option<Self> {
+        let message = body
+            .get("message")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let has = |words: &[&str]| words.iter().any(|w| message.contains(w));
+        match status {
+            401 | 403 if has(&["scope", "permission", "not authorized to access"]) => {
+                Some(Self::InsufficientScope)
+            }
+            401 if has(&["expired"]) => Some(Self::Expired),
+            401 if has(&["revoked", "invalid", "not valid", "malformed"]) => Some(Self::Revoked),
+            401 => Some(Self::Unauthorized),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Expired => "expired",
+            Self::Revoked => "revoked",
+            Self::InsufficientScope => "insufficient_scope",
+            Self::Unauthorized => "unauthorized",
+        }
+    }
+
+    /// Whether a new access token could fix it (a scope problem needs a new grant).
+    pub fn refreshable(self) -> bool {
+        self != Self::InsufficientScope
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            Self::Expired => {
+                "the access token has expired; run `pinterest-ads auth login` (sessions with a refresh token renew themselves)"
+            }
+            Self::Revoked => {
+                "the access token was revoked or is invalid; run `pinterest-ads auth login` for a new one"
+            }
+            Self::InsufficientScope => {
+                "the token lacks a scope this operation needs (`describe` lists them); re-authorize the app with it"
+            }
+            Self::Unauthorized => {
+                "authentication failed; check PINTEREST_ACCESS_TOKEN or run `pinterest-ads auth login`"
+            }
+        }
+    }
+}
+
+impl CliError {
+    pub fn to_json(&self) -> Value {
+        match self {
+            CliError::MissingPathParam {
+                op,
+                path,
+                param,
+                flag,
+            } => json!({
+                "error": self.to_string(),
+                "missing_path_param": param,
+                "flag": format!("--{flag}"),
+                "operation": op,
+                "path": path,
+            }),
+            CliError::Api {
+                status,
+                body,
+                required_scopes,
+                ..
+            } => {
+                let mut out = json!({ "error": self.to_string(), "status": status });
+                for key in ["code", "message", "details"] {
+                    if let Some(v) = body.get(key) {
+                        out[key] = v.clone();
+                    }
+                }
+                if body.get("message").is_none() {
+                    out["body"] = body.clone();
+                }
+                if let Some(failure) = AuthFailure::classify(*status, body) {
+                    out["auth_failure"] = json!(failure.as_str());
+                    out["hint"] = json!(failure.hint());
+                    if failure == AuthFailure::InsufficientScope {
+                        let scopes = missing_scopes(body, required_scopes);
+                        if !scopes.is_empty() {
+                            out["required_scopes"] = json!(scopes);
+                        }
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Scopes to ask for after a scope refusal: the ones the body names (e.g.
+/// "requires ads:write"), else the operation's.
+fn missing_scopes(body: &Value, required: &[String]) -> Vec<String> {
+    let message = body
+        .get("message")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let mut named: Vec<String> = Vec::new();
+    for word in message.split(|c: char| !(c.is_ascii_alphanumeric() || c == ':' || c == '_')) {
+        let is_scope = word.split_once(':').is_some_and(|(a, b)| {
+            !a.is_empty()
+                && !b.is_empty()
+                && !b.contains(':')
+                && a.chars().all(|c| c.is_ascii_lowercase())
+        });
+        if is_scope && !named.iter().any(|s| s == word) {
+            named.push(word.to_string());
+        }
+    }
+    if named.is_empty() {
+        required.to_vec()
+    } else {
+        named
+    }
+}
+
+fn detail_lines(details: &Value) -> Vec<String> {
+    let line = |v: &Value| match v {
+        Value::String(s) => s.clone(),
+        Value::Object(map) => match (map.get("field"), map.get("message")) {
+            (Some(Value::String(field)), Some(Value::String(message))) => {
+                format!("{field}: {message}")
+            }
+            _ => v.to_string(),
+        },
+        other => other.to_string(),
+    };
+    match details {
+        Value::Array(items) => items.iter().map(line).collect(),
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| match v {
+                Value::String(s) => format!("{k}: {s}"),
+                other => format!("{k}: {other}"),
+            })
+            .collect(),
+        Value::Null => Vec::new(),
+        other => vec![line(other)],
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::MissingPathParam { param, flag, .. } => {
+                write!(f, "missing required path param: {param} (--{flag})")
+            }
+            CliError::Api {
+                status,
+                reason,
+                body,
+                required_scopes,
+            } => {
+                match body.get("message").and_then(|v| v.as_str()) {
+                    None if body.is_null() => write!(f, "http {status} {reason}")?,
+                    None => write!(f, "http {status} {reason}: {body}")?,
+                    Some(message) => {
+                        write!(f, "http {status} {reason}: {message}")?;
+                        if let Some(code) = body.get("code") {
+                            write!(f, " (code {code})")?;
+                        }
+                        for line in body.get("details").map(detail_lines).unwrap_or_default() {
+                            write!(f, "\n  - {line}")?;
+                        }
+                    }
+                }
+                if let Some(failure) = AuthFailure::classify(*status, body) {
+                    write!(f, "\n  hint: {}", failure.hint())?;
+                    let scopes = missing_scopes(body, required_scopes);
+                    if failure == AuthFailure::InsufficientScope && !scopes.is_empty() {
+                        write!(
+                            f,
+                            "\n  required scopes: {} (include them when authorizing the app, then `auth login --code`)",
+                            scopes.join(", ")
+                        )?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Renders any error as a JSON object, using structured detail when available.
+pub fn error_json(err: &anyhow::Error) -> Value {
+    match err.downcast_ref::<CliError>() {
+        Some(cli) => cli.to_json(),
+        None => json!({ "error": err.to_string() }),
+    }
+}
@@ -0,0 +1,370 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use url::Url;
+
+/// Responses saved to a directory as JSON files, one per request, so a run
+/// can be replayed later without the network (e.g. in CI). Requests are keyed
+/// by method, URL path and query, not host or auth, so fixtures recorded
+/// against the API replay against any base URL. The nth identical request of
+/// a run gets the nth recording, and the last one again once they run out, so
+/// retries and polls replay in order.
+#[derive(Debug)]
+pub struct Fixtures {
+    dir: PathBuf,
+    replay: bool,
+    /// Requests seen so far this run, by fixture name.
+    seen: Mutex<HashMap<String, u32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    path: String,
+    query: String,
+    status: u16,
+    /// Repeated headers joined with `, `.
+    headers: BTreeMap<String, String>,
+    /// The body, when it is JSON; otherwise in `text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+impl Fixtures {
+    /// Saves every response the client gets into `dir`.
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            replay: false,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Answers every request from the fixtures in `dir`; nothing is sent.
+    pub fn replay(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            return Err(anyhow!("no fixture directory {}", dir.display()));
+        }
+        Ok(Self {
+            dir,
+            replay: true,
+            seen: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn replaying(&self) -> bool {
+        self.replay
+    }
+
+    /// The recorded response to `method url`, as status, headers and body.
+    pub fn response(&self, method: &str, url: &str) -> Result<(StatusCode, HeaderMap, Vec<u8>)> {
+        let (path, query) = split(url);
+        let name = name(method, &path, &query);
+        let nth = self.next(&name);
+        let file = (1..=nth)
+            .rev()
+            .map(|n| self.file(&name, n))
+            .find(|file| file.is_file())
+            .ok_or_else(|| {
+                anyhow!(
+                    "no fixture for {method} {} in {} (record one with --record)",
+                    with_query(&path, &query),
+                    self.dir.display()
+                )
+            })?;
+        let text =
+            std::fs::read_to_string(&file).with_context(|| format!("read {}", file.display()))?;
+        let fixture: Fixture =
+            serde_json::from_str(&text).with_context(|| format!("parse {}", file.display()))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &fixture.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        let body = match (fixture.body, fixture.text) {
+            (Some(body), _) => serde_json::to_vec(&body)?,
+            (None, text) => text.unwrap_or_default().into_bytes(),
+        };
+        log::debug!("{method} {url}: replayed from {}", file.display());
+        let status = StatusCode::from_u16(fixture.status)
+            .with_context(|| format!("invalid status in {}", file.display()))?;
+        Ok((status, headers, body))
+    }
+
+    /// Saves one response, its body read from `body`; failing to write only
+    /// logs a warning.
+    pub fn save(
+        &self,
+        method: &str,
+        url: &str,
+        status: StatusCode,
+        headers: &HeaderMap,
+        mut body: impl Read + Seek,
+    ) {
+        let (path, query) = split(url);
+        let name = name(method, &path, &query);
+        let file = self.file(&name, self.next(&name));
+        let fixture = Fixture {
+            method: method.to_string(),
+            path,
+            query,
+            status: status.as_u16(),
+            headers: joined(headers),
+            body: None,
+            text: None,
+        };
+        match self.write(&file, fixture, &mut body) {
+            Ok(()) => log::debug!("{method} {url}: recorded to {}", file.display()),
+            Err(err) => log::warn!("record {}: {err:#}", file.display()),
+        }
+    }
+
+    /// Writes `fixture` with `body` to `file` through a temp file renamed into
+    /// place, so an interrupted run never leaves half a fixture. A JSON body
+    /// is copied in as sent rather than parsed into memory.
+    fn write(
+        &self,
+        file: &Path,
+        mut fixture: Fixture,
+        body: &mut (impl Read + Seek),
+    ) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).context("create fixture directory")?;
+        let json = serde_json::from_reader::<_, IgnoredAny>(BufReader::new(&mut *body)).is_ok();
+        body.rewind().context("rewind recorded body")?;
+        if !json {
+            let mut bytes = Vec::new();
+            body.read_to_end(&mut bytes).context("read recorded body")?;
+            fixture.text = Some(String::from_utf8_lossy(&bytes).into_owned());
+        }
+        let mut head = serde_json::to_string_pretty(&fixture)?;
+        let mut out = tempfile::NamedTempFile::new_in(&self.dir).context("create temp file")?;
+        if json {
+            // Reopen the closing brace to append the body field.
+            head.truncate(head.len() - "\n}".len());
+            write!(out, "{head},\n  \"body\": ")?;
+            std::io::copy(body, &mut out).context("write fixture")?;
+            out.write_all(b"\n}\n")?;
+        } else {
+            writeln!(out, "{head}")?;
+        }
+        out.persist(file)
+            .map_err(|err| anyhow!("write fixture: {}", err.error))?;
+        Ok(())
+    }
+
+    /// Which occurrence of `name` this is in the run, from 1.
+    fn next(&self, name: &str) -> u32 {
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        let n = seen.entry(name.to_string()).or_default();
+        *n += 1;
+        *n
+    }
+
+    fn file(&self, name: &str, nth: u32) -> PathBuf {
+        match nth {
+            1 => self.dir.join(format!("{name}.json")),
+            n => self.dir.join(format!("{name}.{n}.json")),
+        }
+    }
+}
+
+/// Copies what is read through it to a temp file, so a response can be
+/// recorded while the caller consumes it, without a second copy in memory.
+/// If the copy can't be written, the recording is dropped with a warning and
+/// reading goes on.
+pub(crate) struct Tee<R> {
+    inner: R,
+    copy: Option<File>,
+}
+
+impl<R> Tee<R> {
+    pub fn new(inner: R) -> Self {
+        let copy = tempfile::tempfile()
+            .map_err(|err| log::warn!("record: create temp file: {err}"))
+            .ok();
+        Self { inner, copy }
+    }
+
+    /// Everything read so far, rewound; `None` if it couldn't be kept.
+    pub fn into_copy(self) -> Option<File> {
+        let mut copy = self.copy?;
+        copy.rewind()
+            .map_err(|err| log::warn!("record: rewind temp file: {err}"))
+            .ok()?;
+        Some(copy)
+    }
+}
+
+impl<R: Read> Read for Tee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(copy) = &mut self.copy
+            && let Err(err) = copy.write_all(&buf[..n])
+        {
+            log::warn!("record: write temp file: {err}");
+            self.copy = None;
+        }
+        Ok(n)
+    }
+}
+
+fn joined(headers: &HeaderMap) -> BTreeMap<String, String> {
+    let mut out: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in headers {
+        let Ok(value) = value.to_str() else { continue };
+        out.entry(name.to_string())
+            .and_modify(|v| {
+                v.push_str(", ");
+                v.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    out
+}
+
+fn split(url: &str) -> (String, String) {
+    match Url::parse(url) {
+        Ok(url) => (
+            url.path().to_string(),
+            url.query().unwrap_or("").to_string(),
+        ),
+        Err(_) => {
+            let (path, query) = url.split_once('?').unwrap_or((url, ""));
+            (path.to_string(), query.to_string())
+        }
+    }
+}
+
+fn with_query(path: &str, query: &str) -> String {
+    match query {
+        "" => path.to_string(),
+        _ => format!("{path}?{query}"),
+    }
+}
+
+/// A readable file name stem (`GET_v5_ad_accounts_1_campaigns-…`) with a hash
+/// of the full key, so different queries never share a file.
+fn name(method: &str, path: &str, query: &str) -> String {
+    let key = format!("{method} {}", with_query(path, query));
+    let hash: String = Sha256::digest(key.as_bytes())
+        .iter()
+        .take(6)
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let slug: String = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .flat_map(|s| std::iter::once('_').chain(s.chars()))
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(80)
+        .collect();
+    format!("{method}{slug}-{hash}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn recorded_bodies_replay_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://api.example.com/v5/ad_accounts/1/campaigns?page_size=2";
+        let recorder = Fixtures::record(dir.path());
+        let json = br#"{"items": [{"id": "9007199254740993"}], "bookmark": null}"#;
+        recorder.save(
+            "GET",
+            url,
+            StatusCode::OK,
+            &HeaderMap::new(),
+            Cursor::new(json),
+        );
+        let text = b"<html>bad gateway</html>";
+        recorder.save(
+            "GET",
+            url,
+            StatusCode::BAD_GATEWAY,
+            &HeaderMap::new(),
+            Cursor::new(text),
+        );
+        // Only the two fixtures: no temp file is left behind.
+        assert_eq!(files(dir.path()).len(), 2);
+
+        let player = Fixtures::replay(dir.path()).unwrap();
+        let (status, _, body) = player.response("GET", url).unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            serde_json::from_slice::<Value>(json).unwrap()
+        );
+        let (status, _, body) = player.response("GET", url).unwrap();
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(body, text);
+    }
+
+    #[test]
+    fn fixture_files_stay_valid_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Fixtures::record(dir.path());
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("9"));
+        recorder.save(
+            "POST",
+            "https://api.example.com/v5/boards",
+            StatusCode::CREATED,
+            &headers,
+            Cursor::new(b"{\"id\":\"1\"}\n"),
+        );
+        let [name] = files(dir.path()).try_into().unwrap();
+        let text = std::fs::read_to_string(dir.path().join(name)).unwrap();
+        let fixture: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(fixture["status"], 201);
+        assert_eq!(fixture["headers"]["x-ratelimit-remaining"], "9");
+        assert_eq!(fixture["body"], serde_json::json!({"id": "1"}));
+    }
+
+    #[test]
+    fn tee_keeps_everything_read_through_it() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        let mut tee = Tee::new(data.as_slice());
+        let mut read = Vec::new();
+        tee.read_to_end(&mut read).unwrap();
+        let mut copy = Vec::new();
+        tee.into_copy().unwrap().read_to_end(&mut copy).unwrap();
+        assert_eq!(
+            (read.as_slice(), copy.as_slice()),
+            (data.as_slice(), data.as_slice())
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::{Result, anyhow};
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io::{BufReader, Read};
+
+/// Parses a JSON document from `reader` one list item at a time, so a huge
+/// response is never held in memory whole. Each element of a top-level array,
+/// or of a top-level object's `items[]`, goes to `on_item` as soon as it is
+/// parsed; the object's other fields are dropped. An object without `items`
+/// is handed over whole. Returns the number of items.
+pub fn for_each_item<R: Read>(reader: R, on_item: impl FnMut(Value) -> Result<()>) -> Result<u64> {
+    let mut sink = Sink {
+        on_item,
+        seen: 0,
+        failed: None,
+    };
+    let mut de = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    let parsed = de
+        .deserialize_any(&mut sink)
+        .and_then(|()| de.end())
+        .map_err(|err| anyhow!("decode json: {err}"));
+    match sink.failed {
+        // The callback's error, not the parser's "stopped" that carried it out.
+        Some(err) => Err(err),
+        None => parsed.map(|()| sink.seen),
+    }
+}
+
+struct Sink<F> {
+    on_item: F,
+    seen: u64,
+    failed: Option<anyhow::Error>,
+}
+
+impl<F: FnMut(Value) -> Result<()>> Sink<F> {
+    fn emit<E: de::Error>(&mut self, item: Value) -> Result<(), E> {
+        self.seen += 1;
+        (self.on_item)(item).map_err(|err| {
+            self.failed = Some(err);
+            E::custom("stopped by the item handler")
+        })
+    }
+}
+
+impl<'de, F: FnMut(Value) -> Result<()>> Visitor<'de> for &mut Sink<F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON array or an object with items[]")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(item) = seq.next_element::<Value>()? {
+            self.emit(item)?;
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut rest = Map::new();
+        let mut streamed = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "items" && !streamed {
+                map.next_value_seed(Items(&mut *self))?;
+                streamed = true;
+            } else {
+                rest.insert(key, map.next_value()?);
+            }
+        }
+        if !streamed {
+            self.emit(Value::Object(rest))?;
+        }
+        Ok(())
+    }
+}
+
+/// The `items` value of a top-level object, streamed like a top-level array.
+struct Items<'a, F>(&'a mut Sink<F>);
+
+impl<'de, F: FnMut(Value) -> Result<()>> DeserializeSeed<'de> for Items<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self.0)
+    }
+}
@@ -1,54 +1,275 @@
-mod client;
-mod command_tree;
-mod media_upload;
-mod pagination;
-mod s3;
-mod sources;
+mod config;
+mod conversions;
+mod curl;
+mod dates;
+mod docs;
+mod output;
+mod pipeline;
+mod schema;
+mod session;
+// The library's test server, compiled again for the binary's tests.
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_server.rs"]
+mod test_server;
+
+use pinterest_ads::{
+    cancel, client, command_tree, diff, error, media_upload, metrics, monitor, pagination, query,
+    request_log, sources, tree_check,
+};
 
 use anyhow::{Context, Result, anyhow};
 use clap::{Arg, ArgAction, Command};
 use command_tree::{CommandTree, Operation, ParamDef};
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::env;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
+use std::sync::{Arc, OnceLock};
 
-use crate::client::{Auth, Body, PinterestClient};
+use crate::config::{Config, env_value, load_config};
+use crate::output::{Envelope, OutputOptions, write_json, write_stdout_line};
+use pinterest_ads::adaptive::AdaptiveLimit;
+use pinterest_ads::client::{ApiResponse, Auth, Body, PinterestClient};
+use pinterest_ads::fixtures::Fixtures;
+use pinterest_ads::response_cache::ResponseCache;
+use pinterest_ads::sources::{InputFormat, SourceOptions};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
 fn main() {
-    if let Err(err) = run() {
+    cancel::install();
+    let args: Vec<String> = env::args().collect();
+    let tree = match config::bootstrap_command_tree(&args) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+    let matches = build_cli(&tree).get_matches();
+    let started = std::time::Instant::now();
+    let result = run(&tree, &matches);
+    if let Err(err) = &result {
+        if matches.get_flag("json_errors_to_stdout") {
+            // Nothing useful is left to do if stdout is gone too.
+            let _ = write_stdout_line(&error::error_json(err).to_string());
+        } else if matches
+            .get_one::<String>("error_format")
+            .map(|v| v.as_str())
+            == Some("json")
+        {
+            eprintln!("{}", error::error_json(err));
+        } else {
+            eprintln!("error: {err}");
+        }
+    }
+    if let Err(err) = write_stats(&matches, started.elapsed(), result.is_ok()) {
         eprintln!("error: {err}");
         std::process::exit(1);
     }
+    if result.is_err() {
+        std::process::exit(if cancel::is_cancelled() {
+            cancel::EXIT_INTERRUPTED
+        } else {
+            1
+        });
+    }
+    if cancel::is_cancelled() {
+        std::process::exit(cancel::EXIT_INTERRUPTED);
+    }
+}
+
+/// The run's `--correlation-id` (or a generated one), shared by every client
+/// the run builds.
+static CORRELATION_ID: OnceLock<String> = OnceLock::new();
+
+/// The id sent with every request of this run; announced on stderr once.
+fn correlation_id(matches: &clap::ArgMatches) -> &'static str {
+    CORRELATION_ID.get_or_init(|| {
+        let id = matches
+            .get_one::<String>("correlation_id")
+            .cloned()
+            .unwrap_or_else(random_uuid);
+        eprintln!("correlation id: {id}");
+        id
+    })
+}
+
+/// A version 4 UUID; std's randomly keyed hasher supplies the bits.
+fn random_uuid() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let bits = |salt: u64| {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u64(salt);
+        hasher.finish()
+    };
+    let high = (bits(0) & 0xffff_ffff_ffff_0fff) | 0x4000;
+    let low = (bits(1) & 0x3fff_ffff_ffff_ffff) | 0x8000_0000_0000_0000;
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
 }
 
-fn run() -> Result<()> {
-    let tree = command_tree::load_command_tree();
-    let cli = build_cli(&tree);
-    let matches = cli.get_matches();
+/// Request counters for `--stats`, shared by every client the run builds.
+static STATS: OnceLock<Arc<metrics::Metrics>> = OnceLock::new();
+
+fn stats_enabled(matches: &clap::ArgMatches) -> bool {
+    matches.get_flag("stats") || matches.contains_id("stats_file")
+}
+
+/// Reports the run's request counters in `--stats-format`, to stderr or
+/// `--stats-file` (replaced atomically so a scraper never reads half a file).
+fn write_stats(matches: &clap::ArgMatches, run: std::time::Duration, success: bool) -> Result<()> {
+    if !stats_enabled(matches) {
+        return Ok(());
+    }
+    let stats = STATS.get().map(|m| m.snapshot()).unwrap_or_default();
+    let text = match matches
+        .get_one::<String>("stats_format")
+        .map(|v| v.as_str())
+    {
+        Some("prometheus") => stats.prometheus(run, success),
+        Some("json") => {
+            let mut value = serde_json::to_value(&stats)?;
+            value["error_responses"] = serde_json::json!(stats.error_responses());
+            value["latency_histogram"] = stats
+                .latency_buckets()
+                .map(|(le, count)| serde_json::json!({ "le": le, "count": count }))
+                .collect();
+            value["run_seconds"] = serde_json::json!(run.as_secs_f64());
+            value["success"] = Value::Bool(success);
+            format!("{value}\n")
+        }
+        _ => {
+            let statuses: Vec<String> = stats
+                .statuses
+                .iter()
+                .map(|(status, n)| format!("{status}: {n}"))
+                .collect();
+            let latency: Vec<String> = stats
+                .latency_buckets()
+                .filter(|(_, n)| *n > 0)
+                .map(|(le, n)| match le {
+                    Some(le) => format!("<={le}s: {n}"),
+                    None => format!(">{}s: {n}", metrics::LATENCY_BUCKETS.last().unwrap_or(&0.0)),
+                })
+                .collect();
+            format!(
+                "stats: {} requests ({}), {} error responses, {} transport errors, {} retries ({:.2}s waiting), {:.2}s in requests, {:.2}s total\n{}",
+                stats.requests,
+                if statuses.is_empty() {
+                    "no responses".to_string()
+                } else {
+                    statuses.join(", ")
+                },
+                stats.error_responses(),
+                stats.transport_errors,
+                stats.retries,
+                stats.retry_wait_seconds,
+                stats.request_seconds,
+                run.as_secs_f64(),
+                if latency.is_empty() {
+                    String::new()
+                } else {
+                    format!("latency: {}\n", latency.join(", "))
+                }
+            )
+        }
+    };
+    let Some(path) = matches.get_one::<String>("stats_file") else {
+        eprint!("{text}");
+        return Ok(());
+    };
+    let path = std::path::Path::new(path);
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(std::path::Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|err| anyhow!("write stats file {}: {err}", path.display()))?;
+    file.write_all(text.as_bytes())
+        .map_err(|err| anyhow!("write stats file {}: {err}", path.display()))?;
+    // Temp files are owner-only; collectors often run as another user.
+    #[cfg(unix)]
+    std::fs::set_permissions(
+        file.path(),
+        std::os::unix::fs::PermissionsExt::from_mode(0o644),
+    )
+    .map_err(|err| anyhow!("write stats file {}: {err}", path.display()))?;
+    file.persist(path)
+        .map_err(|err| anyhow!("write stats file {}: {err}", path.display()))?;
+    Ok(())
+}
 
+fn run(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     if let Some(matches) = matches.subcommand_matches("list") {
-        return handle_list(&tree, matches);
+        return handle_list(tree, matches);
     }
     if let Some(matches) = matches.subcommand_matches("describe") {
-        return handle_describe(&tree, matches);
+        return handle_describe(tree, matches);
     }
     if let Some(matches) = matches.subcommand_matches("tree") {
-        return handle_tree(&tree, matches);
+        return handle_tree(tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("validate-tree") {
+        return handle_validate_tree(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("docs") {
+        return handle_docs(tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("schema") {
+        return handle_schema(tree, matches);
     }
     if let Some(matches) = matches.subcommand_matches("raw") {
-        return handle_raw(&tree, &matches);
+        return handle_raw(tree, matches);
+    }
+    if matches.get_flag("explain")
+        && let Some(name @ ("ping" | "run-pipeline" | "batch" | "version-check" | "auth" | "diff")) =
+            matches.subcommand_name()
+    {
+        return Err(anyhow!(
+            "--explain describes a single operation or raw request, not `{name}`"
+        ));
+    }
+    if let Some(matches) = matches.subcommand_matches("ping") {
+        return handle_ping(tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("run-pipeline") {
+        return handle_pipeline(tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("batch") {
+        return handle_batch(tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("version-check") {
+        return handle_version_check(tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        return handle_diff(tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("auth") {
+        return handle_auth(tree, matches);
+    }
+    if let Some(matches) = matches
+        .subcommand_matches("config")
+        .and_then(|m| m.subcommand_matches("check"))
+    {
+        return handle_config_check(tree, matches);
     }
 
-    let config = load_config(&tree, &matches)?;
+    let mut config = load_config(tree, matches)?;
     setup_logging(matches.get_flag("debug"))?;
 
-    let client = PinterestClient::new(config.base_url.clone(), config.timeout)?;
+    let client = session::prepare(&api_client(matches, &config)?, &mut config)?;
 
-    let pretty = matches.get_flag("pretty");
-    let raw_output = matches.get_flag("raw_output");
+    let mut out = output_options(matches, &config)?;
+    let raw_output = raw_output(matches, &config);
     let all = matches.get_flag("all");
-    let max_pages = matches.get_one::<u64>("max_pages").copied().unwrap_or(0);
-    let max_items = matches.get_one::<u64>("max_items").copied().unwrap_or(0);
+    let limits = page_limits(matches);
 
     let (res_name, res_matches) = matches
         .subcommand()
@@ -57,175 +278,1965 @@ fn run() -> Result<()> {
         .subcommand()
         .ok_or_else(|| anyhow!("operation required"))?;
 
+    let method = find_op(tree, res_name, op_name).map_or("POST", |op| op.method.as_str());
+    config.policy.check(res_name, op_name, method)?;
+    for (res, name) in underlying_ops(res_name, op_name, op_matches) {
+        if let Some(op) = find_op(tree, res, name) {
+            config.policy.check(res, name, &op.method)?;
+        }
+    }
+
+    if matches.get_flag("explain")
+        && (matches!(
+            (res_name, op_name),
+            ("media", "upload") | ("conversions", "send")
+        ) || matches.contains_id("each_from")
+            || several_ad_accounts(matches))
+    {
+        return Err(anyhow!(
+            "--explain describes a single request; {res_name} {op_name} would send several here"
+        ));
+    }
     if res_name == "media" && op_name == "upload" {
-        return handle_media_upload(&client, &config, op_matches, pretty);
+        // Registration and processing polls share `media create`'s timeout.
+        let client = with_op_timeout(&client, matches, find_op(tree, "media", "create"));
+        return handle_media_upload(&client, &config, op_matches, &out);
+    }
+    if res_name == "conversions" && op_name == "send" {
+        return handle_conversions_send(tree, &client, &config, op_matches);
     }
 
-    let op = find_op(&tree, res_name, op_name)
+    let op = find_op(tree, res_name, op_name)
         .ok_or_else(|| anyhow!("unknown command {res_name} {op_name}"))?;
 
+    if op_matches
+        .try_get_one::<bool>("example")
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false)
+    {
+        return handle_example(tree, op);
+    }
+
+    if op.deprecated {
+        let mut message = format!("{res_name} {op_name} is deprecated");
+        if let Some(replacement) = &op.replacement {
+            message.push_str(&format!("; use {replacement} instead"));
+        }
+        if matches.get_flag("strict") {
+            return Err(anyhow!("{message} (--strict)"));
+        }
+        log::warn!("{message}");
+    }
+
+    check_scopes(op, &config, matches)?;
+    let client = with_op_timeout(&client, matches, Some(op))
+        .with_param_headers(build_header_params(op, op_matches)?)
+        .with_required_scopes(required_scopes(op));
+    if matches.contains_id("each_from") {
+        return handle_bulk(&client, &config, op, op_matches, matches, &out);
+    }
+    let accounts: Vec<&String> = matches
+        .get_many::<String>("ad_account_id")
+        .into_iter()
+        .flatten()
+        .collect();
+    if accounts.len() > 1 {
+        return handle_accounts(&client, &config, op, op_matches, matches, &out, &accounts);
+    }
+
     let auth = select_auth(op, &config)?;
-    let path = build_path(op, op_matches, &config)?;
-    let url = client.build_url(&path);
+    let path = build_path(op, op_matches, &config, None)?;
+    let url = client.build_url(&path)?;
+    confirm_destructive(op, &format!("{} {url}", op.method), matches)?;
+
+    let mut query = build_query_params(op, op_matches, &config.sources)?;
+    let filters: Vec<String> = matches
+        .get_many::<String>("filter")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    let plan = query::plan_filters(&filters, &op.params)?;
+    query.extend(plan.server);
+    out.filters = plan.client;
+    // Under --raw a list op prints the whole page, not the items the schema describes.
+    if !(raw_output && op.list_key.is_some()) {
+        out.columns = op.response_fields.clone().unwrap_or_default();
+    }
+    apply_page_size(op, &mut query, all || matches.get_flag("count"))?;
+    apply_date_window(op, &mut query, matches)?;
+    let mut body = if op_matches
+        .try_get_one::<bool>("interactive")
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false)
+    {
+        Some(Body::Json(prompt_body(tree, op, matches)?))
+    } else {
+        let template = body_template(config.template_dir.as_deref(), res_name, op)?;
+        build_body(op, op_matches, &config.sources, template)?
+    };
+    let mut method = op.method.as_str();
+
+    if matches.get_flag("params_in_body") {
+        if method != "GET" || body.is_some() || (all && op.paginated) {
+            return Err(anyhow!(
+                "--params-in-body only applies to single GET requests without a body"
+            ));
+        }
+        let mut params = query::pairs_to_json(&query);
+        // A single value for an array param must still be sent as an array.
+        for param in op.params.iter().filter(|p| p.schema_type == "array") {
+            if let Some(v) = params.get_mut(&param.name)
+                && !v.is_array()
+            {
+                *v = Value::Array(vec![v.take()]);
+            }
+        }
+        body = Some(Body::Json(params));
+        query = Vec::new();
+        method = "POST";
+    }
+
+    if matches.get_flag("explain") {
+        let (_, why) = auth_choice(op, &config)?;
+        let text = explain(Some(op), method, &url, &why, &query, body.as_ref());
+        return write_stdout_line(&text);
+    }
+    if print_curl(matches, &client, method, &url, &auth, &query, body.as_ref())? {
+        return Ok(());
+    }
+
+    if matches.get_flag("follow") {
+        if method != "GET" {
+            return Err(anyhow!("--follow only applies to GET operations"));
+        }
+        return follow(
+            &client, op, &url, &auth, &query, matches, &mut out, raw_output,
+        );
+    }
+
+    if matches.get_flag("count") {
+        let count = if op.paginated {
+            pagination::count_all(&client, op.method.as_str(), &url, &auth, &query, limits)?
+        } else {
+            let response = client.request(method, &url, &auth, &query, body)?;
+            response_count(op, response)?
+        };
+        return out.emit(&serde_json::json!({ "count": count }));
+    }
+
+    if op_matches
+        .try_get_one::<bool>("verify_media")
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false)
+    {
+        let Some(Body::Json(value)) = &body else {
+            return Err(anyhow!(
+                "--verify-media needs a JSON --body (not --body-stream)"
+            ));
+        };
+        media_upload::verify_media_ids(&client, &auth, value)?;
+    }
+
+    if let Some(specs) = op_matches
+        .try_get_many::<String>("if_unchanged")
+        .ok()
+        .flatten()
+    {
+        let guard = ReadGuard {
+            tree,
+            resource: res_name,
+            op,
+            matches: op_matches,
+            config: &config,
+        };
+        guard.check(&client, &auth, body.as_ref(), specs)?;
+    }
 
-    let query = build_query_params(op, op_matches)?;
-    let body = build_body(op, op_matches)?;
+    if matches.get_flag("stream") {
+        if !op.paginated {
+            return Err(anyhow!("--stream needs a paginated list operation"));
+        }
+        let positions = matches.get_flag("stream_position");
+        pagination::for_each_item(
+            &client,
+            &op.method,
+            &url,
+            &auth,
+            &query,
+            limits,
+            |item, position| out.emit_stream_item(item, positions.then_some(position)),
+        )?;
+        return Ok(());
+    }
+    if matches.get_flag("stream_response") {
+        if method != "GET" || all {
+            return Err(anyhow!(
+                "--stream-response needs a single GET (for paginated lists, use --all --stream)"
+            ));
+        }
+        client.stream_items(&url, &auth, &query, |item| {
+            out.emit_stream_item(&item, None)
+        })?;
+        return Ok(());
+    }
 
-    let response = if all && op.paginated {
-        pagination::paginate_all(
+    let show_bookmark = matches.get_flag("show_bookmark");
+    if show_bookmark && (!op.paginated || all) {
+        return Err(anyhow!(
+            "--show-bookmark needs a single page of a paginated list operation (not --all)"
+        ));
+    }
+    let page_callback = PageCallback::from_matches(matches, &config, &client)?;
+    if page_callback.is_some() && !(all && op.paginated) {
+        return Err(anyhow!(
+            "--page-callback needs --all on a paginated list operation"
+        ));
+    }
+    let auto_batch = matches.get_one::<u64>("auto_batch").map(|n| *n as usize);
+    let mut late_failure = None;
+    let (response, status) = if let Some(size) = auto_batch {
+        let concurrency = matches
+            .get_one::<u64>("auto_batch_concurrency")
+            .map_or(1, |n| *n as usize);
+        let adaptive = adaptive_limit(matches, concurrency);
+        let client = match &adaptive {
+            Some(limit) => client.with_adaptive_limit(Arc::clone(limit)),
+            None => client.clone(),
+        };
+        let chunked = send_chunks(
+            &client,
+            method,
+            &url,
+            &auth,
+            &query,
+            body,
+            size,
+            concurrency,
+            adaptive.as_deref(),
+        )?;
+        late_failure = chunked.failure;
+        (chunked.merged, 200)
+    } else if all && op.paginated {
+        let items = PageCallback::paginate(
+            page_callback.as_ref(),
             &client,
             op.method.as_str(),
             &url,
             &auth,
             &query,
-            max_pages,
-            max_items,
-        )?
+            limits,
+        );
+        match allow_404(items, method, matches)? {
+            // Every page succeeded, or paginate_all would have failed; with
+            // --continue-on-error, a failed page is reported after the output.
+            Some(items) => {
+                late_failure = pagination::failed_page(&items);
+                (items, 200)
+            }
+            None => (Value::Null, 404),
+        }
     } else {
-        client.request(op.method.as_str(), &url, &auth, &query, body)?
+        let resp = client.request_response(method, &url, &auth, &query, body);
+        match allow_404(resp, method, matches)? {
+            Some(resp) => {
+                check_success_code(op, resp.status, matches)?;
+                let resp = follow_accepted(&client, &auth, matches, &url, resp)?;
+                (resp.body, resp.status.as_u16())
+            }
+            None => (Value::Null, 404),
+        }
     };
+    if matches.get_flag("validate_response") && !response.is_null() {
+        let label = format!("{res_name} {}", op.name);
+        validate_response(tree, op, &label, &response, matches)?;
+    }
+    let bookmark = show_bookmark.then(|| pagination::next_bookmark(&response).map(str::to_string));
+    if matches.get_flag("envelope") {
+        out.envelope = Some(Envelope {
+            method: method.to_string(),
+            url: query::append_to_url(&url, &query),
+            status,
+            bookmark: bookmark.clone().map(Value::from),
+        });
+    } else if let Some(bookmark) = &bookmark {
+        // On stderr, so stdout stays the page's items.
+        match bookmark {
+            Some(bookmark) => eprintln!("next page: --bookmark {bookmark}"),
+            None => eprintln!("next page: none (last page)"),
+        }
+    }
 
-    let output = if raw_output {
-        response
-    } else if let Some(items) = response.get("items") {
-        items.clone()
-    } else {
-        response
+    // The merged `--auto-batch` result keeps its `{items, other}` shape.
+    out.emit(&unwrap_items(
+        op,
+        response,
+        raw_output || auto_batch.is_some(),
+    ))?;
+    late_failure.map_or(Ok(()), Err)
+}
+
+/// `--validate-response`: compares a result with the operation's response
+/// schema (`--all` results are `{items}` like a page). Differences are
+/// warnings, or an error under `--strict`.
+fn validate_response(
+    tree: &CommandTree,
+    op: &Operation,
+    label: &str,
+    response: &Value,
+    matches: &clap::ArgMatches,
+) -> Result<()> {
+    let Some(schema) = &op.response_schema else {
+        log::warn!("--validate-response: the command tree has no response schema for {label}");
+        return Ok(());
+    };
+    let issues = schema::check_value(&schema::resolve_refs(tree, schema), response);
+    if issues.is_empty() {
+        log::debug!("response matches the {label} schema");
+        return Ok(());
+    }
+    if matches.get_flag("strict") {
+        return Err(anyhow!(
+            "response doesn't match the {label} schema:\n  - {}",
+            issues.join("\n  - ")
+        ));
+    }
+    for issue in &issues {
+        log::warn!("response doesn't match the {label} schema: {issue}");
+    }
+    Ok(())
+}
+
+/// What [`send_chunks`] got back: the chunks' responses merged, and an error
+/// naming the chunks that failed, if any did.
+struct Chunked {
+    merged: Value,
+    failure: Option<anyhow::Error>,
+}
+
+/// `--auto-batch`: sends a JSON array body as consecutive chunks of `size`
+/// items, `concurrency` at a time (or as many as `adaptive` allows), and
+/// merges the responses in chunk order into `{"items": [...], "other": [...]}`:
+/// `items` concatenates their `items[]` (or the arrays themselves), `other`
+/// holds any other response. A failed chunk doesn't stop the others; it is
+/// reported with its item range and left out of the merged result.
+#[allow(clippy::too_many_arguments)]
+fn send_chunks(
+    client: &PinterestClient,
+    method: &str,
+    url: &str,
+    auth: &Auth,
+    query: &[(String, String)],
+    body: Option<Body>,
+    size: usize,
+    concurrency: usize,
+    adaptive: Option<&AdaptiveLimit>,
+) -> Result<Chunked> {
+    let Some(Body::Json(Value::Array(items))) = body else {
+        return Err(anyhow!("--auto-batch needs a JSON array --body"));
     };
+    let chunks: Vec<&[Value]> = items.chunks(size).collect();
+    let label = |i: usize| {
+        let start = i * size;
+        let end = start + chunks[i].len() - 1;
+        format!("chunk {} (items {start}-{end})", i + 1)
+    };
+    log::debug!(
+        "--auto-batch: {} items in {} chunks of up to {size}",
+        items.len(),
+        chunks.len()
+    );
+    let results = parallel_map(chunks.len(), concurrency, adaptive, |i| {
+        let body = Body::Json(Value::Array(chunks[i].to_vec()));
+        let result = client
+            .request(method, url, auth, query, Some(body))
+            .map_err(|err| anyhow!("{}: {err:#}", label(i)));
+        if let Err(err) = &result {
+            log::warn!("{err}");
+        }
+        result
+    });
+
+    let mut merged_items = Vec::new();
+    let mut others = Vec::new();
+    let mut failed = Vec::new();
+    for (i, result) in results.into_iter().enumerate() {
+        match result {
+            Some(Ok(Value::Array(items))) => merged_items.extend(items),
+            Some(Ok(Value::Object(mut map))) if map.get("items").is_some_and(Value::is_array) => {
+                if let Some(Value::Array(items)) = map.remove("items") {
+                    merged_items.extend(items);
+                }
+            }
+            Some(Ok(other)) => others.push(other),
+            Some(Err(_)) => failed.push(label(i)),
+            None => failed.push(format!("{} (not sent)", label(i))),
+        }
+    }
+    let merged = serde_json::json!({ "items": merged_items, "other": others });
+    let failure = (!failed.is_empty()).then(|| {
+        anyhow!(
+            "{} of {} chunks failed: {}",
+            failed.len(),
+            chunks.len(),
+            failed.join(", ")
+        )
+    });
+    if let Some(limit) = adaptive {
+        log::debug!("adaptive concurrency ended at {}", limit.limit());
+    }
+    Ok(Chunked { merged, failure })
+}
+
+/// Under `--adaptive-concurrency`, a limit that starts at one call in flight
+/// and tunes itself up to `ceiling`; the bulk paths start `ceiling` workers
+/// and gate each call on it.
+fn adaptive_limit(matches: &clap::ArgMatches, ceiling: usize) -> Option<Arc<AdaptiveLimit>> {
+    matches
+        .get_flag("adaptive_concurrency")
+        .then(|| Arc::new(AdaptiveLimit::new(1, ceiling)))
+}
+
+/// Runs `task(i)` for every `i` in `0..count` on up to `workers` threads and
+/// returns the results in index order. A task that never started because the
+/// run was cancelled is `None`. With a `limit`, each task also holds one of
+/// its permits while it runs.
+fn parallel_map<T: Send>(
+    count: usize,
+    workers: usize,
+    limit: Option<&AdaptiveLimit>,
+    task: impl Fn(usize) -> T + Sync,
+) -> Vec<Option<T>> {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let results = std::sync::Mutex::new(
+        std::iter::repeat_with(|| None)
+            .take(count)
+            .collect::<Vec<Option<T>>>(),
+    );
+    std::thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, count.max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let _permit = limit.map(AdaptiveLimit::acquire);
+                    let i = next.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    if i >= count || cancel::is_cancelled() {
+                        break;
+                    }
+                    let result = task(i);
+                    results.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `--allow-404`: a GET answered with 404 yields `None` (printed as `null`)
+/// instead of an error. Other failures, and other methods, are unchanged.
+fn allow_404<T>(result: Result<T>, method: &str, matches: &clap::ArgMatches) -> Result<Option<T>> {
+    match result {
+        Err(err)
+            if method == "GET"
+                && matches.get_flag("allow_404")
+                && matches!(
+                    err.downcast_ref::<error::CliError>(),
+                    Some(error::CliError::Api { status: 404, .. })
+                ) =>
+        {
+            log::debug!("404 allowed: {err}");
+            Ok(None)
+        }
+        other => other.map(Some),
+    }
+}
+
+/// `--if-unchanged`: reads the resource through the resource's `get` op and
+/// refuses the update unless every condition holds. This is a client-side
+/// check, so a change landing between the read and the write isn't caught.
+struct ReadGuard<'a> {
+    tree: &'a CommandTree,
+    resource: &'a str,
+    op: &'a Operation,
+    matches: &'a clap::ArgMatches,
+    config: &'a Config,
+}
+
+impl ReadGuard<'_> {
+    fn check<'s>(
+        &self,
+        client: &PinterestClient,
+        auth: &Auth,
+        body: Option<&Body>,
+        specs: impl Iterator<Item = &'s String>,
+    ) -> Result<()> {
+        let read = find_op(self.tree, self.resource, "get")
+            .filter(|o| o.method == "GET")
+            .ok_or_else(|| {
+                anyhow!(
+                    "--if-unchanged: {} has no get operation to read the current state",
+                    self.resource
+                )
+            })?;
+
+        // Updates like `campaigns update` name the target by `id` in the body;
+        // it fills the get op's path param that no flag supplies.
+        let id = match body {
+            Some(Body::Json(Value::Array(items))) if items.len() == 1 => items[0].get("id"),
+            Some(Body::Json(Value::Array(_))) => {
+                return Err(anyhow!(
+                    "--if-unchanged needs a body that updates exactly one item"
+                ));
+            }
+            Some(Body::Json(value)) => value.get("id"),
+            _ => None,
+        };
+        let mut row = serde_json::Map::new();
+        if let Some(id) = id {
+            for param in read.params.iter().filter(|p| p.location == "path") {
+                let from_flag = self
+                    .matches
+                    .try_get_one::<String>(&param_key(param))
+                    .ok()
+                    .flatten()
+                    .is_some();
+                if !from_flag && param.name != "ad_account_id" {
+                    row.insert(param.name.clone(), id.clone());
+                }
+            }
+        }
+        let path = build_path(read, self.matches, self.config, Some(&row))?;
+        let current =
+            client
+                .uncached()
+                .request("GET", &client.build_url(&path)?, auth, &[], None)?;
 
-    write_json(&output, pretty)?;
+        for spec in specs {
+            let (spec_path, negate) = match spec.split_once("!=") {
+                Some((field, value)) => (format!("{field}={value}"), true),
+                None => (spec.clone(), false),
+            };
+            let filter = query::ClientFilter::parse(&spec_path, "--if-unchanged")?;
+            if filter.matches(&current) != negate {
+                continue;
+            }
+            let actual = output::lookup(&current, &filter.path)
+                .map_or_else(|| "missing".to_string(), |v| v.to_string());
+            return Err(anyhow!(
+                "--if-unchanged {spec} failed: {} is {actual} in {path}; {} not sent",
+                filter.path,
+                self.op.method
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Fails when `--config-stdin` has already consumed stdin, so `reader` (another
+/// use of stdin) doesn't go on with empty input. Every stdin reader calls this.
+fn claim_stdin(matches: &clap::ArgMatches, reader: &str, instead: &str) -> Result<()> {
+    if matches.get_flag("config_stdin") {
+        return Err(anyhow!(
+            "{reader} and --config-stdin both read stdin; {instead}"
+        ));
+    }
     Ok(())
 }
 
-struct Config {
-    base_url: String,
-    access_token: Option<String>,
-    client_id: Option<String>,
-    client_secret: Option<String>,
-    conversion_token: Option<String>,
-    ad_account_id: Option<String>,
-    timeout: Option<u64>,
+/// Asks before running a DELETE (or an op the command tree marks destructive)
+/// from an interactive terminal; scripts and `--assume-yes` are never prompted.
+fn confirm_destructive(op: &Operation, target: &str, matches: &clap::ArgMatches) -> Result<()> {
+    if !is_destructive(op) {
+        return Ok(());
+    }
+    confirm(target, matches)
 }
 
-fn load_config(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<Config> {
-    let base_url = matches
-        .get_one::<String>("base_url")
-        .cloned()
-        .or_else(|| env::var("PINTEREST_BASE_URL").ok())
-        .unwrap_or_else(|| tree.base_url.clone());
+fn is_destructive(op: &Operation) -> bool {
+    op.method == "DELETE" || op.destructive
+}
 
-    let access_token = matches
-        .get_one::<String>("access_token")
-        .cloned()
-        .or_else(|| env::var("PINTEREST_ACCESS_TOKEN").ok());
+/// The `[y/N]` prompt behind [`confirm_destructive`].
+fn confirm(target: &str, matches: &clap::ArgMatches) -> Result<()> {
+    if matches.get_flag("assume_yes")
+        || matches.get_flag("explain")
+        || !std::io::stdin().is_terminal()
+    {
+        return Ok(());
+    }
+    claim_stdin(
+        matches,
+        "the confirmation prompt",
+        "pass --assume-yes to skip it",
+    )?;
+    eprint!("about to {target}\ncontinue? [y/N] ");
+    std::io::stderr().flush().ok();
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .context("read confirmation")?;
+    if matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        return Ok(());
+    }
+    Err(anyhow!("aborted; pass --assume-yes to skip this prompt"))
+}
 
-    let client_id = matches
-        .get_one::<String>("client_id")
-        .cloned()
-        .or_else(|| env::var("PINTEREST_CLIENT_ID").ok());
+fn page_limits(matches: &clap::ArgMatches) -> pagination::PageLimits {
+    pagination::PageLimits {
+        max_pages: matches.get_one::<u64>("max_pages").copied().unwrap_or(0),
+        max_items: matches.get_one::<u64>("max_items").copied().unwrap_or(0),
+        continue_on_error: matches.get_flag("continue_on_error"),
+    }
+}
 
-    let client_secret = matches
-        .get_one::<String>("client_secret")
-        .cloned()
-        .or_else(|| env::var("PINTEREST_CLIENT_SECRET").ok());
+/// `--raw`/`--items-only` for this call, else the configured default.
+fn raw_output(matches: &clap::ArgMatches, config: &Config) -> bool {
+    if matches.get_flag("items_only") {
+        return false;
+    }
+    matches.get_flag("raw_output") || config.default_raw_output
+}
 
-    let conversion_token = matches
-        .get_one::<String>("conversion_token")
-        .cloned()
-        .or_else(|| env::var("PINTEREST_CONVERSION_TOKEN").ok());
+/// Flags a 2xx the spec doesn't list for the op (e.g. 200 where 202 means the
+/// async path was taken): a warning, or an error under `--strict`.
+fn check_success_code(
+    op: &Operation,
+    status: reqwest::StatusCode,
+    matches: &clap::ArgMatches,
+) -> Result<()> {
+    let Some(codes) = op.success_codes.as_ref().filter(|c| !c.is_empty()) else {
+        return Ok(());
+    };
+    if codes.contains(&status.as_u16()) {
+        return Ok(());
+    }
+    let expected = codes
+        .iter()
+        .map(u16::to_string)
+        .collect::<Vec<_>>()
+        .join(" or ");
+    let message = format!(
+        "{} {} returned {status}; the spec expects {expected}",
+        op.method, op.path
+    );
+    if matches.get_flag("strict") {
+        return Err(anyhow!("{message} (--strict)"));
+    }
+    log::warn!("{message}");
+    Ok(())
+}
 
-    let ad_account_id = matches
-        .get_one::<String>("ad_account_id")
-        .cloned()
-        .or_else(|| env::var("PINTEREST_AD_ACCOUNT_ID").ok());
-
-    let timeout = matches.get_one::<u64>("timeout").copied();
-
-    Ok(Config {
-        base_url,
-        access_token,
-        client_id,
-        client_secret,
-        conversion_token,
-        ad_account_id,
-        timeout,
-    })
+/// With `--prefer`, a 202 carrying a monitor URL is polled until the operation
+/// finishes; with `--no-wait` the response becomes
+/// `{status, monitor_url, response}` instead. A relative monitor URL is
+/// resolved against `url`, the request's.
+fn follow_accepted(
+    client: &PinterestClient,
+    auth: &Auth,
+    matches: &clap::ArgMatches,
+    url: &str,
+    resp: ApiResponse,
+) -> Result<ApiResponse> {
+    if !matches.contains_id("prefer") {
+        return Ok(resp);
+    }
+    let Some(monitor_url) = monitor::monitor_url(&resp) else {
+        return Ok(resp);
+    };
+    let monitor_url = monitor::resolve(url, &monitor_url)?;
+    if matches.get_flag("no_wait") {
+        let body = serde_json::json!({
+            "status": resp.status.as_u16(),
+            "monitor_url": monitor_url,
+            "response": resp.body,
+        });
+        return Ok(ApiResponse { body, ..resp });
+    }
+    log::info!("accepted; waiting on {monitor_url}");
+    let timeout = matches
+        .get_one::<u64>("wait_timeout")
+        .map(|secs| std::time::Duration::from_secs(*secs))
+        .unwrap_or(monitor::DEFAULT_TIMEOUT);
+    monitor::wait_for_completion(client, auth, &monitor_url, timeout)
 }
 
-fn setup_logging(debug: bool) -> Result<()> {
-    if debug {
-        env_logger::Builder::from_env("RUST_LOG")
-            .filter_level(log::LevelFilter::Debug)
-            .init();
-    } else {
-        env_logger::Builder::from_env("RUST_LOG")
-            .filter_level(log::LevelFilter::Warn)
-            .init();
+/// `--follow`: repeats the GET on one client every `--interval` seconds until
+/// Ctrl-C. Rate limits are retried by the client as usual; a request that
+/// still fails (network, 429, 5xx) is skipped with a warning, while other API
+/// errors (bad auth, bad params) end the run since repeating won't fix them.
+#[allow(clippy::too_many_arguments)]
+fn follow(
+    client: &PinterestClient,
+    op: &Operation,
+    url: &str,
+    auth: &Auth,
+    query: &[(String, String)],
+    matches: &clap::ArgMatches,
+    out: &mut OutputOptions,
+    raw_output: bool,
+) -> Result<()> {
+    let interval =
+        std::time::Duration::from_secs(matches.get_one::<u64>("interval").copied().unwrap_or(60));
+    let changed_only = matches.get_flag("changed_only");
+    let mut last = None;
+    while !cancel::is_cancelled() {
+        let started = std::time::Instant::now();
+        match client.request_response("GET", url, auth, query, None) {
+            Ok(resp) => {
+                let result = unwrap_items(op, resp.body, raw_output);
+                if !(changed_only && last.as_ref() == Some(&result)) {
+                    if matches.get_flag("envelope") {
+                        out.envelope = Some(Envelope {
+                            method: "GET".to_string(),
+                            url: query::append_to_url(url, query),
+                            status: resp.status.as_u16(),
+                            bookmark: None,
+                        });
+                    }
+                    out.emit(&result)?;
+                }
+                last = Some(result);
+            }
+            Err(err) => match err.downcast_ref::<error::CliError>() {
+                Some(error::CliError::Api { status, .. }) if *status != 429 && *status < 500 => {
+                    return Err(err);
+                }
+                _ => log::warn!("--follow: {err}; trying again next interval"),
+            },
+        }
+        // Short naps so Ctrl-C ends the wait promptly.
+        while !cancel::is_cancelled() {
+            let remaining = interval.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+            std::thread::sleep(remaining.min(std::time::Duration::from_millis(200)));
+        }
     }
     Ok(())
 }
 
-fn build_cli(tree: &CommandTree) -> Command {
-    let mut cmd = Command::new("pinterest-ads")
-        .about("Pinterest Ads API CLI (auto-generated from OpenAPI)")
-        .version(env!("CARGO_PKG_VERSION"))
-        .subcommand_required(true)
-        .arg_required_else_help(true)
-        .arg(
-            Arg::new("access_token")
-                .long("access-token")
-                .global(true)
-                .value_name("TOKEN")
-                .help("Bearer access token (env: PINTEREST_ACCESS_TOKEN)"),
-        )
-        .arg(
-            Arg::new("client_id")
-                .long("client-id")
-                .global(true)
-                .value_name("ID")
-                .help("OAuth client id / app id (env: PINTEREST_CLIENT_ID)"),
-        )
-        .arg(
-            Arg::new("client_secret")
-                .long("client-secret")
-                .global(true)
-                .value_name("SECRET")
-                .help("OAuth client secret (env: PINTEREST_CLIENT_SECRET)"),
-        )
-        .arg(
-            Arg::new("conversion_token")
-                .long("conversion-token")
-                .global(true)
-                .value_name("TOKEN")
-                .help("Conversions API token (env: PINTEREST_CONVERSION_TOKEN)"),
-        )
+/// `--count` for a single response: a bare array, the op's list key, or else a
+/// `data`/`items` array.
+fn response_count(op: &Operation, response: Value) -> Result<u64> {
+    let list = match unwrap_items(op, response, false) {
+        Value::Array(items) => Some(items.len()),
+        other => ["data", "items"]
+            .iter()
+            .find_map(|key| other.get(*key)?.as_array().map(Vec::len)),
+    };
+    list.map(|n| n as u64)
+        .ok_or_else(|| anyhow!("--count needs a list operation (an array, or a data/items array)"))
+}
+
+/// Unwraps the op's declared list property (`items`, `data`, ...); anything
+/// else, or `--raw`, prints the whole response.
+fn unwrap_items(op: &Operation, response: Value, raw_output: bool) -> Value {
+    if raw_output {
+        return response;
+    }
+    match op.list_key.as_deref().and_then(|key| response.get(key)) {
+        Some(list) => list.clone(),
+        None => response,
+    }
+}
+
+/// Runs the op once per row of `--each-from` (a JSON array or NDJSON of objects
+/// keyed by path/query param name, overriding the flags) and emits one
+/// `{input, ok, result|error}` entry per row. A failing row doesn't stop the run.
+fn handle_bulk(
+    client: &PinterestClient,
+    config: &Config,
+    op: &Operation,
+    op_matches: &clap::ArgMatches,
+    matches: &clap::ArgMatches,
+    out: &OutputOptions,
+) -> Result<()> {
+    let source = matches
+        .get_one::<String>("each_from")
+        .ok_or_else(|| anyhow!("--each-from required"))?;
+    if matches.get_flag("params_in_body") || matches.contains_id("filter") {
+        return Err(anyhow!(
+            "--params-in-body and --filter cannot be combined with --each-from"
+        ));
+    }
+    if several_ad_accounts(matches) {
+        return Err(anyhow!(
+            "--each-from cannot be combined with several ad accounts"
+        ));
+    }
+    let rows = parse_rows(&sources::read_source_to_string(source, &config.sources)?)?;
+    confirm_destructive(
+        op,
+        &format!(
+            "{} {} for {} rows of {source}",
+            op.method,
+            op.path,
+            rows.len()
+        ),
+        matches,
+    )?;
+    for key in rows.iter().flat_map(|row| row.keys()) {
+        if !op
+            .params
+            .iter()
+            .any(|p| &p.name == key && matches!(p.location.as_str(), "path" | "query"))
+        {
+            return Err(anyhow!(
+                "--each-from key {key:?} is not a path or query param of {}",
+                op.name
+            ));
+        }
+    }
+
+    let auth = select_auth(op, config)?;
+    let template = body_template(
+        config.template_dir.as_deref(),
+        matches.subcommand_name().unwrap_or_default(),
+        op,
+    )?;
+    let body = build_body(op, op_matches, &config.sources, template)?;
+    let page_callback = PageCallback::from_matches(matches, config, client)?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        if cancel::is_cancelled() {
+            log::warn!(
+                "interrupted; returning {} rows processed so far",
+                results.len()
+            );
+            break;
+        }
+        let run_row = RowRun {
+            client,
+            config,
+            op,
+            op_matches,
+            matches,
+            auth: &auth,
+            body: body.as_ref(),
+            page_callback: page_callback.as_ref(),
+        };
+        results.push(match run_row.run(&row) {
+            Ok(result) => serde_json::json!({ "input": row, "ok": true, "result": result }),
+            Err(err) => {
+                serde_json::json!({ "input": row, "ok": false, "error": error::error_json(&err) })
+            }
+        });
+    }
+
+    if matches.get_flag("only_errors") {
+        let total = results.len();
+        results.retain(|r| r["ok"] == false);
+        let failed = results.len();
+        out.emit(&Value::Array(results))?;
+        if failed > 0 {
+            return Err(anyhow!("{failed} of {total} rows failed"));
+        }
+        return Ok(());
+    }
+    out.emit(&Value::Array(results))
+}
+
+/// The tree ops a command sends besides its own request, so the operation
+/// policy also covers what `media upload`, `conversions send` and
+/// `--verify-media` call under the hood.
+fn underlying_ops(
+    res_name: &str,
+    op_name: &str,
+    op_matches: &clap::ArgMatches,
+) -> Vec<(&'static str, &'static str)> {
+    let flag = |id: &str| {
+        op_matches
+            .try_get_one::<bool>(id)
+            .ok()
+            .flatten()
+            .copied()
+            .unwrap_or(false)
+    };
+    let mut ops = match (res_name, op_name) {
+        ("media", "upload") => vec![("media", "create")],
+        ("conversions", "send") => vec![("events", "create")],
+        _ => Vec::new(),
+    };
+    // Processing polls after an upload, and the check before a create.
+    let waits = res_name == "media"
+        && (flag("wait") || op_matches.try_contains_id("wait_until").unwrap_or(false));
+    if waits || flag("verify_media") {
+        ops.push(("media", "get"));
+    }
+    ops
+}
+
+/// Whether `--ad-account-id` was given more than one id.
+fn several_ad_accounts(matches: &clap::ArgMatches) -> bool {
+    matches
+        .get_many::<String>("ad_account_id")
+        .is_some_and(|ids| ids.len() > 1)
+}
+
+/// Fails if `--ad-account-id` was given more than one id, for commands that
+/// use it only as a default.
+fn single_ad_account(matches: &clap::ArgMatches, command: &str) -> Result<()> {
+    if several_ad_accounts(matches) {
+        return Err(anyhow!("{command} takes a single --ad-account-id"));
+    }
+    Ok(())
+}
+
+/// Runs `op` once per `--ad-account-id` value and emits one
+/// `{ad_account_id, items|result|error}` entry per account, in the order given.
+/// A failing account doesn't stop the others.
+fn handle_accounts(
+    client: &PinterestClient,
+    config: &Config,
+    op: &Operation,
+    op_matches: &clap::ArgMatches,
+    matches: &clap::ArgMatches,
+    out: &OutputOptions,
+    accounts: &[&String],
+) -> Result<()> {
+    if !op.params.iter().any(|p| p.name == "ad_account_id") {
+        return Err(anyhow!("{} does not take an ad account id", op.name));
+    }
+    for flag in ["params_in_body", "stream", "count"] {
+        if matches.get_flag(flag) {
+            return Err(anyhow!(
+                "--{} cannot be combined with several ad accounts",
+                flag.replace('_', "-")
+            ));
+        }
+    }
+    if matches.contains_id("filter") {
+        return Err(anyhow!(
+            "--filter cannot be combined with several ad accounts"
+        ));
+    }
+    confirm_destructive(
+        op,
+        &format!(
+            "{} {} for {} ad accounts",
+            op.method,
+            op.path,
+            accounts.len()
+        ),
+        matches,
+    )?;
+
+    let auth = select_auth(op, config)?;
+    let template = body_template(
+        config.template_dir.as_deref(),
+        matches.subcommand_name().unwrap_or_default(),
+        op,
+    )?;
+    let body = build_body(op, op_matches, &config.sources, template)?;
+    let page_callback = PageCallback::from_matches(matches, config, client)?;
+    let run = RowRun {
+        client,
+        config,
+        op,
+        op_matches,
+        matches,
+        auth: &auth,
+        body: body.as_ref(),
+        page_callback: page_callback.as_ref(),
+    };
+    let mut results = Vec::new();
+    for account in accounts {
+        if cancel::is_cancelled() {
+            log::warn!(
+                "interrupted; returning {} accounts processed so far",
+                results.len()
+            );
+            break;
+        }
+        let mut row = serde_json::Map::new();
+        row.insert(
+            "ad_account_id".to_string(),
+            Value::String(account.to_string()),
+        );
+        let mut entry = serde_json::json!({ "ad_account_id": account });
+        match run.run(&row) {
+            Ok(items @ Value::Array(_)) => entry["items"] = items,
+            Ok(result) => entry["result"] = result,
+            Err(err) => entry["error"] = error::error_json(&err),
+        }
+        results.push(entry);
+    }
+    out.emit(&Value::Array(results))
+}
+
+/// One run of an op with some path/query params taken from `row`, for
+/// `--each-from` rows and per-account runs.
+struct RowRun<'a> {
+    client: &'a PinterestClient,
+    config: &'a Config,
+    op: &'a Operation,
+    op_matches: &'a clap::ArgMatches,
+    matches: &'a clap::ArgMatches,
+    auth: &'a Auth,
+    body: Option<&'a Body>,
+    page_callback: Option<&'a PageCallback>,
+}
+
+impl RowRun<'_> {
+    fn run(&self, row: &serde_json::Map<String, Value>) -> Result<Value> {
+        let (op, matches) = (self.op, self.matches);
+        let all = matches.get_flag("all");
+        let path = build_path(op, self.op_matches, self.config, Some(row))?;
+        let url = self.client.build_url(&path)?;
+        let mut query = build_query_params(op, self.op_matches, &self.config.sources)?;
+        apply_row_query(op, &mut query, row);
+        apply_page_size(op, &mut query, all)?;
+        apply_date_window(op, &mut query, matches)?;
+        let response = if all && op.paginated {
+            let limits = page_limits(matches);
+            PageCallback::paginate(
+                self.page_callback,
+                self.client,
+                &op.method,
+                &url,
+                self.auth,
+                &query,
+                limits,
+            )?
+        } else {
+            let body = self.body.cloned();
+            self.client
+                .request(&op.method, &url, self.auth, &query, body)?
+        };
+        Ok(unwrap_items(op, response, raw_output(matches, self.config)))
+    }
+}
+
+/// Runs a pipeline's steps in order with one client, substituting
+/// `${steps.N.path}` from earlier results, and emits one
+/// `{step, op, ok, result|error}` entry per step run. The first failure stops
+/// the run unless `continue_on_error` is set (for the pipeline or the step).
+fn handle_pipeline(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let mut config = load_config(tree, matches)?;
+    setup_logging(matches.get_flag("debug"))?;
+    let client = session::prepare(&api_client(matches, &config)?, &mut config)?;
+    let out = output_options(matches, &config)?;
+    let raw_output = raw_output(matches, &config);
+    let file = matches
+        .get_one::<String>("file")
+        .ok_or_else(|| anyhow!("pipeline file required"))?;
+    single_ad_account(matches, "run-pipeline")?;
+    let pipeline = pipeline::parse(&sources::read_source_to_string(file, &config.sources)?)?;
+
+    // Resolve every op (and check the policy) before anything is sent.
+    let mut ops = Vec::new();
+    for (i, step) in pipeline.steps.iter().enumerate() {
+        let (res_name, op_name) = step
+            .op
+            .split_once(char::is_whitespace)
+            .map(|(r, o)| (r.trim(), o.trim()))
+            .ok_or_else(|| anyhow!("step {i}: op must be \"resource op\", got {:?}", step.op))?;
+        let op = find_op(tree, res_name, op_name)
+            .ok_or_else(|| anyhow!("step {i}: unknown command {res_name} {op_name}"))?;
+        config.policy.check(res_name, op_name, &op.method)?;
+        check_call_params(op, &step.params).map_err(|err| anyhow!("step {i}: {err}"))?;
+        ops.push(op);
+    }
+
+    let mut results: Vec<Option<Value>> = Vec::new();
+    let mut entries = Vec::new();
+    let mut failed = None;
+    for (i, (step, op)) in pipeline.steps.iter().zip(&ops).enumerate() {
+        if cancel::is_cancelled() {
+            log::warn!("interrupted; returning {i} steps run so far");
+            break;
+        }
+        let run_step = || -> Result<Value> {
+            let params = match pipeline::interpolate(&Value::Object(step.params.clone()), &results)?
+            {
+                Value::Object(map) => map,
+                _ => unreachable!("interpolation keeps the value's shape"),
+            };
+            let body = match (&step.body, &step.form) {
+                (Some(_), Some(_)) => {
+                    return Err(anyhow!(
+                        "body and form are mutually exclusive: a request has a single body"
+                    ));
+                }
+                (Some(body), None) => Some(Body::Json(pipeline::interpolate(body, &results)?)),
+                (None, Some(form)) => {
+                    match pipeline::interpolate(&Value::Object(form.clone()), &results)? {
+                        Value::Object(map) => Some(Body::Form(form_fields(map, &config.sources)?)),
+                        _ => unreachable!("interpolation keeps the value's shape"),
+                    }
+                }
+                (None, None) => None,
+            };
+            let call = Call {
+                client: &client,
+                matches,
+                config: &config,
+                raw_output,
+                confirmed: false,
+            };
+            call.run(op, &params, body, &format!("step {i}"))
+        };
+        match run_step() {
+            Ok(result) => {
+                entries.push(
+                    serde_json::json!({ "step": i, "op": step.op, "ok": true, "result": result }),
+                );
+                results.push(Some(result));
+            }
+            Err(err) => {
+                entries.push(serde_json::json!({
+                    "step": i,
+                    "op": step.op,
+                    "ok": false,
+                    "error": error::error_json(&err),
+                }));
+                results.push(None);
+                failed.get_or_insert((i, err.to_string()));
+                if !step.continue_on_error.unwrap_or(pipeline.continue_on_error) {
+                    break;
+                }
+            }
+        }
+    }
+
+    out.emit(&Value::Array(entries))?;
+    match failed {
+        Some((i, err)) => Err(anyhow!("step {i} ({}) failed: {err}", pipeline.steps[i].op)),
+        None => Ok(()),
+    }
+}
+
+/// `params` keys must name path or query params of `op`.
+fn check_call_params(op: &Operation, params: &serde_json::Map<String, Value>) -> Result<()> {
+    for key in params.keys() {
+        if !op
+            .params
+            .iter()
+            .any(|p| &p.name == key && matches!(p.location.as_str(), "path" | "query"))
+        {
+            return Err(anyhow!(
+                "{key:?} is not a path or query param of {} {}",
+                op.method,
+                op.path
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// What a standalone call (a pipeline step or a `batch` entry) runs with;
+/// its params come from the step, not from flags.
+struct Call<'a> {
+    client: &'a PinterestClient,
+    matches: &'a clap::ArgMatches,
+    config: &'a Config,
+    raw_output: bool,
+    /// Destructive calls were confirmed up front (batch, whose calls run on
+    /// worker threads that mustn't prompt at once).
+    confirmed: bool,
+}
+
+impl Call<'_> {
+    fn run(
+        &self,
+        op: &Operation,
+        params: &serde_json::Map<String, Value>,
+        body: Option<Body>,
+        label: &str,
+    ) -> Result<Value> {
+        let path = build_path(op, self.matches, self.config, Some(params))?;
+        let url = self.client.build_url(&path)?;
+        let mut query = Vec::new();
+        apply_row_query(op, &mut query, params);
+        if !self.confirmed {
+            confirm_destructive(op, &format!("{} {path} ({label})", op.method), self.matches)?;
+        }
+        let auth = select_auth(op, self.config)?;
+        check_scopes(op, self.config, self.matches)?;
+        let client = with_op_timeout(self.client, self.matches, Some(op))
+            .with_required_scopes(required_scopes(op));
+        let response = client.request(&op.method, &url, &auth, &query, body)?;
+        Ok(unwrap_items(op, response, self.raw_output))
+    }
+}
+
+/// Runs a JSON array of independent `{resource, op, params, body|form}` calls
+/// read from stdin (or a file), up to `--concurrency` at a time, and emits one
+/// `{index, resource, op, ok, result|error}` entry per call in input order.
+/// Unlike a pipeline nothing is shared between calls and a failure doesn't
+/// stop the others; the run fails at the end if any call did.
+fn handle_batch(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let mut config = load_config(tree, matches)?;
+    setup_logging(matches.get_flag("debug"))?;
+    let client = session::prepare(&api_client(matches, &config)?, &mut config)?;
+    let out = output_options(matches, &config)?;
+    let raw_output = raw_output(matches, &config);
+    single_ad_account(matches, "batch")?;
+    let text = match matches.get_one::<String>("file").map(String::as_str) {
+        None | Some("-") => {
+            claim_stdin(matches, "batch input", "pass the calls as FILE")?;
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)
+                .context("read batch from stdin")?;
+            text
+        }
+        Some(file) => sources::read_source_to_string(file, &config.sources)?,
+    };
+    let calls = pipeline::parse_batch(&text)?;
+
+    // Resolve every op (and check the policy) before anything is sent.
+    let mut ops = Vec::new();
+    for (i, call) in calls.iter().enumerate() {
+        let op = find_op(tree, &call.resource, &call.op)
+            .ok_or_else(|| anyhow!("call {i}: unknown command {} {}", call.resource, call.op))?;
+        config.policy.check(&call.resource, &call.op, &op.method)?;
+        check_call_params(op, &call.params).map_err(|err| anyhow!("call {i}: {err}"))?;
+        if call.body.is_some() && call.form.is_some() {
+            return Err(anyhow!(
+                "call {i}: body and form are mutually exclusive: a request has a single body"
+            ));
+        }
+        ops.push(op);
+    }
+    // One prompt for every destructive call, before the workers start: calls
+    // prompting from several threads would race for stdin.
+    let mut destructive = Vec::new();
+    for (i, (call, op)) in calls.iter().zip(&ops).enumerate() {
+        if is_destructive(op) {
+            let path = build_path(op, matches, &config, Some(&call.params))
+                .map_err(|err| anyhow!("call {i}: {err}"))?;
+            destructive.push(format!("{} {path} (call {i})", op.method));
+        }
+    }
+    if !destructive.is_empty() {
+        confirm(&destructive.join("\n         "), matches)?;
+    }
+
+    let concurrency = matches
+        .get_one::<u64>("concurrency")
+        .map_or(4, |n| *n as usize)
+        .min(calls.len());
+    let adaptive = adaptive_limit(matches, concurrency);
+    let client = match &adaptive {
+        Some(limit) => client.with_adaptive_limit(Arc::clone(limit)),
+        None => client,
+    };
+    let runner = Call {
+        client: &client,
+        matches,
+        config: &config,
+        raw_output,
+        confirmed: true,
+    };
+    let entries: Vec<Value> = parallel_map(calls.len(), concurrency, adaptive.as_deref(), |i| {
+        let (call, op) = (&calls[i], ops[i]);
+        let result = (|| {
+            let body = match (&call.body, &call.form) {
+                (Some(body), _) => Some(Body::Json(body.clone())),
+                (None, Some(form)) => Some(Body::Form(form_fields(form.clone(), &config.sources)?)),
+                (None, None) => None,
+            };
+            runner.run(op, &call.params, body, &format!("call {i}"))
+        })();
+        let mut entry = serde_json::json!({
+            "index": i,
+            "resource": call.resource,
+            "op": call.op,
+            "ok": result.is_ok(),
+        });
+        match result {
+            Ok(result) => entry["result"] = result,
+            Err(err) => entry["error"] = error::error_json(&err),
+        }
+        entry
+    })
+    .into_iter()
+    .flatten()
+    .collect();
+    if entries.len() < calls.len() {
+        log::warn!(
+            "interrupted; returning {} of {} calls",
+            entries.len(),
+            calls.len()
+        );
+    }
+    if let Some(limit) = &adaptive {
+        log::debug!("adaptive concurrency ended at {}", limit.limit());
+    }
+    let failed = entries.iter().filter(|e| e["ok"] == false).count();
+    out.emit(&Value::Array(entries))?;
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {} calls failed", calls.len()));
+    }
+    Ok(())
+}
+
+/// Accepts a JSON array of objects or one JSON object per line.
+fn parse_rows(text: &str) -> Result<Vec<serde_json::Map<String, Value>>> {
+    let values: Vec<Value> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text).context("invalid JSON array for --each-from")?
+    } else {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("invalid JSON on --each-from line {}", i + 1))
+            })
+            .collect::<Result<_>>()?
+    };
+    values
+        .into_iter()
+        .map(|v| match v {
+            Value::Object(map) => Ok(map),
+            other => Err(anyhow!(
+                "--each-from rows must be JSON objects, got {other}"
+            )),
+        })
+        .collect()
+}
+
+fn row_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn apply_row_query(
+    op: &Operation,
+    query: &mut Vec<(String, String)>,
+    row: &serde_json::Map<String, Value>,
+) {
+    for param in op.params.iter().filter(|p| p.location == "query") {
+        let Some(value) = row.get(&param.name) else {
+            continue;
+        };
+        remove_query_key(query, &param.name, None);
+        let values = match value {
+            Value::Array(items) => items.iter().filter_map(row_value).collect(),
+            other => row_value(other).into_iter().collect::<Vec<_>>(),
+        };
+        for v in values {
+            query.push((param.name.clone(), v));
+        }
+    }
+}
+
+fn output_options(matches: &clap::ArgMatches, config: &Config) -> Result<OutputOptions> {
+    let template = matches
+        .get_one::<String>("template")
+        .map(|raw| sources::read_source_to_string(raw, &config.sources))
+        .transpose()?;
+    let redact = matches
+        .get_many::<String>("redact")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    let sort = matches
+        .get_many::<String>("sort_output")
+        .into_iter()
+        .flatten()
+        .map(|spec| {
+            let (path, descending) = match spec.rsplit_once(':') {
+                Some((path, "asc")) => (path, false),
+                Some((path, "desc")) => (path, true),
+                Some(_) => {
+                    return Err(anyhow!(
+                        "--sort-output {spec:?}: expected PATH, PATH:asc or PATH:desc"
+                    ));
+                }
+                None => (spec.as_str(), false),
+            };
+            Ok((path.trim().to_string(), descending))
+        })
+        .collect::<Result<_>>()?;
+    let scale = matches
+        .get_many::<String>("scale")
+        .into_iter()
+        .flatten()
+        .map(|spec| {
+            let (path, factor) = spec
+                .split_once('=')
+                .ok_or_else(|| anyhow!("--scale {spec:?}: expected PATH=FACTOR"))?;
+            let factor = factor
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .ok_or_else(|| anyhow!("--scale {spec:?}: factor must be a number"))?;
+            Ok((path.trim().trim_start_matches("$.").to_string(), factor))
+        })
+        .collect::<Result<_>>()?;
+    let indent = match (matches.get_one::<u8>("indent"), matches.get_flag("tab")) {
+        (Some(_), true) => return Err(anyhow!("--indent and --tab can't be combined")),
+        (Some(n), false) => Some(" ".repeat(usize::from(*n))),
+        (None, true) => Some("\t".to_string()),
+        (None, false) => None,
+    };
+    Ok(OutputOptions {
+        pretty: matches.get_flag("pretty") || indent.is_some(),
+        indent,
+        template,
+        redact,
+        strip_nulls: matches.get_flag("strip_nulls"),
+        scale,
+        output_template: matches.get_one::<String>("output_template").cloned(),
+        filters: Vec::new(),
+        sort,
+        compact_arrays: matches.get_flag("compact_arrays"),
+        raw_field: matches.get_one::<String>("raw_field").cloned(),
+        envelope: None,
+        csv: matches.get_flag("csv"),
+        columns: Vec::new(),
+        sink: output_sink(matches, Some(config))?,
+    })
+}
+
+/// `--output`: a file, or an http(s) webhook POSTed with `--output-header`s.
+fn output_sink(
+    matches: &clap::ArgMatches,
+    config: Option<&Config>,
+) -> Result<Option<Arc<output::Sink>>> {
+    let Some(target) = matches.get_one::<String>("output") else {
+        return Ok(None);
+    };
+    let headers = client::parse_headers(
+        matches
+            .get_many::<String>("output_header")
+            .into_iter()
+            .flatten(),
+    )?;
+    let http = webhook_client(config)?;
+    Ok(Some(Arc::new(output::Sink::parse(target, http, headers))))
+}
+
+fn webhook_client(config: Option<&Config>) -> Result<reqwest::blocking::Client> {
+    let mut http = reqwest::blocking::Client::builder().user_agent("pinterest-ads-cli/0.1.0");
+    if let Some(config) = config {
+        http = config.http_version.apply(http);
+        if let Some(seconds) = config.timeout {
+            http = http.timeout(std::time::Duration::from_secs(seconds));
+        }
+    }
+    http.build().context("build webhook client")
+}
+
+/// `--page-callback`: each page of an `--all` run POSTed to a webhook as it
+/// arrives. Failed deliveries are retried with backoff; a page that still
+/// fails is skipped with a warning, or ends the run under `--page-callback-fatal`.
+struct PageCallback {
+    sink: output::Sink,
+    retry: pinterest_ads::retry::RetryPolicy,
+    fatal: bool,
+}
+
+impl PageCallback {
+    fn from_matches(
+        matches: &clap::ArgMatches,
+        config: &Config,
+        client: &PinterestClient,
+    ) -> Result<Option<Self>> {
+        let Some(url) = matches.get_one::<String>("page_callback") else {
+            return Ok(None);
+        };
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err(anyhow!("--page-callback must be an http(s) URL, got {url}"));
+        }
+        let headers = client::parse_headers(
+            matches
+                .get_many::<String>("page_callback_header")
+                .into_iter()
+                .flatten(),
+        )?;
+        let retries = matches
+            .get_one::<u32>("page_callback_retries")
+            .copied()
+            .unwrap_or(3);
+        Ok(Some(Self {
+            sink: output::Sink::parse(url, webhook_client(Some(config))?, headers),
+            retry: client
+                .retry_policy()
+                .derive(retries, std::time::Duration::from_secs(1)),
+            fatal: matches.get_flag("page_callback_fatal"),
+        }))
+    }
+
+    /// Delivers page `number` (0-based).
+    fn deliver(&self, page: &Value, number: u64) -> Result<()> {
+        let text = page.to_string();
+        let mut retry = 0;
+        loop {
+            let err = match self.sink.write(&text, "application/json") {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            retry += 1;
+            if self.retry.allows(retry) && !cancel::is_cancelled() {
+                let delay = self.retry.delay(retry, None);
+                log::warn!("page {}: {err:#}; retrying in {delay:.1?}", number + 1);
+                std::thread::sleep(delay);
+                continue;
+            }
+            if self.fatal {
+                return Err(anyhow!("--page-callback: page {}: {err:#}", number + 1));
+            }
+            log::warn!("page {} not delivered, skipping: {err:#}", number + 1);
+            return Ok(());
+        }
+    }
+
+    /// [`pagination::paginate_all`], delivering pages when there is a callback.
+    fn paginate(
+        callback: Option<&Self>,
+        client: &PinterestClient,
+        method: &str,
+        url: &str,
+        auth: &Auth,
+        query: &[(String, String)],
+        limits: pagination::PageLimits,
+    ) -> Result<Value> {
+        pagination::paginate_all_with(client, method, url, auth, query, limits, |page, n| {
+            match callback {
+                Some(callback) => callback.deliver(page, n),
+                None => Ok(()),
+            }
+        })
+    }
+}
+
+fn setup_logging(debug: bool) -> Result<()> {
+    let level = if debug {
+        log::LevelFilter::Debug
+    } else {
+        log::LevelFilter::Warn
+    };
+    // Running the CLI twice in one process (tests) keeps the first logger.
+    let _ = env_logger::Builder::from_env("RUST_LOG")
+        .filter_level(level)
+        .try_init();
+    Ok(())
+}
+
+fn build_cli(tree: &CommandTree) -> Command {
+    let mut cmd = Command::new("pinterest-ads")
+        .about("Pinterest Ads API CLI (auto-generated from OpenAPI)")
+        .version(env!("CARGO_PKG_VERSION"))
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .global(true)
+                .value_name("PATH")
+                .help("Config file (env: PINTEREST_CONFIG; default: <config dir>/pinterest-ads/config.toml)"),
+        )
+        .arg(
+            Arg::new("config_stdin")
+                .long("config-stdin")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Read config keys as a JSON/YAML object from stdin (over the config file, under flags and env)"),
+        )
+        .arg(
+            Arg::new("no_env")
+                .long("no-env")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Ignore PINTEREST_* environment variables; settings come only from flags, the session and the config file"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .global(true)
+                .value_name("NAME")
+                .help("Use the [profiles.NAME] table of the config file over its top-level keys (env: PINTEREST_PROFILE)"),
+        )
+        .arg(
+            Arg::new("command_tree")
+                .long("command-tree")
+                .global(true)
+                .value_name("PATH")
+                .help("Build commands from this generated command tree JSON instead of the embedded one (env: PINTEREST_COMMAND_TREE)"),
+        )
+        .arg(
+            Arg::new("template_dir")
+                .long("template-dir")
+                .global(true)
+                .value_name("DIR")
+                .help("Merge DIR/<resource>_<op>.json, when present, under each --body (env: PINTEREST_TEMPLATE_DIR)"),
+        )
+        .arg(
+            Arg::new("access_token")
+                .long("access-token")
+                .global(true)
+                .value_name("TOKEN")
+                .help("Bearer access token (env: PINTEREST_ACCESS_TOKEN)"),
+        )
+        .arg(
+            Arg::new("access_token_file")
+                .long("access-token-file")
+                .global(true)
+                .value_name("PATH")
+                .conflicts_with("access_token")
+                .help("Read the access token from a file (env: PINTEREST_ACCESS_TOKEN_FILE)"),
+        )
+        .arg(
+            Arg::new("client_id")
+                .long("client-id")
+                .global(true)
+                .value_name("ID")
+                .help("OAuth client id / app id (env: PINTEREST_CLIENT_ID)"),
+        )
+        .arg(
+            Arg::new("client_secret")
+                .long("client-secret")
+                .global(true)
+                .value_name("SECRET")
+                .help("OAuth client secret (env: PINTEREST_CLIENT_SECRET)"),
+        )
+        .arg(
+            Arg::new("client_secret_file")
+                .long("client-secret-file")
+                .global(true)
+                .value_name("PATH")
+                .conflicts_with("client_secret")
+                .help("Read the client secret from a file (env: PINTEREST_CLIENT_SECRET_FILE)"),
+        )
+        .arg(
+            Arg::new("conversion_token")
+                .long("conversion-token")
+                .global(true)
+                .value_name("TOKEN")
+                .help("Conversions API token (env: PINTEREST_CONVERSION_TOKEN)"),
+        )
+        .arg(
+            Arg::new("conversion_token_file")
+                .long("conversion-token-file")
+                .global(true)
+                .value_name("PATH")
+                .conflicts_with("conversion_token")
+                .help("Read the conversions token from a file (env: PINTEREST_CONVERSION_TOKEN_FILE)"),
+        )
+        .arg(
+            Arg::new("ad_account_id")
+                .long("ad-account-id")
+                .global(true)
+                .value_name("ID")
+                .action(ArgAction::Append)
+                .value_delimiter(',')
+                .help("Default ad account id for ad_accounts/{ad_account_id} paths (env: PINTEREST_AD_ACCOUNT_ID); repeat or comma-separate to run once per account"),
+        )
+        .arg(
+            Arg::new("base_url")
+                .long("base-url")
+                .global(true)
+                .value_name("URL")
+                .help("API base URL (env: PINTEREST_BASE_URL)"),
+        )
+        .arg(
+            Arg::new("env")
+                .long("env")
+                .global(true)
+                .value_name("NAME")
+                .conflicts_with("base_url")
+                .help("Named environment whose base URL to use: production, sandbox, or one from [environments] in the config (env: PINTEREST_ENV)"),
+        )
+        .arg(
+            Arg::new("source_auth_header")
+                .long("source-auth-header")
+                .global(true)
+                .value_name("VALUE")
+                .help("Authorization header value for http(s) source downloads (env: PINTEREST_SOURCE_AUTH_HEADER)"),
+        )
+        .arg(
+            Arg::new("source_max_redirects")
+                .long("source-max-redirects")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Max redirects followed for http(s) source downloads (default 10)"),
+        )
+        .arg(
+            Arg::new("no_decompress")
+                .long("no-decompress")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Use .gz sources and gzip-encoded downloads as-is instead of gunzipping them"),
+        )
+        .arg(
+            Arg::new("input_format")
+                .long("input-format")
+                .global(true)
+                .value_parser(["json", "yaml"])
+                .help("Syntax of --body, --params and --form (default json; .yaml/.yml files are always YAML)"),
+        )
+        .arg(
+            Arg::new("s3_role_arn")
+                .long("s3-role-arn")
+                .global(true)
+                .value_name("ARN")
+                .help("IAM role assumed for s3:// sources (env: PINTEREST_S3_ROLE_ARN)"),
+        )
+        .arg(
+            Arg::new("s3_external_id")
+                .long("s3-external-id")
+                .global(true)
+                .value_name("ID")
+                .help("External id for --s3-role-arn (env: PINTEREST_S3_EXTERNAL_ID)"),
+        )
+        .arg(
+            Arg::new("s3_max_attempts")
+                .long("s3-max-attempts")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("Attempts for s3:// downloads; interrupted transfers resume (default 3)"),
+        )
+        .arg(
+            Arg::new("s3_timeout")
+                .long("s3-timeout")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Timeout for each S3 call to start responding (default 60)"),
+        )
+        .arg(
+            Arg::new("pretty")
+                .long("pretty")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Pretty-print JSON output"),
+        )
+        .arg(
+            Arg::new("indent")
+                .long("indent")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u8).range(0..=16))
+                .conflicts_with_all(["tab", "csv", "stream", "stream_response"])
+                .help("Pretty-print JSON output indented by N spaces per level (default 2)"),
+        )
+        .arg(
+            Arg::new("tab")
+                .long("tab")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["csv", "stream", "stream_response"])
+                .help("Pretty-print JSON output indented with tabs"),
+        )
+        .arg(
+            Arg::new("compact_arrays")
+                .long("compact-arrays")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Pretty-print JSON but keep arrays of scalars on one line"),
+        )
+        .arg(
+            Arg::new("raw_output")
+                .long("raw")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Return full API response (do not unwrap items[])"),
+        )
+        .arg(
+            Arg::new("items_only")
+                .long("items-only")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("raw_output")
+                .help("Unwrap items[] even when default_raw_output is configured"),
+        )
+        .arg(
+            Arg::new("body_stream")
+                .long("body-stream")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Stream a --body source to the API as-is instead of parsing it (large JSON bodies)"),
+        )
+        .arg(
+            Arg::new("auto_batch")
+                .long("auto-batch")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .conflicts_with_all(["all", "stream", "stream_response", "count", "each_from", "body_stream"])
+                .help("Send a JSON array --body as chunks of N items and merge the responses (for batch create/update endpoints with a per-request limit)"),
+        )
+        .arg(
+            Arg::new("auto_batch_concurrency")
+                .long("auto-batch-concurrency")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64).range(1..=32))
+                .requires("auto_batch")
+                .help("Chunks in flight at once under --auto-batch (default 1: one after another)"),
+        )
+        .arg(
+            Arg::new("adaptive_concurrency")
+                .long("adaptive-concurrency")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("For batch and --auto-batch: start with one call in flight and tune up to batch --concurrency or --auto-batch-concurrency; grow on success, halve on 429 or X-RateLimit-Remaining: 0"),
+        )
+        .arg(
+            Arg::new("gzip_body")
+                .long("gzip-body")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Gzip JSON request bodies and send Content-Encoding: gzip (only for endpoints that accept it)"),
+        )
+        .arg(
+            Arg::new("template")
+                .long("template")
+                .global(true)
+                .value_name("FILE")
+                .help("Render the (unwrapped) response through a Handlebars template instead of printing JSON"),
+        )
+        .arg(
+            Arg::new("output_template")
+                .long("output-template")
+                .global(true)
+                .value_name("PATTERN")
+                .conflicts_with("template")
+                .help("Write each item of an array result to its own file, e.g. \"out/{id}.json\""),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .global(true)
+                .value_name("FILE|URL")
+                .conflicts_with_all(["stream", "output_template"])
+                .help("Write the result to FILE (replaced atomically) or POST it to an http(s) webhook URL instead of stdout"),
+        )
         .arg(
-            Arg::new("ad_account_id")
-                .long("ad-account-id")
+            Arg::new("output_header")
+                .long("output-header")
                 .global(true)
-                .value_name("ID")
-                .help("Default ad account id for ad_accounts/{ad_account_id} paths (env: PINTEREST_AD_ACCOUNT_ID)"),
+                .value_name("NAME: VALUE")
+                .action(ArgAction::Append)
+                .requires("output")
+                .help("Header sent with --output webhook POSTs (repeatable), e.g. for auth"),
         )
         .arg(
-            Arg::new("base_url")
-                .long("base-url")
+            Arg::new("csv")
+                .long("csv")
                 .global(true)
-                .value_name("URL")
-                .help("API base URL (env: PINTEREST_BASE_URL)"),
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["pretty", "compact_arrays", "template", "output_template", "raw_field", "stream", "envelope"])
+                .help("Print results as CSV, with columns in response schema order"),
         )
         .arg(
-            Arg::new("pretty")
-                .long("pretty")
+            Arg::new("raw_field")
+                .long("raw-field")
+                .global(true)
+                .value_name("PATH")
+                .conflicts_with_all(["template", "output_template"])
+                .help("Print only the scalar at this dotted path (e.g. id, items.0.id), unquoted, for shell capture"),
+        )
+        .arg(
+            Arg::new("params_from_env")
+                .long("params-from-env")
+                .global(true)
+                .value_name("PREFIX")
+                .help("Read query params from env vars named PREFIX<param> (e.g. PINT_PARAM_granularity=DAY), under flags and --params"),
+        )
+        .arg(
+            Arg::new("envelope")
+                .long("envelope")
                 .global(true)
                 .action(ArgAction::SetTrue)
-                .help("Pretty-print JSON output"),
+                .conflicts_with_all(["stream", "count", "output_template", "each_from"])
+                .help("Wrap the result as {request: {method, url}, status, response}"),
         )
         .arg(
-            Arg::new("raw_output")
-                .long("raw")
+            Arg::new("filter")
+                .long("filter")
+                .global(true)
+                .value_name("FIELD=VALUE")
+                .action(ArgAction::Append)
+                .help("Filter results (repeatable); fields the endpoint accepts as query params are sent to the server, others are matched client-side"),
+        )
+        .arg(
+            Arg::new("redact")
+                .long("redact")
+                .global(true)
+                .value_name("PATH[,PATH...]")
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .help("Mask values at dotted JSON paths with \"***\" in the output (cosmetic, not a security boundary)"),
+        )
+        .arg(
+            Arg::new("sort_output")
+                .long("sort-output")
+                .global(true)
+                .value_name("PATH[:asc|desc]")
+                .action(ArgAction::Append)
+                .conflicts_with("stream")
+                .help("Sort the output array by the value at a dotted path, client-side (repeatable; first is most significant)"),
+        )
+        .arg(
+            Arg::new("scale")
+                .long("scale")
+                .global(true)
+                .value_name("PATH=FACTOR[,PATH=FACTOR...]")
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .help("Multiply numbers at a dotted JSON path by FACTOR in the output, e.g. spend_in_micro_dollar=1e-6 (repeatable)"),
+        )
+        .arg(
+            Arg::new("strip_nulls")
+                .long("strip-nulls")
                 .global(true)
                 .action(ArgAction::SetTrue)
-                .help("Return full API response (do not unwrap items[])"),
+                .help("Drop keys whose value is null from the output, at any depth"),
+        )
+        .arg(
+            Arg::new("error_format")
+                .long("error-format")
+                .global(true)
+                .value_name("FORMAT")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("How errors are printed to stderr; json includes structured detail (e.g. missing_path_param)"),
+        )
+        .arg(
+            Arg::new("json_errors_to_stdout")
+                .long("json-errors-to-stdout")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print the final error as --error-format json to stdout instead of stderr (for runners that only capture stdout); the exit code is still non-zero"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Turn warnings (e.g. deprecated operations, unexpected success codes) into errors"),
+        )
+        .arg(
+            Arg::new("validate_response")
+                .long("validate-response")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Check the response against the operation's response schema and warn about wrong types and missing or unknown fields (an error under --strict)"),
+        )
+        .arg(
+            Arg::new("assume_yes")
+                .long("assume-yes")
+                .short('y')
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Skip the confirmation prompt for destructive operations (DELETE)"),
         )
         .arg(
             Arg::new("debug")
@@ -234,6 +2245,36 @@ fn build_cli(tree: &CommandTree) -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Enable debug logging"),
         )
+        .arg(
+            Arg::new("log_file")
+                .long("log-file")
+                .global(true)
+                .value_name("PATH")
+                .help("Append one JSON line per HTTP request (time, method, url, status, duration; no secrets)"),
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("When the run ends, report request counts by status, retries and timing (stderr unless --stats-file)"),
+        )
+        .arg(
+            Arg::new("stats_format")
+                .long("stats-format")
+                .global(true)
+                .value_name("FORMAT")
+                .value_parser(["text", "json", "prometheus"])
+                .default_value("text")
+                .help("--stats output: text, json or prometheus (textfile collector format)"),
+        )
+        .arg(
+            Arg::new("stats_file")
+                .long("stats-file")
+                .global(true)
+                .value_name("PATH")
+                .help("Write --stats to PATH (replaced atomically), e.g. a node_exporter textfile .prom file; implies --stats"),
+        )
         .arg(
             Arg::new("timeout")
                 .long("timeout")
@@ -242,6 +2283,260 @@ fn build_cli(tree: &CommandTree) -> Command {
                 .value_parser(clap::value_parser!(u64))
                 .help("HTTP timeout in seconds"),
         )
+        .arg(
+            Arg::new("op_timeout")
+                .long("op-timeout")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Request timeout for this operation, overriding its default (media and report ops allow 300s) and --timeout"),
+        )
+        .arg(
+            Arg::new("cache_ttl")
+                .long("cache-ttl")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Reuse successful GET responses cached on disk for up to SECONDS instead of requesting again"),
+        )
+        .arg(
+            Arg::new("dedupe")
+                .long("dedupe")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Share one response between identical GETs in flight at the same time (e.g. repeated reads in a batch)"),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .global(true)
+                .value_name("DIR")
+                .help("Save every API response to DIR as a JSON fixture, for --replay"),
+        )
+        .arg(
+            Arg::new("replay")
+                .long("replay")
+                .global(true)
+                .value_name("DIR")
+                .conflicts_with("record")
+                .help("Answer every API request from the fixtures in DIR instead of the network (e.g. in CI)"),
+        )
+        .arg(
+            Arg::new("pool_max_idle_per_host")
+                .long("pool-max-idle-per-host")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Idle API connections kept per host for reuse (default: reqwest's)"),
+        )
+        .arg(
+            Arg::new("pool_idle_timeout")
+                .long("pool-idle-timeout")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Close idle pooled connections after SECONDS (default: reqwest's 90s)"),
+        )
+        .arg(
+            Arg::new("http1")
+                .long("http1")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("http2_prior_knowledge")
+                .help("Use HTTP/1.1 only (for proxies that break on HTTP/2)"),
+        )
+        .arg(
+            Arg::new("http2_prior_knowledge")
+                .long("http2-prior-knowledge")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Speak HTTP/2 without negotiating it first"),
+        )
+        .arg(
+            Arg::new("max_redirects")
+                .long("max-redirects")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Redirects the API client follows (default 10); 0 prints the 3xx status and Location instead"),
+        )
+        .arg(
+            Arg::new("print_curl")
+                .long("print-curl")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print the request as a curl command (credentials redacted) instead of sending it"),
+        )
+        .arg(
+            Arg::new("print_curl_insecure")
+                .long("print-curl-insecure")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Like --print-curl, but with real credentials"),
+        )
+        .arg(
+            Arg::new("explain")
+                .long("explain")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["print_curl", "print_curl_insecure"])
+                .help("Describe the request in plain language instead of sending it"),
+        )
+        .arg(
+            Arg::new("max_retries")
+                .long("max-retries")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .help("Retry 429 (rate limited) and 502/503/504 responses up to N times, honoring Retry-After (default 3)"),
+        )
+        .arg(
+            Arg::new("retry_methods")
+                .long("retry-methods")
+                .global(true)
+                .value_name("METHOD[,METHOD...]")
+                .help("Methods retried on 502/503/504, --retry-404 and --retry-if (default GET,HEAD; 429s are retried for any method)"),
+        )
+        .arg(
+            Arg::new("retry_max_delay")
+                .long("retry-max-delay")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Cap on the exponential backoff between retries (default 30)"),
+        )
+        .arg(
+            Arg::new("retry_after_cap")
+                .long("retry-after-cap")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Longest single wait honored for a server's Retry-After, with a warning when hit (default 300)"),
+        )
+        .arg(
+            Arg::new("no_retry_jitter")
+                .long("no-retry-jitter")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Wait the exact backoff delay instead of a random 50-100% of it"),
+        )
+        .arg(
+            Arg::new("retry_budget")
+                .long("retry-budget")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64))
+                .help("Total retries allowed across the whole run (e.g. all --each-from rows); then fail fast"),
+        )
+        .arg(
+            Arg::new("retry_404")
+                .long("retry-404")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .help("Retry --retry-methods requests (GET by default) that return 404 up to N times with backoff (eventual consistency)"),
+        )
+        .arg(
+            Arg::new("show_bookmark")
+                .long("show-bookmark")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("For one page of a list, print the next page's bookmark to stderr (or as `bookmark` under --envelope)"),
+        )
+        .arg(
+            Arg::new("allow_404")
+                .long("allow-404")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print null and exit 0 when a GET returns 404 (after any --retry-404), instead of failing"),
+        )
+        .arg(
+            Arg::new("retry_if")
+                .long("retry-if")
+                .global(true)
+                .value_name("PATH=VALUE")
+                .action(ArgAction::Append)
+                .help("Retry a successful GET while the response field equals VALUE (repeatable; any match retries)"),
+        )
+        .arg(
+            Arg::new("retry_if_max")
+                .long("retry-if-max")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .help("Max --retry-if retries (default 10)"),
+        )
+        .arg(
+            Arg::new("retry_if_any_method")
+                .long("retry-if-any-method")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("retry_if")
+                .help("Apply --retry-if to every method, not just --retry-methods (only for idempotent endpoints)"),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .short('H')
+                .global(true)
+                .value_name("NAME: VALUE")
+                .action(ArgAction::Append)
+                .help("Extra request header (repeatable); an explicit Authorization replaces the computed auth"),
+        )
+        .arg(
+            Arg::new("correlation_id")
+                .long("correlation-id")
+                .global(true)
+                .value_name("ID")
+                .help("Id sent as X-Request-Id on every request of the run (default: a random UUID, printed to stderr)"),
+        )
+        .arg(
+            Arg::new("prefer")
+                .long("prefer")
+                .global(true)
+                .value_name("VALUE")
+                .help("Send a Prefer header (e.g. respond-async); a 202 with a monitor URL is then polled to completion"),
+        )
+        .arg(
+            Arg::new("no_wait")
+                .long("no-wait")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("prefer")
+                .help("With --prefer, print a 202's monitor URL ({status, monitor_url, response}) instead of polling it"),
+        )
+        .arg(
+            Arg::new("wait_timeout")
+                .long("wait-timeout")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .requires("prefer")
+                .conflicts_with("no_wait")
+                .help("With --prefer, give up polling a 202's monitor URL after SECONDS (default 600)"),
+        )
+        .arg(
+            Arg::new("params_in_body")
+                .long("params-in-body")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Send a GET's query params as a JSON body via POST (only for endpoints that accept it)"),
+        )
+        .arg(
+            Arg::new("each_from")
+                .long("each-from")
+                .global(true)
+                .value_name("FILE")
+                .help("Run the command once per JSON object (array or NDJSON) of param values; reports ok/error per row"),
+        )
+        .arg(
+            Arg::new("only_errors")
+                .long("only-errors")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("each_from")
+                .help("With --each-from, print only failed rows and exit non-zero if any failed"),
+        )
         .arg(
             Arg::new("all")
                 .long("all")
@@ -249,21 +2544,143 @@ fn build_cli(tree: &CommandTree) -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Auto-paginate bookmark-based endpoints"),
         )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("all")
+                .conflicts_with_all(["count", "template", "output_template", "pretty", "compact_arrays", "each_from"])
+                .help("With --all, print each item as an NDJSON line as its page arrives"),
+        )
+        .arg(
+            Arg::new("stream_response")
+                .long("stream-response")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["all", "stream", "count", "template", "output_template", "pretty", "compact_arrays", "each_from", "envelope"])
+                .help("Parse a huge GET response incrementally, printing each element of its top-level array (or items[]) as an NDJSON line"),
+        )
+        .arg(
+            Arg::new("page_callback")
+                .long("page-callback")
+                .global(true)
+                .value_name("URL")
+                .requires("all")
+                .conflicts_with_all(["stream", "count"])
+                .help("With --all, POST each page's JSON body to URL as it arrives (e.g. a warehouse loader)"),
+        )
+        .arg(
+            Arg::new("page_callback_header")
+                .long("page-callback-header")
+                .global(true)
+                .value_name("NAME: VALUE")
+                .action(ArgAction::Append)
+                .requires("page_callback")
+                .help("Header sent with --page-callback POSTs (repeatable), e.g. for auth"),
+        )
+        .arg(
+            Arg::new("page_callback_retries")
+                .long("page-callback-retries")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .requires("page_callback")
+                .help("Retries per page when --page-callback delivery fails, with backoff (default 3)"),
+        )
+        .arg(
+            Arg::new("page_callback_fatal")
+                .long("page-callback-fatal")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("page_callback")
+                .help("Stop the run when a page can't be delivered, instead of skipping it with a warning"),
+        )
+        .arg(
+            Arg::new("stream_position")
+                .long("stream-position")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("stream")
+                .conflicts_with("raw_field")
+                .help("With --stream, add the item's page and index within it (0-based) as _page and _index"),
+        )
+        .arg(
+            Arg::new("since")
+                .long("since")
+                .global(true)
+                .value_name("DATE")
+                .help("Window start for the op's date params: YYYY-MM-DD, today, yesterday, 7d, 2w"),
+        )
+        .arg(
+            Arg::new("until")
+                .long("until")
+                .global(true)
+                .value_name("DATE")
+                .help("Window end for the op's date params (same formats as --since)"),
+        )
+        .arg(
+            Arg::new("page_size")
+                .long("page-size")
+                .global(true)
+                .value_name("N")
+                .help("Items per page (clamped to the endpoint max); --all defaults to the max"),
+        )
         .arg(
             Arg::new("max_pages")
                 .long("max-pages")
                 .global(true)
-                .value_name("N")
-                .value_parser(clap::value_parser!(u64))
-                .help("Max pages to fetch when --all"),
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64))
+                .help("Max pages to fetch when --all"),
+        )
+        .arg(
+            Arg::new("max_items")
+                .long("max-items")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64))
+                .help("Max items to fetch when --all"),
+        )
+        .arg(
+            Arg::new("continue_on_error")
+                .long("continue-on-error")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("With --all, keep the pages fetched before a failed one, end the output with a gap marker and exit non-zero"),
+        )
+        .arg(
+            Arg::new("count")
+                .long("count")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("each_from")
+                .help("Print {\"count\": N}: how many items a list op returns across all pages (uses a server-reported total when there is one)"),
+        )
+        .arg(
+            Arg::new("follow")
+                .long("follow")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["all", "stream", "count", "each_from", "params_in_body"])
+                .help("Re-run a GET every --interval seconds and print each result until Ctrl-C"),
+        )
+        .arg(
+            Arg::new("interval")
+                .long("interval")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("60")
+                .help("Seconds between --follow requests, counted from the start of each"),
         )
         .arg(
-            Arg::new("max_items")
-                .long("max-items")
+            Arg::new("changed_only")
+                .long("changed-only")
                 .global(true)
-                .value_name("N")
-                .value_parser(clap::value_parser!(u64))
-                .help("Max items to fetch when --all"),
+                .action(ArgAction::SetTrue)
+                .requires("follow")
+                .help("With --follow, print a result only when it differs from the last one printed"),
         );
 
     cmd = cmd.subcommand(
@@ -274,6 +2691,39 @@ fn build_cli(tree: &CommandTree) -> Command {
                     .long("json")
                     .action(ArgAction::SetTrue)
                     .help("Emit machine-readable JSON"),
+            )
+            .arg(
+                Arg::new("writes")
+                    .long("writes")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("reads")
+                    .help("Only mutating operations (POST/PATCH/PUT/DELETE)"),
+            )
+            .arg(
+                Arg::new("reads")
+                    .long("reads")
+                    .action(ArgAction::SetTrue)
+                    .help("Only read operations (GET)"),
+            )
+            .arg(
+                Arg::new("method")
+                    .long("method")
+                    .value_name("METHOD")
+                    .action(ArgAction::Append)
+                    .help("Only operations using this HTTP method (repeatable)"),
+            )
+            .arg(
+                Arg::new("tag")
+                    .long("tag")
+                    .value_name("NAME")
+                    .action(ArgAction::Append)
+                    .help("Only operations with this OpenAPI tag, across resources (repeatable)"),
+            )
+            .arg(
+                Arg::new("by_tag")
+                    .long("by-tag")
+                    .action(ArgAction::SetTrue)
+                    .help("Group operations by OpenAPI tag instead of resource"),
             ),
     );
 
@@ -290,6 +2740,31 @@ fn build_cli(tree: &CommandTree) -> Command {
             ),
     );
 
+    cmd = cmd.subcommand(
+        Command::new("docs")
+            .about("Export the command tree as reference docs")
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_name("markdown")
+                    .value_parser(["markdown"])
+                    .default_value("markdown"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("schema")
+            .about("Show request body and param schema for an operation")
+            .arg(Arg::new("resource").required(true))
+            .arg(Arg::new("op").required(true))
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit the resolved JSON Schema"),
+            ),
+    );
+
     cmd = cmd.subcommand(
         Command::new("tree").about("Show full command tree").arg(
             Arg::new("json")
@@ -299,6 +2774,179 @@ fn build_cli(tree: &CommandTree) -> Command {
         ),
     );
 
+    cmd = cmd.subcommand(
+        Command::new("validate-tree")
+            .about("Check a command_tree.json for structural problems (e.g. before passing it to --command-tree)")
+            .arg(
+                Arg::new("path")
+                    .value_name("PATH")
+                    .required(true)
+                    .help("Command tree file, as written by tools/gen_command_tree.py"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit machine-readable JSON"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("config")
+            .about("Inspect resolved configuration")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("check")
+                    .about("Show which settings are set and where from (secrets are not printed)")
+                    .arg(
+                        Arg::new("json")
+                            .long("json")
+                            .action(ArgAction::SetTrue)
+                            .help("Emit machine-readable JSON"),
+                    ),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("auth")
+            .about("Log in once and let later commands use the saved session")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("login")
+                    .about("Save an access token (or exchange an OAuth code) as the session")
+                    .arg(
+                        Arg::new("token")
+                            .long("token")
+                            .value_name("TOKEN")
+                            .conflicts_with("code")
+                            .help("Access token to save; read from stdin when neither this nor --code is given"),
+                    )
+                    .arg(
+                        Arg::new("code")
+                            .long("code")
+                            .value_name("CODE")
+                            .requires("redirect_uri")
+                            .help("OAuth authorization code to exchange (needs client id and secret)"),
+                    )
+                    .arg(
+                        Arg::new("redirect_uri")
+                            .long("redirect-uri")
+                            .value_name("URI")
+                            .requires("code")
+                            .help("Redirect URI the code was issued for"),
+                    )
+                    .arg(
+                        Arg::new("refresh_token")
+                            .long("refresh-token")
+                            .value_name("TOKEN")
+                            .conflicts_with("code")
+                            .help("Refresh token saved with --token, used once it expires"),
+                    )
+                    .arg(
+                        Arg::new("expires_in")
+                            .long("expires-in")
+                            .value_name("SECONDS")
+                            .value_parser(clap::value_parser!(i64).range(1..))
+                            .conflicts_with("code")
+                            .help("Lifetime of the --token access token"),
+                    )
+                    .arg(
+                        Arg::new("scope")
+                            .long("scope")
+                            .value_name("SCOPES")
+                            .conflicts_with("code")
+                            .help("Scopes the --token was granted (comma-separated), checked before each call"),
+                    ),
+            )
+            .subcommand(
+                Command::new("status")
+                    .about("Show the saved session, its expiry and whether it is in use")
+                    .arg(
+                        Arg::new("json")
+                            .long("json")
+                            .action(ArgAction::SetTrue)
+                            .help("Emit machine-readable JSON"),
+                    ),
+            )
+            .subcommand(Command::new("logout").about("Delete the saved session")),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("ping")
+            .about("Verify credentials: fetch the authenticated user (and default ad account)"),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("run-pipeline")
+            .about("Run the operations listed in a YAML/JSON pipeline file, in order")
+            .arg(
+                Arg::new("file")
+                    .required(true)
+                    .value_name("FILE|URL|S3")
+                    .help("Pipeline file: steps of {op, params, body|form, continue_on_error}"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("batch")
+            .about("Run a JSON array of independent operations read from stdin, several at a time")
+            .arg(
+                Arg::new("file")
+                    .value_name("FILE|URL|S3")
+                    .help("Read the calls from FILE instead of stdin (- for stdin): [{resource, op, params, body|form}]"),
+            )
+            .arg(
+                Arg::new("concurrency")
+                    .long("concurrency")
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(u64).range(1..=32))
+                    .default_value("4")
+                    .help("Calls in flight at once (the ceiling under --adaptive-concurrency)"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("diff")
+            .about("Compare a result with a saved baseline: items added, removed and changed")
+            .arg(
+                Arg::new("baseline")
+                    .long("baseline")
+                    .value_name("FILE")
+                    .required(true)
+                    .help("Earlier output of the same read (JSON, or NDJSON from --stream)"),
+            )
+            .arg(
+                Arg::new("current")
+                    .value_name("FILE|URL|S3")
+                    .help("The newer output; stdin when omitted or -"),
+            )
+            .arg(
+                Arg::new("key")
+                    .long("key")
+                    .value_name("PATH")
+                    .default_value("id")
+                    .help("Dotted path of the field that identifies an item"),
+            )
+            .arg(
+                Arg::new("update_baseline")
+                    .long("update-baseline")
+                    .action(ArgAction::SetTrue)
+                    .help("Replace the baseline with the current result afterwards (a missing baseline counts as empty)"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("version-check")
+            .about("Compare the command tree's API version with the latest published spec")
+            .arg(
+                Arg::new("spec_url")
+                    .long("spec-url")
+                    .value_name("URL")
+                    .default_value(LATEST_SPEC_URL)
+                    .help("OpenAPI spec to compare against"),
+            ),
+    );
+
     cmd = cmd.subcommand(
         Command::new("raw")
             .about("Make a raw API call")
@@ -314,13 +2962,21 @@ fn build_cli(tree: &CommandTree) -> Command {
                 Arg::new("params")
                     .long("params")
                     .value_name("JSON")
-                    .help("JSON object of query parameters"),
+                    .help("JSON object of query parameters (string or source)"),
+            )
+            .arg(
+                Arg::new("param")
+                    .long("param")
+                    .value_name("KEY=VALUE")
+                    .action(ArgAction::Append)
+                    .help("Query param by name (repeatable; repeat the key for several values); overrides --params"),
             )
             .arg(
                 Arg::new("body")
                     .long("body")
                     .value_name("JSON|@FILE|URL|S3")
-                    .help("JSON request body (string or source)"),
+                    .action(ArgAction::Append)
+                    .help("JSON request body (string or source); repeat to deep-merge objects, later wins"),
             )
             .arg(
                 Arg::new("form")
@@ -343,13 +2999,21 @@ fn build_cli(tree: &CommandTree) -> Command {
                 Arg::new("params")
                     .long("params")
                     .value_name("JSON")
-                    .help("JSON object of query parameters"),
+                    .help("JSON object of query parameters (string or source)"),
+            );
+            op_cmd = op_cmd.arg(
+                Arg::new("param")
+                    .long("param")
+                    .value_name("KEY=VALUE")
+                    .action(ArgAction::Append)
+                    .help("Query or path param by its API name (repeatable); overrides --params, overridden by the param's own flag"),
             );
             op_cmd = op_cmd.arg(
                 Arg::new("body")
                     .long("body")
                     .value_name("JSON|@FILE|URL|S3")
-                    .help("JSON request body (string or source)"),
+                    .action(ArgAction::Append)
+                    .help("JSON request body (string or source); repeat to deep-merge objects, later wins"),
             );
             op_cmd = op_cmd.arg(
                 Arg::new("form")
@@ -357,7 +3021,38 @@ fn build_cli(tree: &CommandTree) -> Command {
                     .value_name("JSON|@FILE|URL|S3")
                     .help("Form body as JSON object (for application/x-www-form-urlencoded)"),
             );
-            for param in &op.params {
+            if op.request_body.is_some() {
+                op_cmd = op_cmd.arg(
+                    Arg::new("example")
+                        .long("example")
+                        .action(ArgAction::SetTrue)
+                        .help("Print a skeleton request body for this operation and exit"),
+                );
+                op_cmd = op_cmd.arg(
+                    Arg::new("interactive")
+                        .long("interactive")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["body", "form", "example", "each_from"])
+                        .help("Prompt for each required body field (terminal only) instead of passing --body"),
+                );
+                op_cmd = op_cmd.arg(
+                    Arg::new("verify_media")
+                        .long("verify-media")
+                        .action(ArgAction::SetTrue)
+                        .help("Check that media_id values in --body have finished processing before sending"),
+                );
+            }
+            if matches!(op.method.as_str(), "PATCH" | "PUT") {
+                op_cmd = op_cmd.arg(
+                    Arg::new("if_unchanged")
+                        .long("if-unchanged")
+                        .value_name("FIELD=VALUE|FIELD!=VALUE")
+                        .action(ArgAction::Append)
+                        .conflicts_with("each_from")
+                        .help("Read the resource first and only send the update if the field still equals (or, with !=, differs from) VALUE"),
+                );
+            }
+            for param in op.params.iter().filter(|p| !is_global_param(p)) {
                 op_cmd = op_cmd.arg(build_param_arg(param));
             }
             res_cmd = res_cmd.subcommand(op_cmd);
@@ -376,14 +3071,49 @@ fn build_cli(tree: &CommandTree) -> Command {
                     .arg(
                         Arg::new("file")
                             .long("file")
-                            .value_name("FILE|URL|S3")
-                            .required(true),
+                            .value_name("FILE|GLOB|URL|S3")
+                            .required_unless_present("register_only")
+                            .help("Media source; a glob uploads every matching file"),
+                    )
+                    .arg(
+                        Arg::new("register_only")
+                            .long("register-only")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with_all(["file", "wait", "wait_until", "upload_params"])
+                            .help("Only register (POST /media) and print upload_url/upload_parameters for another system to upload"),
                     )
                     .arg(
                         Arg::new("wait")
                             .long("wait")
                             .action(ArgAction::SetTrue)
                             .help("Wait for processing to complete"),
+                    )
+                    .arg(
+                        Arg::new("wait_until")
+                            .long("wait-until")
+                            .value_name("STATUS")
+                            .action(ArgAction::Append)
+                            .help("Stop waiting once any listed status is reached (repeatable; implies --wait)"),
+                    )
+                    .arg(
+                        Arg::new("upload_params")
+                            .long("upload-params")
+                            .value_name("skip|stringify|strict")
+                            .value_parser(["skip", "stringify", "strict"])
+                            .default_value("skip")
+                            .help("Handling of non-string upload_parameters from the register response"),
+                    )
+                    .arg(
+                        Arg::new("register_body")
+                            .long("register-body")
+                            .value_name("JSON|@FILE")
+                            .help("Extra fields merged into the POST /media registration payload (media_type comes from --media-type)"),
+                    )
+                    .arg(
+                        Arg::new("id_only")
+                            .long("id-only")
+                            .action(ArgAction::SetTrue)
+                            .help("Print only the media_id (one per line for a glob)"),
                     ),
             );
         }
@@ -391,6 +3121,32 @@ fn build_cli(tree: &CommandTree) -> Command {
         cmd = cmd.subcommand(res_cmd);
     }
 
+    cmd = cmd.subcommand(
+        Command::new("conversions")
+            .about("Conversions API helpers")
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+            .subcommand(
+                Command::new("send")
+                    .about("Stream NDJSON events to /ad_accounts/{id}/events in batches, hashing user_data")
+                    .arg(
+                        Arg::new("file")
+                            .long("file")
+                            .value_name("FILE|URL|S3|-")
+                            .required(true)
+                            .help("NDJSON events, one per line; - reads stdin"),
+                    )
+                    .arg(
+                        Arg::new("batch_size")
+                            .long("batch-size")
+                            .value_name("N")
+                            .value_parser(clap::value_parser!(usize))
+                            .default_value("1000")
+                            .help("Events per request (max 1000)"),
+                    ),
+            ),
+    );
+
     cmd
 }
 
@@ -403,10 +3159,13 @@ fn build_param_arg(param: &ParamDef) -> Arg {
         arg = arg.action(ArgAction::Append);
     }
 
-    if param.location == "path" && param.required && param.name != "ad_account_id" {
+    if param.location == "header" && param.required {
+        // Unlike query params, --params can't supply header params.
         arg = arg.required(true);
     }
 
+    // Path params aren't required here: --each-from rows or config defaults
+    // may supply them, and build_path reports any that stay missing.
     arg
 }
 
@@ -423,31 +3182,353 @@ fn param_value_name(param: &ParamDef) -> String {
     param.schema_type.clone()
 }
 
+// Params whose flag matches a global flag read the global arg instead of
+// defining a clashing per-op one.
+const GLOBAL_PARAM_ARGS: &[(&str, &str)] = &[
+    ("ad-account-id", "ad_account_id"),
+    ("client-id", "client_id"),
+    ("page-size", "page_size"),
+];
+
+fn is_global_param(param: &ParamDef) -> bool {
+    GLOBAL_PARAM_ARGS
+        .iter()
+        .any(|(flag, _)| *flag == param.flag)
+}
+
 fn param_key(param: &ParamDef) -> String {
+    if let Some((_, id)) = GLOBAL_PARAM_ARGS
+        .iter()
+        .find(|(flag, _)| *flag == param.flag)
+    {
+        return id.to_string();
+    }
     format!("param__{}", param.name)
 }
 
-fn handle_list(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
-    if matches.get_flag("json") {
-        let mut out = Vec::new();
-        for res in &tree.resources {
-            let ops: Vec<String> = res.ops.iter().map(|op| op.name.clone()).collect();
-            out.push(serde_json::json!({"resource": res.name, "ops": ops}));
+fn handle_list(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let methods: Vec<String> = matches
+        .get_many::<String>("method")
+        .into_iter()
+        .flatten()
+        .map(|m| m.to_ascii_uppercase())
+        .collect();
+    let tags: Vec<String> = matches
+        .get_many::<String>("tag")
+        .into_iter()
+        .flatten()
+        .map(|t| t.to_ascii_lowercase())
+        .collect();
+    let writes = matches.get_flag("writes");
+    let reads = matches.get_flag("reads");
+    let keep = |op: &Operation| {
+        let is_read = matches!(op.method.as_str(), "GET" | "HEAD");
+        (methods.is_empty() || methods.contains(&op.method))
+            && (tags.is_empty()
+                || op
+                    .tags
+                    .iter()
+                    .any(|t| tags.contains(&t.to_ascii_lowercase())))
+            && (!writes || !is_read)
+            && (!reads || is_read)
+    };
+    let filtered = writes || reads || !methods.is_empty();
+    if matches.get_flag("by_tag") {
+        return list_by_tag(tree, &keep, filtered, matches.get_flag("json"));
+    }
+    let resources: Vec<(&str, Vec<&Operation>)> = tree
+        .resources
+        .iter()
+        .map(|res| {
+            (
+                res.name.as_str(),
+                res.ops.iter().filter(|op| keep(op)).collect::<Vec<_>>(),
+            )
+        })
+        .filter(|(_, ops)| !ops.is_empty())
+        .collect();
+
+    if matches.get_flag("json") {
+        let mut out = Vec::new();
+        for (name, ops) in &resources {
+            let ops: Vec<String> = ops.iter().map(|op| op.name.clone()).collect();
+            out.push(serde_json::json!({"resource": name, "ops": ops}));
+        }
+        write_json(&Value::Array(out), true)?;
+        return Ok(());
+    }
+
+    for (name, ops) in &resources {
+        write_stdout_line(name)?;
+        for op in ops {
+            if filtered {
+                write_stdout_line(&format!("  {} ({})", op.name, op.method))?;
+            } else {
+                write_stdout_line(&format!("  {}", op.name))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Groups operations under each of their tags; untagged ops are listed under "untagged".
+fn list_by_tag(
+    tree: &CommandTree,
+    keep: &dyn Fn(&Operation) -> bool,
+    filtered: bool,
+    json: bool,
+) -> Result<()> {
+    let mut groups: BTreeMap<&str, Vec<(&str, &Operation)>> = BTreeMap::new();
+    for res in &tree.resources {
+        for op in res.ops.iter().filter(|op| keep(op)) {
+            if op.tags.is_empty() {
+                groups.entry("untagged").or_default().push((&res.name, op));
+            }
+            for tag in &op.tags {
+                groups.entry(tag).or_default().push((&res.name, op));
+            }
+        }
+    }
+
+    if json {
+        let out: Vec<Value> = groups
+            .iter()
+            .map(|(tag, ops)| {
+                let ops: Vec<Value> = ops
+                    .iter()
+                    .map(|(res, op)| serde_json::json!({"resource": res, "op": op.name}))
+                    .collect();
+                serde_json::json!({"tag": tag, "ops": ops})
+            })
+            .collect();
+        return write_json(&Value::Array(out), true);
+    }
+
+    for (tag, ops) in &groups {
+        write_stdout_line(tag)?;
+        for (res, op) in ops {
+            if filtered {
+                write_stdout_line(&format!("  {res} {} ({})", op.name, op.method))?;
+            } else {
+                write_stdout_line(&format!("  {res} {}", op.name))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_describe(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let resource = matches
+        .get_one::<String>("resource")
+        .ok_or_else(|| anyhow!("resource required"))?;
+    let op_name = matches
+        .get_one::<String>("op")
+        .ok_or_else(|| anyhow!("operation required"))?;
+
+    let op = find_op(tree, resource, op_name)
+        .ok_or_else(|| anyhow!("unknown command {resource} {op_name}"))?;
+
+    if matches.get_flag("json") {
+        write_json(&serde_json::to_value(op)?, true)?;
+        return Ok(());
+    }
+
+    write_stdout_line(&format!("{} {}", resource, op.name))?;
+    write_stdout_line(&format!("  method: {}", op.method))?;
+    write_stdout_line(&format!("  path: {}", op.path))?;
+    write_stdout_line(&format!("  paginated: {}", op.paginated))?;
+    if op.method == "DELETE" || op.destructive {
+        write_stdout_line("  destructive: true (prompts unless --assume-yes)")?;
+    }
+    if let Some(timeout) = op.timeout {
+        write_stdout_line(&format!("  timeout: {timeout}s (--op-timeout overrides)"))?;
+    }
+    if let Some(codes) = &op.success_codes {
+        let codes: Vec<String> = codes.iter().map(u16::to_string).collect();
+        write_stdout_line(&format!("  success codes: {}", codes.join(", ")))?;
+    }
+    if op.deprecated {
+        match &op.replacement {
+            Some(replacement) => {
+                write_stdout_line(&format!("  deprecated: true (use {replacement})"))?
+            }
+            None => write_stdout_line("  deprecated: true")?,
+        }
+    }
+
+    if !op.security.is_empty() {
+        let schemes: Vec<String> = op
+            .security
+            .iter()
+            .flat_map(|req| req.keys().cloned().collect::<Vec<_>>())
+            .collect();
+        write_stdout_line(&format!("  auth: {}", schemes.join(" | ")))?;
+        // Best effort: describe works without credentials or a readable config.
+        if let Ok(config) = load_config(tree, matches) {
+            let chosen = match auth_choice(op, &config) {
+                Ok((_, why)) => why,
+                Err(err) => format!("none ({err})"),
+            };
+            write_stdout_line(&format!("  auth with current config: {chosen}"))?;
+        }
+        let scopes = required_scopes(op);
+        if !scopes.is_empty() {
+            write_stdout_line(&format!("  scopes: {}", scopes.join(", ")))?;
+        }
+    }
+
+    if let Some(rb) = &op.request_body {
+        write_stdout_line(&format!("  request_body: required={}", rb.required))?;
+        if !rb.content_types.is_empty() {
+            write_stdout_line(&format!(
+                "    content_types: {}",
+                rb.content_types.join(", ")
+            ))?;
+        }
+    }
+
+    if !op.params.is_empty() {
+        write_stdout_line("  params:")?;
+        for param in &op.params {
+            write_stdout_line(&format!(
+                "    --{}  {} ({}, required={})",
+                param.flag,
+                param_value_name(param),
+                param.location,
+                param.required
+            ))?;
+        }
+    }
+
+    write_stdout_line("  example:")?;
+    write_stdout_line(&format!("    {}", example_invocation(tree, resource, op)))?;
+
+    Ok(())
+}
+
+/// A copy-ready command line using schema examples where present and
+/// NAME placeholders otherwise; bodies get a skeleton from the schema.
+fn example_invocation(tree: &CommandTree, resource: &str, op: &Operation) -> String {
+    let mut line = format!("pinterest-ads {} {}", resource, op.name);
+    for param in op.params.iter().filter(|p| p.required) {
+        let value = match &param.example {
+            Some(Value::String(v)) => v.clone(),
+            Some(Value::Array(items)) if !items.is_empty() => items
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| v.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            Some(v) if !v.is_null() && !v.is_array() => v.to_string(),
+            _ => param.name.to_ascii_uppercase(),
+        };
+        line.push_str(&format!(" --{} {}", param.flag, curl::shell_quote(&value)));
+    }
+    if let Some(rb) = &op.request_body {
+        let skeleton = rb
+            .schema
+            .as_ref()
+            .map(|s| schema::example_value(&schema::resolve_refs(tree, s)))
+            .unwrap_or_else(|| Value::Object(Default::default()));
+        let json_body =
+            rb.content_types.is_empty() || rb.content_types.iter().any(|c| c == "application/json");
+        let flag = if json_body { "--body" } else { "--form" };
+        line.push_str(&format!(
+            " {flag} {}",
+            curl::shell_quote(&skeleton.to_string())
+        ));
+    }
+    line
+}
+
+/// Handles `--print-curl`/`--print-curl-insecure`; returns whether it printed
+/// (and the request should not be sent).
+fn print_curl(
+    matches: &clap::ArgMatches,
+    client: &PinterestClient,
+    method: &str,
+    url: &str,
+    auth: &Auth,
+    query: &[(String, String)],
+    body: Option<&Body>,
+) -> Result<bool> {
+    let insecure = matches.get_flag("print_curl_insecure");
+    if !insecure && !matches.get_flag("print_curl") {
+        return Ok(false);
+    }
+    let command = curl::render(client, method, url, auth, query, body, insecure);
+    write_stdout_line(&command)?;
+    Ok(true)
+}
+
+/// `--explain`: the request an operation would send, as a sentence a
+/// reviewer can follow (what, where, with which auth and body). Nothing is sent.
+fn explain(
+    op: Option<&Operation>,
+    method: &str,
+    url: &str,
+    auth: &str,
+    query: &[(String, String)],
+    body: Option<&Body>,
+) -> String {
+    let mut text = format!("This will {method} {}", query::append_to_url(url, query));
+    if let Some(summary) = op.and_then(|op| op.summary.as_deref()) {
+        let summary = summary.trim().trim_end_matches('.');
+        let mut chars = summary.chars();
+        // Lower-case "Update campaigns", but not an acronym like "URL".
+        let lowered = match (chars.next(), chars.next()) {
+            (Some(first), Some(second)) if !second.is_uppercase() => first
+                .to_lowercase()
+                .chain(summary[first.len_utf8()..].chars())
+                .collect(),
+            _ => summary.to_string(),
+        };
+        text.push_str(&format!(" to {lowered}"));
+    }
+    text.push_str(&format!(", using {auth}"));
+    match body {
+        None => text.push_str(", with no body."),
+        Some(Body::Json(value)) => text.push_str(&format!(
+            ", with body:\n{}",
+            serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+        )),
+        Some(Body::Form(fields)) => {
+            let fields: Vec<String> = fields.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            text.push_str(&format!(", with form fields: {}.", fields.join(", ")));
         }
-        write_json(&Value::Array(out), true)?;
-        return Ok(());
+        Some(Body::JsonFile(file)) => text.push_str(&format!(
+            ", with the JSON body streamed from {}.",
+            file.file_name
+        )),
     }
-
-    for res in &tree.resources {
-        write_stdout_line(&res.name)?;
-        for op in &res.ops {
-            write_stdout_line(&format!("  {}", op.name))?;
+    if let Some(op) = op {
+        if op.method == "DELETE" || op.destructive {
+            text.push_str("\nThis is destructive: it asks for confirmation first unless --assume-yes is given.");
+        }
+        if op.deprecated {
+            text.push_str("\nThe operation is deprecated");
+            if let Some(replacement) = &op.replacement {
+                text.push_str(&format!("; use {replacement} instead"));
+            }
+            text.push('.');
         }
     }
-    Ok(())
+    text.push_str("\nNothing was sent (--explain).");
+    text
 }
 
-fn handle_describe(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+fn handle_docs(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let doc = docs::render_markdown(tree);
+    match output_sink(matches, None)? {
+        Some(sink) => sink.write(&doc, "text/markdown"),
+        None => output::write_stdout(&doc),
+    }
+}
+
+fn handle_schema(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     let resource = matches
         .get_one::<String>("resource")
         .ok_or_else(|| anyhow!("resource required"))?;
@@ -458,51 +3539,119 @@ fn handle_describe(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()>
     let op = find_op(tree, resource, op_name)
         .ok_or_else(|| anyhow!("unknown command {resource} {op_name}"))?;
 
+    let body_schema = op
+        .request_body
+        .as_ref()
+        .and_then(|rb| rb.schema.as_ref())
+        .map(|s| schema::resolve_refs(tree, s));
+
     if matches.get_flag("json") {
-        write_json(&serde_json::to_value(op)?, true)?;
+        let body = op.request_body.as_ref().map(|rb| {
+            serde_json::json!({
+                "required": rb.required,
+                "content_types": rb.content_types,
+                "schema": body_schema,
+            })
+        });
+        let out = serde_json::json!({
+            "params": op.params,
+            "body": body,
+        });
+        write_json(&out, true)?;
         return Ok(());
     }
 
     write_stdout_line(&format!("{} {}", resource, op.name))?;
-    write_stdout_line(&format!("  method: {}", op.method))?;
-    write_stdout_line(&format!("  path: {}", op.path))?;
-    write_stdout_line(&format!("  paginated: {}", op.paginated))?;
-
-    if !op.security.is_empty() {
-        let schemes: Vec<String> = op
-            .security
-            .iter()
-            .flat_map(|req| req.keys().cloned().collect::<Vec<_>>())
-            .collect();
-        write_stdout_line(&format!("  auth: {}", schemes.join(" | ")))?;
-    }
-
-    if let Some(rb) = &op.request_body {
-        write_stdout_line(&format!("  request_body: required={}", rb.required))?;
-        if !rb.content_types.is_empty() {
-            write_stdout_line(&format!(
-                "    content_types: {}",
-                rb.content_types.join(", ")
-            ))?;
-        }
-    }
 
     if !op.params.is_empty() {
         write_stdout_line("  params:")?;
         for param in &op.params {
             write_stdout_line(&format!(
-                "    --{}  {} ({}, required={})",
-                param.flag,
+                "    {}  {} ({}{})",
+                param.name,
                 param_value_name(param),
                 param.location,
-                param.required
+                if param.required { ", required" } else { "" }
             ))?;
         }
     }
 
+    let Some(rb) = &op.request_body else {
+        write_stdout_line("  body: none")?;
+        return Ok(());
+    };
+    write_stdout_line(&format!(
+        "  body: {} ({})",
+        rb.content_types.join(", "),
+        if rb.required { "required" } else { "optional" }
+    ))?;
+    let Some(body_schema) = body_schema else {
+        write_stdout_line("    (no schema in command tree)")?;
+        return Ok(());
+    };
+    let root = schema::merge_all_of(&body_schema);
+    write_stdout_line(&format!("    <root>  {}", schema::schema_type(&root)))?;
+    for field in schema::list_fields(&body_schema) {
+        let mut line = format!("    {}  {}", field.path, field.schema_type);
+        if field.required {
+            line.push_str(" (required)");
+        }
+        if !field.enum_values.is_empty() {
+            line.push_str(&format!(" [{}]", field.enum_values.join("|")));
+        }
+        write_stdout_line(&line)?;
+    }
+
     Ok(())
 }
 
+fn handle_example(tree: &CommandTree, op: &Operation) -> Result<()> {
+    let body_schema = op
+        .request_body
+        .as_ref()
+        .and_then(|rb| rb.schema.as_ref())
+        .ok_or_else(|| anyhow!("no request body schema for {}", op.name))?;
+    let resolved = schema::resolve_refs(tree, body_schema);
+    write_json(&schema::example_value(&resolved), true)
+}
+
+/// `--interactive`: builds a JSON body by prompting on stderr for each
+/// required field of the op's body schema.
+fn prompt_body(tree: &CommandTree, op: &Operation, matches: &clap::ArgMatches) -> Result<Value> {
+    let rb = op
+        .request_body
+        .as_ref()
+        .ok_or_else(|| anyhow!("request body not supported for this operation"))?;
+    if !rb.content_types.iter().any(|ct| ct == "application/json") {
+        return Err(anyhow!("--interactive only builds JSON bodies; use --form"));
+    }
+    let body_schema = rb.schema.as_ref().ok_or_else(|| {
+        anyhow!(
+            "no request body schema for {}; pass --body instead",
+            op.name
+        )
+    })?;
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "--interactive needs a terminal; pass --body instead"
+        ));
+    }
+    claim_stdin(matches, "--interactive", "pass --body instead")?;
+    let resolved = schema::resolve_refs(tree, body_schema);
+    let stdin = std::io::stdin();
+    let body = schema::prompt_value(&resolved, "", &mut |prompt| {
+        eprint!("{prompt}");
+        std::io::stderr().flush().ok();
+        let mut answer = String::new();
+        if stdin.read_line(&mut answer).context("read answer")? == 0 {
+            return Err(anyhow!("aborted; no input"));
+        }
+        Ok(answer)
+    })?;
+    eprintln!("body: {body}");
+    Ok(body)
+}
+
 fn handle_tree(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     if matches.get_flag("json") {
         write_json(&serde_json::to_value(tree)?, true)?;
@@ -512,10 +3661,141 @@ fn handle_tree(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     Ok(())
 }
 
+/// `validate-tree`: lists each problem as `PATH:LINE: resource op: message`
+/// and fails if there are any.
+fn handle_validate_tree(matches: &clap::ArgMatches) -> Result<()> {
+    let path = matches
+        .get_one::<String>("path")
+        .ok_or_else(|| anyhow!("PATH required"))?;
+    let problems = tree_check::check_file(std::path::Path::new(path))?;
+    if matches.get_flag("json") {
+        write_json(&serde_json::to_value(&problems)?, true)?;
+    } else {
+        for problem in &problems {
+            let line = problem.line.map(|n| format!("{n}:")).unwrap_or_default();
+            let op = problem
+                .op
+                .as_ref()
+                .map(|op| format!(" {op}"))
+                .unwrap_or_default();
+            write_stdout_line(&format!(
+                "{path}:{line} {}{op}: {}",
+                problem.resource, problem.message
+            ))?;
+        }
+    }
+    match problems.len() {
+        0 => {
+            if !matches.get_flag("json") {
+                write_stdout_line(&format!("{path}: ok"))?;
+            }
+            Ok(())
+        }
+        1 => Err(anyhow!("1 problem in {path}")),
+        n => Err(anyhow!("{n} problems in {path}")),
+    }
+}
+
+fn api_client(matches: &clap::ArgMatches, config: &Config) -> Result<PinterestClient> {
+    let prefer = matches
+        .get_one::<String>("prefer")
+        .map(|value| format!("Prefer: {value}"));
+    let headers = client::parse_headers(
+        matches
+            .get_many::<String>("header")
+            .into_iter()
+            .flatten()
+            .chain(prefer.as_ref()),
+    )?;
+    let retry_if = client::RetryIf {
+        predicates: matches
+            .get_many::<String>("retry_if")
+            .into_iter()
+            .flatten()
+            .map(|spec| query::ClientFilter::parse(spec, "--retry-if"))
+            .collect::<Result<_>>()?,
+        max: matches
+            .get_one::<u32>("retry_if_max")
+            .copied()
+            .unwrap_or(10),
+        any_method: matches.get_flag("retry_if_any_method"),
+    };
+    let mut retry = pinterest_ads::retry::RetryPolicy::new(
+        matches
+            .get_one::<u32>("max_retries")
+            .copied()
+            .unwrap_or(client::DEFAULT_MAX_RETRIES),
+        std::time::Duration::from_secs(1),
+    );
+    if let Some(seconds) = matches.get_one::<u64>("retry_max_delay") {
+        retry.max_delay = std::time::Duration::from_secs(*seconds);
+    }
+    if let Some(seconds) = matches.get_one::<u64>("retry_after_cap") {
+        retry.retry_after_cap = std::time::Duration::from_secs(*seconds);
+    }
+    retry.jitter = !matches.get_flag("no_retry_jitter");
+    if let Some(list) = matches.get_one::<String>("retry_methods") {
+        retry.methods = pinterest_ads::retry::RetryMethods::parse(list)
+            .map_err(|err| anyhow!("--retry-methods: {err}"))?;
+    }
+    let mut builder = PinterestClient::builder(config.base_url.clone())
+        .http_version(config.http_version)
+        .pool(config.pool)
+        .retry(retry)
+        .not_found_retries(matches.get_one::<u32>("retry_404").copied().unwrap_or(0))
+        .retry_if(retry_if)
+        .gzip_body(matches.get_flag("gzip_body"))
+        .dedupe(matches.get_flag("dedupe"));
+    // An explicit --header X-Request-Id is the correlation id itself.
+    if !headers.contains_key(client::CORRELATION_HEADER) {
+        builder = builder.correlation_id(correlation_id(matches));
+    }
+    builder = builder.headers(headers);
+    if let Some(seconds) = config.timeout {
+        builder = builder.timeout(std::time::Duration::from_secs(seconds));
+    }
+    if let Some(max) = matches.get_one::<usize>("max_redirects") {
+        builder = builder.max_redirects(*max);
+    }
+    if let Some(budget) = matches.get_one::<u64>("retry_budget") {
+        builder = builder.retry_budget(*budget);
+    }
+    if let Some(path) = matches.get_one::<String>("log_file") {
+        builder = builder.request_log(Arc::new(request_log::RequestLog::open(path)?));
+    }
+    if let Some(seconds) = matches.get_one::<u64>("cache_ttl") {
+        let dir = directories::ProjectDirs::from("", "", "pinterest-ads")
+            .ok_or_else(|| anyhow!("--cache-ttl: no cache directory for this user"))?
+            .cache_dir()
+            .join("responses");
+        builder = builder.cache(Arc::new(ResponseCache::new(
+            dir,
+            std::time::Duration::from_secs(*seconds),
+        )));
+    }
+    match (
+        matches.get_one::<String>("record"),
+        matches.get_one::<String>("replay"),
+    ) {
+        (Some(_), Some(_)) => return Err(anyhow!("--record and --replay can't be combined")),
+        (Some(dir), None) => builder = builder.fixtures(Arc::new(Fixtures::record(dir))),
+        (None, Some(dir)) => {
+            let fixtures = Fixtures::replay(dir).map_err(|err| anyhow!("--replay: {err}"))?;
+            builder = builder.fixtures(Arc::new(fixtures));
+        }
+        (None, None) => {}
+    }
+    if stats_enabled(matches) {
+        builder = builder.metrics(Arc::clone(STATS.get_or_init(Default::default)));
+    }
+    builder.build()
+}
+
 fn handle_raw(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
-    let config = load_config(tree, matches)?;
+    let mut config = load_config(tree, matches)?;
     setup_logging(matches.get_flag("debug"))?;
-    let client = PinterestClient::new(config.base_url.clone(), config.timeout)?;
+    let client = with_op_timeout(&api_client(matches, &config)?, matches, None);
+    let client = session::prepare(&client, &mut config)?;
 
     let method = matches
         .get_one::<String>("method")
@@ -524,12 +3804,13 @@ fn handle_raw(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     let path = matches
         .get_one::<String>("path")
         .ok_or_else(|| anyhow!("path required"))?;
+    config.policy.check("raw", &method, &method)?;
 
-    let auth = match matches
+    let auth_flag = matches
         .get_one::<String>("auth")
         .map(|v| v.as_str())
-        .unwrap_or("bearer")
-    {
+        .unwrap_or("bearer");
+    let auth = match auth_flag {
         "basic" => Auth::Basic {
             username: config
                 .client_id
@@ -540,12 +3821,7 @@ fn handle_raw(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
                 .clone()
                 .ok_or_else(|| anyhow!("PINTEREST_CLIENT_SECRET missing"))?,
         },
-        "conversion" => Auth::Bearer(
-            config
-                .conversion_token
-                .clone()
-                .ok_or_else(|| anyhow!("PINTEREST_CONVERSION_TOKEN missing"))?,
-        ),
+        "conversion" => conversion_auth(config.conversion_token.as_deref(), path)?,
         _ => Auth::Bearer(
             config
                 .access_token
@@ -555,19 +3831,547 @@ fn handle_raw(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     };
 
     let params_json = matches.get_one::<String>("params");
-    let query = parse_params_json(params_json, &[])?;
+    let mut query = parse_params_json(params_json, &[], false, &config.sources)?;
+    for (key, values) in generic_params(matches)? {
+        query.retain(|(k, _)| *k != key);
+        query.extend(values.into_iter().map(|v| (key.clone(), v)));
+    }
+    if let Some(prefix) = matches.get_one::<String>("params_from_env") {
+        for (key, value) in env_params(prefix) {
+            if !query.iter().any(|(k, _)| *k == key) {
+                query.push((key, value));
+            }
+        }
+    }
 
-    let body = if let Some(raw) = matches.get_one::<String>("body") {
-        Some(Body::Json(parse_json_source(raw)?))
+    if matches.contains_id("body") && matches.contains_id("form") {
+        return Err(body_and_form(None));
+    }
+    let body = if let Some(raws) = body_sources(matches) {
+        Some(json_body(&raws, matches, &config.sources)?)
     } else if let Some(raw) = matches.get_one::<String>("form") {
-        Some(Body::Form(parse_form_source(raw)?))
+        Some(Body::Form(parse_form_source(raw, &config.sources)?))
     } else {
         None
     };
 
-    let url = client.build_url(path);
-    let resp = client.request(&method, &url, &auth, &query, body)?;
-    write_json(&resp, matches.get_flag("pretty"))?;
+    let url = client.build_url(path)?;
+    if matches.get_flag("explain") {
+        let why = match auth_flag {
+            "basic" => format!(
+                "basic auth with the client id{} and secret{}, because of --auth basic",
+                credential_origin(&config, "client_id"),
+                credential_origin(&config, "client_secret")
+            ),
+            "conversion" => format!(
+                "bearer auth with the conversion token{}, because of --auth conversion",
+                credential_origin(&config, "conversion_token")
+            ),
+            _ => format!(
+                "bearer auth with the access token{}, because raw requests use it unless --auth says otherwise",
+                credential_origin(&config, "access_token")
+            ),
+        };
+        let text = explain(None, &method, &url, &why, &query, body.as_ref());
+        return write_stdout_line(&text);
+    }
+    if print_curl(
+        matches,
+        &client,
+        &method,
+        &url,
+        &auth,
+        &query,
+        body.as_ref(),
+    )? {
+        return Ok(());
+    }
+    if matches.get_flag("stream_response") {
+        if method != "GET" {
+            return Err(anyhow!("--stream-response needs a GET"));
+        }
+        let out = output_options(matches, &config)?;
+        client.stream_items(&url, &auth, &query, |item| {
+            out.emit_stream_item(&item, None)
+        })?;
+        return Ok(());
+    }
+    let resp = client.request_response(&method, &url, &auth, &query, body);
+    let Some(resp) = allow_404(resp, &method, matches)? else {
+        return output_options(matches, &config)?.emit(&Value::Null);
+    };
+    let resp = follow_accepted(&client, &auth, matches, &url, resp)?;
+    let mut out = output_options(matches, &config)?;
+    if matches.get_flag("envelope") {
+        out.envelope = Some(Envelope {
+            method: method.clone(),
+            url: query::append_to_url(&url, &query),
+            status: resp.status.as_u16(),
+            bookmark: None,
+        });
+    }
+    out.emit(&resp.body)
+}
+
+fn handle_ping(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let mut config = load_config(tree, matches)?;
+    setup_logging(matches.get_flag("debug"))?;
+    let client = session::prepare(&api_client(matches, &config)?, &mut config)?;
+
+    let op = find_op(tree, "user-account", "get")
+        .ok_or_else(|| anyhow!("unknown command user-account get"))?;
+    let auth = select_auth(op, &config)?;
+    let user = client
+        .request("GET", &client.build_url(&op.path)?, &auth, &[], None)
+        .map_err(|err| anyhow!("auth check failed: {err:#}"))?;
+
+    let mut summary = serde_json::json!({
+        "ok": true,
+        "base_url": config.base_url,
+        "user": pick_fields(&user, &["id", "username", "account_type", "business_name"]),
+    });
+
+    single_ad_account(matches, "ping")?;
+    if let Some(ad_account_id) = &config.ad_account_id {
+        let path = format!("/ad_accounts/{}", urlencoding::encode(ad_account_id));
+        let account = client
+            .request("GET", &client.build_url(&path)?, &auth, &[], None)
+            .map_err(|err| anyhow!("ad account {ad_account_id} check failed: {err:#}"))?;
+        summary["ad_account"] = pick_fields(&account, &["id", "name", "currency", "country"]);
+    }
+
+    output_options(matches, &config)?.emit(&summary)
+}
+
+/// `auth login|status|logout`: manages the session file that supplies the
+/// access token when no flag, env var or config key does.
+fn handle_auth(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let mut config = load_config(tree, matches)?;
+    setup_logging(matches.get_flag("debug"))?;
+    let profile = matches
+        .get_one::<String>("profile")
+        .cloned()
+        .or_else(|| env_value(matches, "PINTEREST_PROFILE"));
+    let path = session::session_path(profile.as_deref())
+        .ok_or_else(|| anyhow!("no config directory for the session file"))?;
+
+    match matches.subcommand() {
+        Some(("login", login)) => {
+            let client = api_client(matches, &config)?;
+            let saved = match login.get_one::<String>("code") {
+                Some(code) => {
+                    let redirect_uri = login
+                        .get_one::<String>("redirect_uri")
+                        .ok_or_else(|| anyhow!("--redirect-uri required"))?;
+                    let form = vec![
+                        ("grant_type".to_string(), "authorization_code".to_string()),
+                        ("code".to_string(), code.clone()),
+                        ("redirect_uri".to_string(), redirect_uri.clone()),
+                    ];
+                    session::token_request(&client, &config, form)?
+                }
+                None => {
+                    let token = match login.get_one::<String>("token") {
+                        Some(token) => token.clone(),
+                        None => read_token_stdin(matches)?,
+                    };
+                    session::Session {
+                        access_token: token,
+                        refresh_token: login.get_one::<String>("refresh_token").cloned(),
+                        expires_at: login
+                            .get_one::<i64>("expires_in")
+                            .map(|secs| session::expires_in(*secs)),
+                        scope: login.get_one::<String>("scope").cloned(),
+                    }
+                }
+            };
+            // Only keep a token the API accepts.
+            let user = client
+                .request(
+                    "GET",
+                    &client.build_url("/user_account")?,
+                    &Auth::Bearer(saved.access_token.clone()),
+                    &[],
+                    None,
+                )
+                .map_err(|err| anyhow!("token check failed: {err:#}"))?;
+            session::save(&path, &saved)?;
+            let username = user
+                .get("username")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown user");
+            write_stdout_line(&format!(
+                "logged in as {username} (session {})",
+                path.display()
+            ))
+        }
+        Some(("status", status)) => {
+            let Some(saved) = session::load(&path)? else {
+                return Err(anyhow!(
+                    "not logged in (no session at {}); run `auth login`",
+                    path.display()
+                ));
+            };
+            let origin = config
+                .origins
+                .iter()
+                .find(|(key, _)| *key == "access_token")
+                .and_then(|(_, origin)| origin.clone());
+            let active = config.session.is_some();
+            if active && saved.expired() && saved.refresh_token.is_some() {
+                let client = api_client(matches, &config)?;
+                session::refresh_if_expired(&client, &mut config)?;
+            }
+            let saved = config.session.as_ref().map_or(saved, |(_, s)| s.clone());
+            let summary = serde_json::json!({
+                "session": path.display().to_string(),
+                "active": active,
+                "overridden_by": if active { None } else { origin },
+                "expires_at": saved.expires_at,
+                "expired": saved.expired(),
+                "refreshable": saved.refresh_token.is_some(),
+                "scope": saved.scope,
+            });
+            if status.get_flag("json") {
+                return write_json(&summary, true);
+            }
+            write_stdout_line(&format!("session: {}", path.display()))?;
+            match summary["overridden_by"].as_str() {
+                Some(origin) => write_stdout_line(&format!("active: no (token from {origin})"))?,
+                None => write_stdout_line("active: yes")?,
+            }
+            let expiry = match (&saved.expires_at, saved.expired()) {
+                (None, _) => "unknown".to_string(),
+                (Some(at), true) => format!("{at} (expired)"),
+                (Some(at), false) => at.clone(),
+            };
+            write_stdout_line(&format!("expires: {expiry}"))?;
+            write_stdout_line(&format!(
+                "refresh token: {}",
+                if saved.refresh_token.is_some() {
+                    "yes"
+                } else {
+                    "no"
+                }
+            ))?;
+            if let Some(scope) = &saved.scope {
+                write_stdout_line(&format!("scope: {scope}"))?;
+            }
+            Ok(())
+        }
+        Some(("logout", _)) => {
+            if session::remove(&path)? {
+                write_stdout_line(&format!("logged out (removed {})", path.display()))
+            } else {
+                write_stdout_line("not logged in")
+            }
+        }
+        _ => Err(anyhow!("auth subcommand required")),
+    }
+}
+
+/// Reads an access token from stdin, prompting when it is a terminal.
+fn read_token_stdin(matches: &clap::ArgMatches) -> Result<String> {
+    claim_stdin(matches, "auth login", "pass --token instead")?;
+    if std::io::stdin().is_terminal() {
+        eprint!("access token: ");
+        std::io::stderr().flush().ok();
+    }
+    let mut token = String::new();
+    std::io::stdin()
+        .read_line(&mut token)
+        .context("read access token")?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(anyhow!(
+            "no access token given; pass --token or pipe it on stdin"
+        ));
+    }
+    Ok(token.to_string())
+}
+
+/// Lists each setting with its origin; secret values are reported only as set.
+fn handle_config_check(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    const SECRETS: &[&str] = &["access_token", "client_secret", "conversion_token"];
+    let config = load_config(tree, matches)?;
+    let value_of = |key: &str| match key {
+        "base_url" => Some(config.base_url.clone()),
+        "client_id" => config.client_id.clone(),
+        "ad_account_id" => matches
+            .get_many::<String>("ad_account_id")
+            .map(|ids| ids.cloned().collect::<Vec<_>>().join(", "))
+            .or_else(|| config.ad_account_id.clone()),
+        "path_defaults" => Some(
+            config
+                .path_defaults
+                .iter()
+                .map(|(param, value)| format!("{param}={value}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        "default_raw_output" => Some(config.default_raw_output.to_string()),
+        "allowed_operations" => config.policy.allowed.as_ref().map(|(v, _)| v.join(", ")),
+        "denied_operations" => config.policy.denied.as_ref().map(|(v, _)| v.join(", ")),
+        "command_tree" => Some(
+            config
+                .command_tree
+                .clone()
+                .unwrap_or_else(|| format!("embedded (API {})", tree.api_version)),
+        ),
+        "template_dir" => config.template_dir.clone(),
+        _ => None,
+    };
+
+    let mut entries = Vec::new();
+    for (key, origin) in &config.origins {
+        let origin = match (key, origin) {
+            (_, Some(origin)) => Some(origin.as_str()),
+            (&"base_url", None) => Some("default"),
+            (&"command_tree", None) => Some("default"),
+            _ => None,
+        };
+        let mut entry =
+            serde_json::json!({ "key": key, "set": origin.is_some(), "source": origin });
+        if origin.is_some() && !SECRETS.contains(key) {
+            entry["value"] = value_of(key).into();
+        }
+        entries.push(entry);
+    }
+
+    if matches.get_flag("json") {
+        return write_json(&Value::Array(entries), true);
+    }
+    for entry in &entries {
+        let key = entry["key"].as_str().unwrap_or_default();
+        let line = match (entry["source"].as_str(), entry["value"].as_str()) {
+            (None, _) => format!("{key}: not set"),
+            (Some(source), Some(value)) => format!("{key}: {value} ({source})"),
+            (Some(source), None) => format!("{key}: set ({source})"),
+        };
+        write_stdout_line(&line)?;
+    }
+    Ok(())
+}
+
+/// `diff`: compares a result (stdin or a file) with a saved baseline.
+fn handle_diff(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let config = load_config(tree, matches)?;
+    setup_logging(matches.get_flag("debug"))?;
+    let out = output_options(matches, &config)?;
+    let baseline = matches
+        .get_one::<String>("baseline")
+        .ok_or_else(|| anyhow!("--baseline required"))?;
+    let key = matches
+        .get_one::<String>("key")
+        .map_or("id", String::as_str);
+    let update = matches.get_flag("update_baseline");
+
+    let current_text = match matches.get_one::<String>("current").map(String::as_str) {
+        None | Some("-") => {
+            claim_stdin(matches, "diff", "pass the current result as FILE")?;
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)
+                .context("read current result from stdin")?;
+            text
+        }
+        Some(file) => sources::read_source_to_string(file, &config.sources)?,
+    };
+    let current = parse_result(&current_text).map_err(|err| anyhow!("current result: {err}"))?;
+    let before = match std::fs::read_to_string(baseline) {
+        Ok(text) => parse_result(&text).map_err(|err| anyhow!("baseline {baseline}: {err}"))?,
+        Err(err) if update && err.kind() == std::io::ErrorKind::NotFound => {
+            log::warn!("no baseline at {baseline} yet; treating it as empty");
+            Value::Array(Vec::new())
+        }
+        Err(err) => return Err(anyhow!("read baseline {baseline}: {err}")),
+    };
+
+    let changes = diff::diff(&before, &current, key)?;
+    // current_text isn't empty (parse_result rejects that), so this never
+    // truncates the baseline.
+    if update {
+        output::write_file_atomic(baseline, current_text.as_bytes())?;
+    }
+    out.emit(&changes)
+}
+
+/// A saved result: one JSON document, or NDJSON lines (`--stream`) as an array.
+/// Empty input is an error, not an empty list: it is far more likely a failed
+/// export than a resource that really has no items.
+fn parse_result(text: &str) -> Result<Value> {
+    if text.trim().is_empty() {
+        return Err(anyhow!("empty input; expected JSON or NDJSON"));
+    }
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok(value);
+    }
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|err| anyhow!("line {}: invalid JSON: {err}", i + 1))
+        })
+        .collect::<Result<Vec<Value>>>()
+        .map(Value::Array)
+}
+
+/// Where tools/fetch_openapi.py downloads the spec the command tree is generated from.
+const LATEST_SPEC_URL: &str =
+    "https://raw.githubusercontent.com/pinterest/api-description/main/v5/openapi.json";
+
+/// Warns (or fails under `--strict`) when a newer spec than the tree's has been published.
+fn handle_version_check(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let config = load_config(tree, matches)?;
+    setup_logging(matches.get_flag("debug"))?;
+    let spec_url = matches
+        .get_one::<String>("spec_url")
+        .ok_or_else(|| anyhow!("--spec-url required"))?;
+
+    let mut builder = config
+        .http_version
+        .apply(reqwest::blocking::Client::builder());
+    if let Some(seconds) = config.timeout {
+        builder = builder.timeout(std::time::Duration::from_secs(seconds));
+    }
+    let spec: Value = builder
+        .build()
+        .context("build http client")?
+        .get(spec_url)
+        .send()
+        .and_then(|resp| resp.error_for_status())
+        .and_then(|resp| resp.json())
+        .map_err(|err| anyhow!("fetch {spec_url}: {err}"))?;
+    let latest = spec
+        .pointer("/info/version")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("{spec_url} has no info.version"))?;
+
+    let behind = version_parts(latest) > version_parts(&tree.api_version);
+    let summary = serde_json::json!({
+        "tree_api_version": tree.api_version,
+        "latest_api_version": latest,
+        "up_to_date": !behind,
+        "spec_url": spec_url,
+    });
+    output_options(matches, &config)?.emit(&summary)?;
+    if behind {
+        let message = format!(
+            "API spec {latest} is newer than the command tree ({}); regenerate it (see README)",
+            tree.api_version
+        );
+        if matches.get_flag("strict") {
+            return Err(anyhow!("{message} (--strict)"));
+        }
+        log::warn!("{message}");
+    }
+    Ok(())
+}
+
+/// `5.23.0` -> `[5, 23]`; non-numeric parts compare as 0. Trailing zeros are
+/// dropped so `5.23` and `5.23.0` compare equal.
+fn version_parts(version: &str) -> Vec<u64> {
+    let mut parts: Vec<u64> = version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect();
+    while parts.last() == Some(&0) {
+        parts.pop();
+    }
+    parts
+}
+
+fn pick_fields(value: &Value, keys: &[&str]) -> Value {
+    let mut out = serde_json::Map::new();
+    for key in keys {
+        if let Some(v) = value.get(*key) {
+            out.insert(key.to_string(), v.clone());
+        }
+    }
+    Value::Object(out)
+}
+
+fn handle_conversions_send(
+    tree: &CommandTree,
+    client: &PinterestClient,
+    config: &Config,
+    matches: &clap::ArgMatches,
+) -> Result<()> {
+    let op = find_op(tree, "events", "create")
+        .ok_or_else(|| anyhow!("unknown command events create"))?;
+    let auth = select_auth(op, config)?;
+    let accounts: Vec<String> = match matches.get_many::<String>("ad_account_id") {
+        Some(ids) => ids.cloned().collect(),
+        None => config.ad_account_id.iter().cloned().collect(),
+    };
+    if accounts.is_empty() {
+        return Err(anyhow!(
+            "--ad-account-id or PINTEREST_AD_ACCOUNT_ID required"
+        ));
+    }
+    let batch_size = conversions::check_batch_size(
+        matches
+            .get_one::<usize>("batch_size")
+            .copied()
+            .unwrap_or(1000),
+    )?;
+    let file = matches
+        .get_one::<String>("file")
+        .ok_or_else(|| anyhow!("--file required"))?;
+    if file == "-" {
+        if accounts.len() > 1 {
+            return Err(anyhow!(
+                "--file - can be read only once; pass a file to send to several ad accounts"
+            ));
+        }
+        claim_stdin(matches, "--file -", "pass the events as a file")?;
+    }
+    let source = match file.as_str() {
+        "-" => None,
+        _ => Some(sources::resolve_source(file, &config.sources)?),
+    };
+
+    // Each account gets the whole file, one after the other.
+    let mut failed = 0;
+    for ad_account_id in &accounts {
+        if cancel::is_cancelled() {
+            break;
+        }
+        let summary = match &source {
+            None => conversions::send_events(
+                client,
+                &auth,
+                ad_account_id,
+                std::io::stdin().lock(),
+                batch_size,
+            )?,
+            Some(source) => {
+                let f = std::fs::File::open(&source.path)
+                    .with_context(|| format!("open {}", source.path.display()))?;
+                conversions::send_events(
+                    client,
+                    &auth,
+                    ad_account_id,
+                    std::io::BufReader::new(f),
+                    batch_size,
+                )
+                .map_err(|err| anyhow!("ad account {ad_account_id}: {err:#}"))?
+            }
+        };
+        let prefix = if accounts.len() > 1 {
+            format!("ad account {ad_account_id}: ")
+        } else {
+            String::new()
+        };
+        eprintln!(
+            "{prefix}sent {} events in {} batches; {} failed",
+            summary.sent, summary.batches, summary.failed
+        );
+        failed += summary.failed;
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} events failed"));
+    }
     Ok(())
 }
 
@@ -575,7 +4379,7 @@ fn handle_media_upload(
     client: &PinterestClient,
     config: &Config,
     matches: &clap::ArgMatches,
-    pretty: bool,
+    out: &OutputOptions,
 ) -> Result<()> {
     let token = config
         .access_token
@@ -586,14 +4390,148 @@ fn handle_media_upload(
     let media_type = matches
         .get_one::<String>("media_type")
         .ok_or_else(|| anyhow!("--media-type required"))?;
-    let file = matches
-        .get_one::<String>("file")
-        .ok_or_else(|| anyhow!("--file required"))?;
-    let wait = matches.get_flag("wait");
+    let register_only = matches.get_flag("register_only");
+    let file = matches.get_one::<String>("file");
+    let mut wait_until: Vec<String> = matches
+        .get_many::<String>("wait_until")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    if wait_until.is_empty() && matches.get_flag("wait") {
+        wait_until.push("succeeded".to_string());
+    }
+    let opts = media_upload::UploadOptions {
+        wait_until,
+        non_string: media_upload::NonStringParams::parse(
+            matches
+                .get_one::<String>("upload_params")
+                .map(|v| v.as_str())
+                .unwrap_or("skip"),
+        )?,
+        register_fields: match matches.get_one::<String>("register_body") {
+            Some(raw) => match parse_json_source(raw, &config.sources)? {
+                Value::Object(map) => map,
+                _ => return Err(anyhow!("--register-body must be a JSON object")),
+            },
+            None => serde_json::Map::new(),
+        },
+    };
+
+    // The id is plain text, whatever the output format flags say.
+    let id_only;
+    let out = if matches.get_flag("id_only") {
+        id_only = OutputOptions {
+            raw_field: Some("media_id".to_string()),
+            ..out.clone()
+        };
+        &id_only
+    } else {
+        out
+    };
+
+    let Some(file) = file else {
+        if !register_only {
+            return Err(anyhow!("--file required"));
+        }
+        let resp = media_upload::register_media(client, &auth, media_type, &opts)?;
+        return out.emit(&resp);
+    };
+
+    if sources::is_glob(file) {
+        let mut results = Vec::new();
+        for file in sources::resolve_sources(file, &config.sources)? {
+            if cancel::is_cancelled() {
+                log::warn!("interrupted; returning {} uploaded files", results.len());
+                break;
+            }
+            results.push(media_upload::upload_media(
+                client, &auth, media_type, &file, &opts,
+            )?);
+        }
+        if matches.get_flag("id_only") {
+            return results.iter().try_for_each(|result| out.emit(result));
+        }
+        return out.emit(&Value::Array(results));
+    }
+
+    let resp = if sources::is_streamable_s3(file, &config.sources) {
+        let source = media_upload::MediaSource::S3 {
+            url: file,
+            opts: &config.sources.s3,
+        };
+        media_upload::upload_media(client, &auth, media_type, source, &opts)?
+    } else {
+        let file = sources::resolve_source(file, &config.sources)?;
+        media_upload::upload_media(client, &auth, media_type, &file, &opts)?
+    };
+    out.emit(&resp)
+}
+
+/// `--op-timeout`, else the op's default from the command tree; without
+/// either the client keeps the global `--timeout`.
+fn with_op_timeout(
+    client: &PinterestClient,
+    matches: &clap::ArgMatches,
+    op: Option<&Operation>,
+) -> PinterestClient {
+    let seconds = matches
+        .get_one::<u64>("op_timeout")
+        .copied()
+        .or_else(|| op.and_then(|op| op.timeout));
+    match seconds {
+        Some(seconds) => client.with_timeout(std::time::Duration::from_secs(seconds)),
+        None => client.clone(),
+    }
+}
+
+/// The OAuth scopes a user token needs for `op` (its `pinterest_oauth2`
+/// security requirement); empty when the tree doesn't say.
+fn required_scopes(op: &Operation) -> Vec<String> {
+    op.security
+        .iter()
+        .find_map(|req| req.get("pinterest_oauth2"))
+        .cloned()
+        .unwrap_or_default()
+}
 
-    let file = sources::resolve_source(file)?;
-    let resp = media_upload::upload_media(client, &auth, media_type, &file, wait)?;
-    write_json(&resp, pretty)?;
+/// Warns (or fails under `--strict`) before calling `op` with the saved
+/// session's token when the scopes it was granted lack one `op` needs.
+/// Tokens from elsewhere, or sessions without a recorded scope, aren't checked.
+fn check_scopes(op: &Operation, config: &Config, matches: &clap::ArgMatches) -> Result<()> {
+    let Some((_, session)) = &config.session else {
+        return Ok(());
+    };
+    let conversion = op
+        .security
+        .iter()
+        .any(|req| req.contains_key("conversion_token"));
+    if config.access_token.as_deref() != Some(session.access_token.as_str())
+        || (conversion && config.conversion_token.is_some())
+    {
+        return Ok(());
+    }
+    let Some(granted) = session.scopes() else {
+        return Ok(());
+    };
+    let missing: Vec<String> = required_scopes(op)
+        .into_iter()
+        .filter(|scope| !granted.contains(&scope.as_str()))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "{} {} needs scope {} but the session was granted {}; re-authorize with it, then `auth login`",
+        op.method,
+        op.path,
+        missing.join(", "),
+        granted.join(", ")
+    );
+    if matches.get_flag("strict") {
+        return Err(anyhow!("{message} (--strict)"));
+    }
+    log::warn!("{message}");
     Ok(())
 }
 
@@ -605,8 +4543,15 @@ fn find_op<'a>(tree: &'a CommandTree, res: &str, op: &str) -> Option<&'a Operati
 }
 
 fn select_auth(op: &Operation, config: &Config) -> Result<Auth> {
+    auth_choice(op, config).map(|(auth, _)| auth)
+}
+
+/// The auth [`select_auth`] picks, with why, for `--explain` and `describe`:
+/// basic when the op requires it, else a configured conversion token when the
+/// op accepts one, else the access token.
+fn auth_choice(op: &Operation, config: &Config) -> Result<(Auth, String)> {
     if op.security.iter().any(|req| req.contains_key("basic")) {
-        return Ok(Auth::Basic {
+        let auth = Auth::Basic {
             username: config
                 .client_id
                 .clone()
@@ -615,43 +4560,134 @@ fn select_auth(op: &Operation, config: &Config) -> Result<Auth> {
                 .client_secret
                 .clone()
                 .ok_or_else(|| anyhow!("PINTEREST_CLIENT_SECRET missing"))?,
-        });
+        };
+        let why = format!(
+            "basic auth with the client id{} and secret{}, because the operation requires basic auth",
+            credential_origin(config, "client_id"),
+            credential_origin(config, "client_secret")
+        );
+        return Ok((auth, why));
     }
 
-    if op
+    let accepts_conversion = op
         .security
         .iter()
-        .any(|req| req.contains_key("conversion_token"))
-    {
-        if let Some(token) = &config.conversion_token {
-            return Ok(Auth::Bearer(token.clone()));
-        }
+        .any(|req| req.contains_key("conversion_token"));
+    if accepts_conversion && let Some(token) = &config.conversion_token {
+        let why = format!(
+            "bearer auth with the conversion token{}, because the operation accepts one and it is set",
+            credential_origin(config, "conversion_token")
+        );
+        return Ok((Auth::Bearer(token.clone()), why));
     }
 
-    let token = config
-        .access_token
-        .clone()
-        .ok_or_else(|| anyhow!("PINTEREST_ACCESS_TOKEN missing"))?;
-    Ok(Auth::Bearer(token))
+    let token = config.access_token.clone().ok_or_else(|| {
+        if accepts_conversion {
+            anyhow!("PINTEREST_CONVERSION_TOKEN or PINTEREST_ACCESS_TOKEN missing")
+        } else {
+            anyhow!("PINTEREST_ACCESS_TOKEN missing")
+        }
+    })?;
+    let because = if accepts_conversion {
+        "no conversion token is set"
+    } else if op.security.is_empty() {
+        "the operation names no scheme and bearer is the default"
+    } else {
+        "the operation uses OAuth"
+    };
+    let why = format!(
+        "bearer auth with the access token{}, because {because}",
+        credential_origin(config, "access_token")
+    );
+    Ok((Auth::Bearer(token), why))
+}
+
+/// ` (from ORIGIN)` for a resolved config key, or nothing when unknown.
+fn credential_origin(config: &Config, key: &str) -> String {
+    config
+        .origins
+        .iter()
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, origin)| origin.as_deref())
+        .map(|origin| format!(" (from {origin})"))
+        .unwrap_or_default()
+}
+
+/// Conversion tokens are scoped to one ad account's `/ad_accounts/{id}/events`
+/// endpoint, so check the path up front instead of letting the API 400/401.
+fn conversion_auth(token: Option<&str>, path: &str) -> Result<Auth> {
+    let token = token.ok_or_else(|| anyhow!("PINTEREST_CONVERSION_TOKEN missing"))?;
+    let url_path = url::Url::parse(path).map(|u| u.path().to_string());
+    let segments: Vec<&str> = url_path
+        .as_deref()
+        .unwrap_or(path)
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let segments = match segments.first() {
+        Some(&"v5") => &segments[1..],
+        _ => &segments[..],
+    };
+    match segments {
+        ["ad_accounts", id, "events"] if !id.starts_with('{') => {
+            Ok(Auth::Bearer(token.to_string()))
+        }
+        ["ad_accounts", _, "events"] => Err(anyhow!(
+            "--auth conversion needs a concrete ad account id in the path (got {path})"
+        )),
+        _ => Err(anyhow!(
+            "--auth conversion only works for /ad_accounts/{{ad_account_id}}/events (got {path})"
+        )),
+    }
 }
 
-fn build_path(op: &Operation, matches: &clap::ArgMatches, config: &Config) -> Result<String> {
+fn build_path(
+    op: &Operation,
+    matches: &clap::ArgMatches,
+    config: &Config,
+    row: Option<&serde_json::Map<String, Value>>,
+) -> Result<String> {
     let mut path = op.path.clone();
 
     for param in op.params.iter().filter(|p| p.location == "path") {
-        let value = matches
-            .get_one::<String>(&param_key(param))
-            .cloned()
+        let value = row
+            .and_then(|row| row.get(&param.name))
+            .and_then(row_value)
+            // try_get: pipeline steps call this with matches that lack op flags.
+            .or_else(|| {
+                matches
+                    .try_get_one::<String>(&param_key(param))
+                    .ok()
+                    .flatten()
+                    .cloned()
+            })
+            .or_else(|| {
+                generic_params(matches)
+                    .ok()?
+                    .into_iter()
+                    .find(|(key, _)| *key == param.name)
+                    .and_then(|(_, mut values)| values.pop())
+            })
             .or_else(|| {
                 if param.name == "ad_account_id" {
                     config.ad_account_id.clone()
                 } else {
                     None
                 }
-            });
+            })
+            .or_else(|| config.path_defaults.get(&param.name).cloned());
 
         let Some(value) = value else {
-            return Err(anyhow!("missing required path param: {}", param.name));
+            return Err(error::CliError::MissingPathParam {
+                op: op.name.clone(),
+                path: op.path.clone(),
+                param: param.name.clone(),
+                flag: param.flag.clone(),
+            }
+            .into());
         };
 
         let encoded = urlencoding::encode(&value);
@@ -665,9 +4701,48 @@ fn build_path(op: &Operation, matches: &clap::ArgMatches, config: &Config) -> Re
     Ok(path)
 }
 
-fn build_query_params(op: &Operation, matches: &clap::ArgMatches) -> Result<Vec<(String, String)>> {
+fn build_query_params(
+    op: &Operation,
+    matches: &clap::ArgMatches,
+    opts: &SourceOptions,
+) -> Result<Vec<(String, String)>> {
     let params_json = matches.get_one::<String>("params");
-    let mut out = parse_params_json(params_json, &op.params)?;
+    let strict = matches.get_flag("strict");
+    let mut out = parse_params_json(params_json, &op.params, strict, opts)?;
+
+    // --param overrides --params; the per-param flags below override both.
+    for (key, values) in generic_params(matches)? {
+        let param = op.params.iter().find(|p| p.name == key);
+        match param.map(|p| p.location.as_str()) {
+            // build_path reads these.
+            Some("path") => continue,
+            Some("query") | None => {}
+            Some(location) => {
+                return Err(anyhow!(
+                    "--param {key}: {key} is a {location} param; use --{}",
+                    param.map(|p| p.flag.as_str()).unwrap_or_default()
+                ));
+            }
+        }
+        remove_query_key(&mut out, &key, param.and_then(|p| p.style.as_deref()));
+        match param {
+            Some(param) if param.style.as_deref() == Some("deepObject") => {
+                let raw = values.last().map(String::as_str).unwrap_or_default();
+                let value = parse_json_source(raw, opts)?;
+                out.extend(encode_deep_object(Some(param), &key, &value, strict)?);
+            }
+            Some(param) if param.schema_type == "array" => {
+                let values = values
+                    .iter()
+                    .flat_map(|v| v.split(','))
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                push_array(&mut out, param, values);
+            }
+            _ => out.extend(values.into_iter().map(|v| (key.clone(), v))),
+        }
+    }
 
     for param in op.params.iter().filter(|p| p.location == "query") {
         let key = param.name.clone();
@@ -675,9 +4750,14 @@ fn build_query_params(op: &Operation, matches: &clap::ArgMatches) -> Result<Vec<
         if param.schema_type == "array" {
             if let Some(values) = matches.get_many::<String>(&param_key(param)) {
                 remove_query_key(&mut out, &key, param.style.as_deref());
-                for v in values {
-                    out.push((key.clone(), v.clone()));
-                }
+                // `--columns A,B` equals `--columns A --columns B`; values with a
+                // literal comma have to come through --params instead.
+                let values = values
+                    .flat_map(|v| v.split(','))
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                push_array(&mut out, param, values);
             }
             continue;
         }
@@ -685,8 +4765,8 @@ fn build_query_params(op: &Operation, matches: &clap::ArgMatches) -> Result<Vec<
         if param.style.as_deref() == Some("deepObject") {
             if let Some(raw) = matches.get_one::<String>(&param_key(param)) {
                 remove_query_key(&mut out, &key, param.style.as_deref());
-                let value = parse_json_source(raw)?;
-                out.extend(encode_deep_object(&key, &value)?);
+                let value = parse_json_source(raw, opts)?;
+                out.extend(encode_deep_object(Some(param), &key, &value, strict)?);
             }
             continue;
         }
@@ -697,9 +4777,146 @@ fn build_query_params(op: &Operation, matches: &clap::ArgMatches) -> Result<Vec<
         }
     }
 
+    if let Some(prefix) = matches.get_one::<String>("params_from_env") {
+        let strict = matches.get_flag("strict");
+        for (key, value) in env_params(prefix) {
+            let Some(param) = op
+                .params
+                .iter()
+                .find(|p| p.location == "query" && p.name == key)
+            else {
+                log::debug!(
+                    "{prefix}{key}: {} has no query param {key}; skipped",
+                    op.path
+                );
+                continue;
+            };
+            let nested = format!("{key}[");
+            if out.iter().any(|(k, _)| *k == key || k.starts_with(&nested)) {
+                continue;
+            }
+            if param.style.as_deref() == Some("deepObject") {
+                let value: Value = serde_json::from_str(&value)
+                    .map_err(|err| anyhow!("invalid JSON in env param {key}: {err}"))?;
+                out.extend(encode_deep_object(Some(param), &key, &value, strict)?);
+            } else if param.schema_type == "array" {
+                let values = value
+                    .split(',')
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                push_array(&mut out, param, values);
+            } else {
+                out.push((key, value));
+            }
+        }
+    }
+
     Ok(out)
 }
 
+/// Env vars named `<prefix><param>`, keyed by the lowercased suffix. Vars
+/// whose name or value isn't UTF-8 are skipped.
+fn env_params(prefix: &str) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = env::vars_os()
+        .filter_map(|(name, value)| {
+            let (name, value) = (name.into_string().ok()?, value.into_string().ok()?);
+            let key = name.strip_prefix(prefix)?.to_ascii_lowercase();
+            (!key.is_empty()).then_some((key, value))
+        })
+        .collect();
+    params.sort();
+    params
+}
+
+/// The op's `in: header` params given as flags, sent with every request of the run.
+fn build_header_params(op: &Operation, matches: &clap::ArgMatches) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for param in op.params.iter().filter(|p| p.location == "header") {
+        let Some(value) = matches.get_one::<String>(&param_key(param)) else {
+            continue;
+        };
+        let name = HeaderName::from_bytes(param.name.as_bytes())
+            .map_err(|err| anyhow!("invalid header param {}: {err}", param.name))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|err| anyhow!("invalid value for --{}: {err}", param.flag))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// Maps `--since`/`--until` onto the op's own start/end date params.
+fn apply_date_window(
+    op: &Operation,
+    query: &mut Vec<(String, String)>,
+    matches: &clap::ArgMatches,
+) -> Result<()> {
+    let since = matches.get_one::<String>("since");
+    let until = matches.get_one::<String>("until");
+    if since.is_none() && until.is_none() {
+        return Ok(());
+    }
+
+    let has_param = |name: &str| {
+        op.params
+            .iter()
+            .any(|p| p.location == "query" && p.name == name)
+    };
+    let Some((start, end)) = dates::WINDOW_PARAMS
+        .iter()
+        .find(|(start, end)| has_param(start) && has_param(end))
+    else {
+        return Err(anyhow!(
+            "--since/--until not supported: {} has no date window params",
+            op.path
+        ));
+    };
+
+    for (key, raw) in [(start, since), (end, until)] {
+        let Some(raw) = raw else { continue };
+        let date = dates::parse_date_expr(raw)?;
+        remove_query_key(query, key, None);
+        query.push((key.to_string(), date.format("%Y-%m-%d").to_string()));
+    }
+    Ok(())
+}
+
+/// Clamps `page_size` to the endpoint's documented maximum and, under `--all`,
+/// defaults it to that maximum so big dumps take fewer round trips.
+fn apply_page_size(op: &Operation, query: &mut Vec<(String, String)>, all: bool) -> Result<()> {
+    let Some(param) = op
+        .params
+        .iter()
+        .find(|p| p.name == "page_size" && p.location == "query")
+    else {
+        if query.iter().any(|(k, _)| k == "page_size") {
+            log::warn!("{} does not take page_size; sending it anyway", op.path);
+        }
+        return Ok(());
+    };
+
+    if let Some((_, value)) = query.iter_mut().find(|(k, _)| k == "page_size") {
+        let requested: u64 = value
+            .parse()
+            .with_context(|| format!("invalid page size: {value}"))?;
+        if let Some(max) = param.maximum
+            && requested as f64 > max
+        {
+            log::warn!("page_size {requested} exceeds the endpoint max {max}; using {max}");
+            *value = (max as u64).to_string();
+        }
+        return Ok(());
+    }
+
+    if all
+        && op.paginated
+        && let Some(max) = param.maximum
+    {
+        query.push(("page_size".to_string(), (max as u64).to_string()));
+    }
+    Ok(())
+}
+
 fn remove_query_key(out: &mut Vec<(String, String)>, key: &str, style: Option<&str>) {
     if style == Some("deepObject") {
         let prefix = format!("{key}[");
@@ -709,46 +4926,114 @@ fn remove_query_key(out: &mut Vec<(String, String)>, key: &str, style: Option<&s
     out.retain(|(k, _)| k != key);
 }
 
+/// `--param KEY=VALUE`s grouped by key, in first-seen order.
+fn generic_params(matches: &clap::ArgMatches) -> Result<Vec<(String, Vec<String>)>> {
+    let mut out: Vec<(String, Vec<String>)> = Vec::new();
+    // try_get: pipeline steps call this with matches that lack op flags.
+    for raw in matches
+        .try_get_many::<String>("param")
+        .ok()
+        .flatten()
+        .into_iter()
+        .flatten()
+    {
+        let (key, value) = raw
+            .split_once('=')
+            .filter(|(key, _)| !key.is_empty())
+            .ok_or_else(|| anyhow!("--param expects KEY=VALUE, got {raw:?}"))?;
+        match out.iter_mut().find(|(k, _)| k == key) {
+            Some((_, values)) => values.push(value.to_string()),
+            None => out.push((key.to_string(), vec![value.to_string()])),
+        }
+    }
+    Ok(out)
+}
+
 fn parse_params_json(
     params_json: Option<&String>,
     params: &[ParamDef],
+    strict: bool,
+    opts: &SourceOptions,
 ) -> Result<Vec<(String, String)>> {
     let Some(raw) = params_json else {
         return Ok(Vec::new());
     };
-    let value: Value = serde_json::from_str(raw).context("invalid JSON for --params")?;
+    let value = parse_json_source(raw, opts).context("--params")?;
     let Value::Object(map) = value else {
-        return Err(anyhow!("--params must be a JSON object"));
+        return Err(anyhow!("--params must be an object"));
     };
 
     let mut out = Vec::new();
     for (k, v) in map {
-        let style = params
-            .iter()
-            .find(|p| p.location == "query" && p.name == k)
-            .and_then(|p| p.style.as_deref());
+        let param = params.iter().find(|p| p.location == "query" && p.name == k);
+        let style = param.and_then(|p| p.style.as_deref());
 
         if style == Some("deepObject") {
-            out.extend(encode_deep_object(&k, &v)?);
+            out.extend(encode_deep_object(param, &k, &v, strict)?);
             continue;
         }
 
-        match v {
-            Value::Array(values) => {
+        match (v, param) {
+            (Value::Array(values), Some(param)) => {
+                let values = values
+                    .iter()
+                    .map(json_value_to_string)
+                    .collect::<Result<_>>()?;
+                push_array(&mut out, param, values);
+            }
+            (Value::Array(values), None) => {
                 for item in values {
                     out.push((k.clone(), json_value_to_string(&item)?));
                 }
             }
-            _ => out.push((k, json_value_to_string(&v)?)),
+            (v, _) => out.push((k, json_value_to_string(&v)?)),
         }
     }
     Ok(out)
 }
 
-fn encode_deep_object(prefix: &str, value: &Value) -> Result<Vec<(String, String)>> {
+/// Array query params repeat the key (`explode`, the OpenAPI default) or, with
+/// `explode: false`, send one comma-joined value.
+fn push_array(out: &mut Vec<(String, String)>, param: &ParamDef, values: Vec<String>) {
+    if param.explode == Some(false) {
+        out.push((param.name.clone(), values.join(",")));
+        return;
+    }
+    for value in values {
+        out.push((param.name.clone(), value));
+    }
+}
+
+/// Encodes `value` as `prefix[key]=...` pairs. Keys the param's schema doesn't
+/// list are likely typos the server would silently ignore: a warning, or an
+/// error under `--strict`.
+fn encode_deep_object(
+    param: Option<&ParamDef>,
+    prefix: &str,
+    value: &Value,
+    strict: bool,
+) -> Result<Vec<(String, String)>> {
     let Value::Object(map) = value else {
         return Err(anyhow!("deepObject param must be a JSON object"));
     };
+    if let Some(fields) = param.and_then(|p| p.fields.as_ref()) {
+        let unknown: Vec<&str> = map
+            .keys()
+            .filter(|k| !fields.contains(k))
+            .map(|k| k.as_str())
+            .collect();
+        if !unknown.is_empty() {
+            let message = format!(
+                "unknown {prefix} key(s) {}; known: {}",
+                unknown.join(", "),
+                fields.join(", ")
+            );
+            if strict {
+                return Err(anyhow!("{message} (--strict)"));
+            }
+            log::warn!("{message}");
+        }
+    }
 
     fn walk(out: &mut Vec<(String, String)>, key: &str, value: &Value) -> Result<()> {
         match value {
@@ -779,9 +5064,82 @@ fn encode_deep_object(prefix: &str, value: &Value) -> Result<Vec<(String, String
     Ok(out)
 }
 
-fn build_body(op: &Operation, matches: &clap::ArgMatches) -> Result<Option<Body>> {
-    let body_arg = matches.get_one::<String>("body");
+/// `resource_op.json` from the template directory, with `${NAME}` in its
+/// string values replaced from the environment; `None` when there is no such
+/// file (or no directory).
+fn body_template(dir: Option<&str>, resource: &str, op: &Operation) -> Result<Option<Value>> {
+    let Some(dir) = dir else {
+        return Ok(None);
+    };
+    let path = std::path::Path::new(dir).join(format!("{resource}_{}.json", op.name));
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(anyhow!("read template {}: {err}", path.display())),
+    };
+    let mut template: Value = serde_json::from_str(&text)
+        .map_err(|err| anyhow!("template {}: invalid JSON: {err}", path.display()))?;
+    expand_env(&mut template).map_err(|err| anyhow!("template {}: {err}", path.display()))?;
+    log::debug!("using body template {}", path.display());
+    Ok(Some(template))
+}
+
+/// Replaces `${NAME}` in every string of `value` with the environment
+/// variable's value; an unset variable is an error.
+fn expand_env(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(text) => {
+            let mut out = String::new();
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("${") {
+                let end = rest[start..]
+                    .find('}')
+                    .ok_or_else(|| anyhow!("unterminated ${{ in {text:?}"))?;
+                let name = &rest[start + 2..start + end];
+                let var = std::env::var(name)
+                    .map_err(|_| anyhow!("environment variable {name} is not set"))?;
+                out.push_str(&rest[..start]);
+                out.push_str(&var);
+                rest = &rest[start + end + 1..];
+            }
+            out.push_str(rest);
+            *text = out;
+        }
+        Value::Array(items) => items.iter_mut().try_for_each(expand_env)?,
+        Value::Object(map) => map.values_mut().try_for_each(expand_env)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Merges `over` into `base`: objects key by key, anything else replaced.
+fn merge_json(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+fn build_body(
+    op: &Operation,
+    matches: &clap::ArgMatches,
+    opts: &SourceOptions,
+    template: Option<Value>,
+) -> Result<Option<Body>> {
+    let body_arg = body_sources(matches);
     let form_arg = matches.get_one::<String>("form");
+    if let Some(template) = template {
+        return templated_body(op, matches, opts, template);
+    }
 
     let Some(rb) = &op.request_body else {
         if body_arg.is_some() || form_arg.is_some() {
@@ -790,70 +5148,222 @@ fn build_body(op: &Operation, matches: &clap::ArgMatches) -> Result<Option<Body>
         return Ok(None);
     };
 
-    if rb.content_types.iter().any(|ct| ct == "application/json") {
-        let Some(raw) = body_arg else {
-            if rb.required {
-                return Err(anyhow!("--body required"));
-            }
-            return Ok(None);
-        };
-        return Ok(Some(Body::Json(parse_json_source(raw)?)));
-    }
-
-    if rb
+    // Pick the encoding from the flag given, so ops accepting both JSON and
+    // urlencoded bodies (whatever the method: POST, PUT or PATCH) can use either.
+    let json = rb.content_types.iter().any(|ct| ct == "application/json");
+    let form = rb
         .content_types
         .iter()
-        .any(|ct| ct == "application/x-www-form-urlencoded")
-    {
-        let Some(raw) = form_arg else {
-            if rb.required {
-                return Err(anyhow!("--form required"));
-            }
-            return Ok(None);
-        };
-        return Ok(Some(Body::Form(parse_form_source(raw)?)));
+        .any(|ct| ct == "application/x-www-form-urlencoded");
+    if !json && !form {
+        return Err(anyhow!(
+            "unsupported request content types: {}",
+            rb.content_types.join(", ")
+        ));
+    }
+    match (body_arg, form_arg) {
+        (Some(_), Some(_)) => Err(body_and_form(Some(op))),
+        (Some(raws), None) if json => Ok(Some(json_body(&raws, matches, opts)?)),
+        (None, Some(raw)) if form => Ok(Some(Body::Form(parse_form_source(raw, opts)?))),
+        (Some(_), None) => Err(anyhow!(
+            "{} {} takes a form body; use --form instead of --body",
+            op.method,
+            op.path
+        )),
+        (None, Some(_)) => Err(anyhow!(
+            "{} {} takes a JSON body; use --body instead of --form",
+            op.method,
+            op.path
+        )),
+        (None, None) if rb.required => Err(anyhow!(match (json, form) {
+            (true, true) => "--body or --form required",
+            (true, false) => "--body required",
+            _ => "--form required",
+        })),
+        (None, None) => Ok(None),
     }
+}
 
-    Err(anyhow!(
-        "unsupported request content types: {}",
-        rb.content_types.join(", ")
-    ))
+/// A JSON body built on a template: `--body` fields win, at any depth.
+fn templated_body(
+    op: &Operation,
+    matches: &clap::ArgMatches,
+    opts: &SourceOptions,
+    mut template: Value,
+) -> Result<Option<Body>> {
+    let takes_json = op
+        .request_body
+        .as_ref()
+        .is_some_and(|rb| rb.content_types.iter().any(|ct| ct == "application/json"));
+    if !takes_json {
+        return Err(anyhow!(
+            "{} {} takes no JSON body, but a body template exists for it",
+            op.method,
+            op.path
+        ));
+    }
+    if matches.contains_id("form") {
+        return Err(anyhow!(
+            "--form can't be used with a body template; the template is JSON"
+        ));
+    }
+    if matches.get_flag("body_stream") {
+        return Err(anyhow!(
+            "--body-stream sends the file as-is and can't be merged with a body template"
+        ));
+    }
+    if let Some(raws) = body_sources(matches) {
+        merge_json(&mut template, layered_body(&raws, opts)?);
+    }
+    Ok(Some(Body::Json(template)))
 }
 
-fn parse_json_source(raw: &str) -> Result<Value> {
-    let text = if sources::looks_like_source(raw) {
-        sources::read_source_to_string(raw)?
-    } else {
-        raw.to_string()
+/// Why `--body` and `--form` can't both be given: they are two encodings of
+/// the one request body, not separate parts of it.
+fn body_and_form(op: Option<&Operation>) -> anyhow::Error {
+    let accepts = op
+        .and_then(|op| op.request_body.as_ref().map(|rb| (op, rb)))
+        .map(|(op, rb)| {
+            format!(
+                "; {} {} accepts {}",
+                op.method,
+                op.path,
+                rb.content_types.join(" or ")
+            )
+        })
+        .unwrap_or_default();
+    anyhow!(
+        "--body and --form are mutually exclusive: a request has a single body, sent either as JSON (--body) or urlencoded (--form){accepts}. Put every field in one of them; path and query params (e.g. --ad-account-id, --params) combine with either"
+    )
+}
+
+/// Every `--body` given, in order.
+fn body_sources(matches: &clap::ArgMatches) -> Option<Vec<&str>> {
+    matches
+        .get_many::<String>("body")
+        .map(|raws| raws.map(String::as_str).collect())
+}
+
+/// Repeated `--body` values parsed and deep-merged in order, later wins; each
+/// must then be an object. A single value is taken as-is.
+fn layered_body(raws: &[&str], opts: &SourceOptions) -> Result<Value> {
+    if let [raw] = raws {
+        return parse_json_source(raw, opts);
+    }
+    let mut body = Value::Object(serde_json::Map::new());
+    for (index, raw) in raws.iter().enumerate() {
+        let layer =
+            parse_json_source(raw, opts).with_context(|| format!("--body #{}", index + 1))?;
+        if !layer.is_object() {
+            return Err(anyhow!(
+                "--body #{} ({raw}) is not a JSON object; repeated --body values are merged, so each must be one",
+                index + 1
+            ));
+        }
+        merge_json(&mut body, layer);
+    }
+    Ok(body)
+}
+
+/// `--body` as parsed JSON, or with `--body-stream` the source file sent verbatim.
+fn json_body(raws: &[&str], matches: &clap::ArgMatches, opts: &SourceOptions) -> Result<Body> {
+    if !matches.get_flag("body_stream") {
+        return Ok(Body::Json(layered_body(raws, opts)?));
+    }
+    let [raw] = raws else {
+        return Err(anyhow!(
+            "--body-stream sends one file as-is; repeated --body values can't be merged with it"
+        ));
     };
-    serde_json::from_str(&text).context("invalid JSON")
+    if matches.get_flag("gzip_body") {
+        return Err(anyhow!(
+            "--gzip-body can't be combined with --body-stream, which sends the file as-is"
+        ));
+    }
+    if !sources::looks_like_source(raw) {
+        return Err(anyhow!(
+            "--body-stream needs a file/URL/S3 source for --body"
+        ));
+    }
+    let file = sources::resolve_source(raw, opts)?;
+    Ok(Body::JsonFile(Arc::new(file)))
 }
 
-fn parse_form_source(raw: &str) -> Result<Vec<(String, String)>> {
+/// Inline text or a source, as JSON or (per `--input-format` and the file
+/// extension) YAML.
+fn parse_json_source(raw: &str, opts: &SourceOptions) -> Result<Value> {
     let text = if sources::looks_like_source(raw) {
-        sources::read_source_to_string(raw)?
+        sources::read_source_to_string(raw, opts)?
     } else {
         raw.to_string()
     };
-    let value: Value = serde_json::from_str(&text).context("invalid JSON for --form")?;
+    match opts.input_format.for_source(raw) {
+        InputFormat::Json => serde_json::from_str(&text).context("invalid JSON"),
+        InputFormat::Yaml => {
+            serde_yaml::from_str(&text).map_err(|err| anyhow!("invalid YAML: {err}"))
+        }
+    }
+}
+
+fn parse_form_source(raw: &str, opts: &SourceOptions) -> Result<Vec<(String, String)>> {
+    let value = parse_json_source(raw, opts).context("--form")?;
     let Value::Object(map) = value else {
-        return Err(anyhow!("--form must be a JSON object"));
+        return Err(anyhow!("--form must be an object"));
     };
+    form_fields(map, opts)
+}
 
+/// Flattens a JSON object into form fields; arrays repeat the key.
+fn form_fields(
+    map: serde_json::Map<String, Value>,
+    opts: &SourceOptions,
+) -> Result<Vec<(String, String)>> {
     let mut out = Vec::new();
     for (k, v) in map {
         match v {
             Value::Array(values) => {
                 for item in values {
-                    out.push((k.clone(), json_value_to_string(&item)?));
+                    out.push((k.clone(), form_field_value(&item, opts)?));
                 }
             }
-            _ => out.push((k, json_value_to_string(&v)?)),
+            _ => {
+                let value = form_field_value(&v, opts)?;
+                out.push((k, value));
+            }
         }
     }
     Ok(out)
 }
 
+/// urlencoded bodies are built in memory, so file-backed fields are capped.
+const MAX_FORM_FIELD_BYTES: u64 = 10 * 1024 * 1024;
+
+/// A string value starting with `@` is read from a source (`@file`,
+/// `@s3://...`, `@https://...`); `@@` escapes a literal leading `@`.
+fn form_field_value(value: &Value, opts: &SourceOptions) -> Result<String> {
+    let Value::String(text) = value else {
+        return json_value_to_string(value);
+    };
+    if let Some(literal) = text.strip_prefix("@@") {
+        return Ok(format!("@{literal}"));
+    }
+    let Some(source) = text.strip_prefix('@') else {
+        return Ok(text.clone());
+    };
+    let file = sources::resolve_source(source, opts)?;
+    let len = std::fs::metadata(&file.path)
+        .with_context(|| format!("stat {}", file.path.display()))?
+        .len();
+    if len > MAX_FORM_FIELD_BYTES {
+        return Err(anyhow!(
+            "form field source {source} is {len} bytes; the limit is {MAX_FORM_FIELD_BYTES}"
+        ));
+    }
+    std::fs::read_to_string(&file.path).map_err(|err| {
+        anyhow!("form field source {source} must be UTF-8 text (e.g. base64): {err}")
+    })
+}
+
 fn json_value_to_string(value: &Value) -> Result<String> {
     match value {
         Value::String(v) => Ok(v.clone()),
@@ -861,27 +5371,354 @@ fn json_value_to_string(value: &Value) -> Result<String> {
     }
 }
 
-fn write_json(value: &Value, pretty: bool) -> Result<()> {
-    if pretty {
-        write_stdout_line(&serde_json::to_string_pretty(value)?)
-    } else {
-        write_stdout_line(&serde_json::to_string(value)?)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_server::{Response, TestServer};
+
+    fn parse(args: &[&str]) -> clap::ArgMatches {
+        let tree = command_tree::load_command_tree();
+        build_cli(&tree)
+            .try_get_matches_from(std::iter::once("pinterest-ads").chain(args.iter().copied()))
+            .unwrap()
     }
-}
 
-fn write_stdout_line(value: &str) -> Result<()> {
-    let mut out = std::io::stdout().lock();
-    if let Err(err) = out.write_all(value.as_bytes()) {
-        if err.kind() == std::io::ErrorKind::BrokenPipe {
-            std::process::exit(0);
-        }
-        return Err(err.into());
+    #[test]
+    fn stdin_readers_refuse_config_stdin() {
+        let matches = parse(&["--config-stdin", "diff", "--baseline", "b.json"]);
+        let (_, diff) = matches.subcommand().unwrap();
+        let err = claim_stdin(diff, "diff", "pass FILE").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "diff and --config-stdin both read stdin; pass FILE"
+        );
+        let matches = parse(&["diff", "--baseline", "b.json"]);
+        let (_, diff) = matches.subcommand().unwrap();
+        assert!(claim_stdin(diff, "diff", "pass FILE").is_ok());
+    }
+
+    #[test]
+    fn parse_result_rejects_empty_input() {
+        assert!(parse_result("").is_err());
+        assert!(parse_result(" \n\t\n").is_err());
+        assert_eq!(parse_result("[]").unwrap(), serde_json::json!([]));
+        assert_eq!(
+            parse_result("{\"id\":1}\n\n{\"id\":2}\n").unwrap(),
+            serde_json::json!([{"id": 1}, {"id": 2}])
+        );
+    }
+
+    #[test]
+    fn version_parts_ignores_trailing_zeros() {
+        assert_eq!(version_parts("5.23"), version_parts("5.23.0"));
+        assert_eq!(version_parts("v5.23.0"), vec![5, 23]);
+        assert!(version_parts("5.24") > version_parts("5.23.0"));
+        assert!(version_parts("5.23.1") > version_parts("5.23"));
+        assert!(version_parts("6") > version_parts("5.99.9"));
+    }
+
+    #[test]
+    fn parallel_map_keeps_index_order() {
+        let results = parallel_map(20, 4, None, |i| i * 2);
+        assert_eq!(results, (0..20).map(|i| Some(i * 2)).collect::<Vec<_>>());
+        assert!(parallel_map(0, 4, None, |i| i).is_empty());
+    }
+
+    #[test]
+    fn dedupe_sends_concurrent_identical_gets_once() {
+        let server = TestServer::start(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            Response::json(200, &serde_json::json!({"id": "1"}))
+        });
+        let gets = |flags: &[&str]| {
+            let mut args = vec!["--config", "/dev/null", "--base-url", server.url()];
+            args.extend(flags);
+            args.extend(["raw", "GET", "/campaigns/1"]);
+            let matches = parse(&args);
+            let config = config::load_config(&command_tree::load_command_tree(), &matches).unwrap();
+            let client = api_client(&matches, &config).unwrap();
+            let url = client.build_url("/campaigns/1").unwrap();
+            let auth = Auth::Bearer("t".into());
+            std::thread::scope(|scope| {
+                for _ in 0..4 {
+                    scope.spawn(|| client.request("GET", &url, &auth, &[], None).unwrap());
+                }
+            });
+        };
+        gets(&["--dedupe"]);
+        assert_eq!(server.requests().len(), 1);
+        gets(&[]);
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[test]
+    fn deep_object_params_encode_special_character_keys() {
+        let value = serde_json::json!({"name is": {"a&b": "x y"}, "ids": [1, 2], "skip": null});
+        let pairs = encode_deep_object(None, "filter", &value, false).unwrap();
+        assert_eq!(
+            query::encode_pairs(&pairs),
+            "filter[name%20is][a%26b]=x%20y&filter[ids]=1&filter[ids]=2"
+        );
+    }
+
+    /// A one-resource tree (`things`) holding `ops`, for op shapes the real
+    /// API doesn't have.
+    fn things_tree(ops: Value) -> CommandTree {
+        serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "api_version": "v5",
+            "base_url": "https://api.example.com/v5",
+            "resources": [{"name": "things", "ops": ops}],
+        }))
+        .unwrap()
+    }
+
+    /// Runs the CLI with `args` against `server`.
+    fn run_against(tree: &CommandTree, server: &TestServer, args: &[&str]) -> Result<()> {
+        let mut argv = vec![
+            "pinterest-ads",
+            "--config",
+            "/dev/null",
+            "--base-url",
+            server.url(),
+        ];
+        argv.extend(["--access-token", "t"]);
+        argv.extend(args);
+        let matches = build_cli(tree).try_get_matches_from(argv)?;
+        run(tree, &matches)
+    }
+
+    fn path_param(name: &str) -> Value {
+        serde_json::json!({
+            "name": name, "flag": name.replace('_', "-"), "in": "path", "required": true,
+            "style": null, "explode": null, "schema_type": "string", "items_type": null,
+        })
+    }
+
+    #[test]
+    fn auto_batch_merges_mixed_responses_in_chunk_order() {
+        // Chunks of one item each; the item says what its response looks like.
+        let server = TestServer::start(|req| {
+            let body: Value = serde_json::from_slice(&req.body).unwrap();
+            let item = &body[0];
+            match item["reply"].as_str().unwrap() {
+                "items" => Response::json(200, &serde_json::json!({"items": [item["n"]]})),
+                "array" => Response::json(200, &serde_json::json!([item["n"]])),
+                _ => Response::json(200, &serde_json::json!({"ok": item["n"]})),
+            }
+        });
+        let client = PinterestClient::new(server.url()).unwrap();
+        let items = serde_json::json!([
+            {"n": 0, "reply": "items"},
+            {"n": 1, "reply": "other"},
+            {"n": 2, "reply": "array"},
+            {"n": 3, "reply": "other"},
+            {"n": 4, "reply": "items"},
+        ]);
+        let url = client.build_url("/things").unwrap();
+        let chunked = send_chunks(
+            &client,
+            "POST",
+            &url,
+            &Auth::Bearer("t".into()),
+            &[],
+            Some(Body::Json(items)),
+            1,
+            3,
+            None,
+        )
+        .unwrap();
+        assert!(chunked.failure.is_none());
+        assert_eq!(
+            chunked.merged,
+            serde_json::json!({"items": [0, 2, 4], "other": [{"ok": 1}, {"ok": 3}]})
+        );
     }
-    if let Err(err) = out.write_all(b"\n") {
-        if err.kind() == std::io::ErrorKind::BrokenPipe {
-            std::process::exit(0);
+
+    #[test]
+    fn policy_covers_the_ops_a_command_calls() {
+        let tree = command_tree::load_command_tree();
+        let server = TestServer::start(|_| {
+            Response::json(
+                200,
+                &serde_json::json!({"media_id": "m1", "upload_url": "x"}),
+            )
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("v.mp4");
+        std::fs::write(&video, b"video").unwrap();
+        let video = video.to_str().unwrap();
+        let run_denying = |denied: &str, args: &[&str]| {
+            let config = dir.path().join("config.toml");
+            std::fs::write(&config, format!("denied_operations = [\"{denied}\"]\n")).unwrap();
+            let mut argv = vec!["pinterest-ads", "--config", config.to_str().unwrap()];
+            argv.extend(["--base-url", server.url(), "--access-token", "t"]);
+            argv.extend(args);
+            run(&tree, &build_cli(&tree).try_get_matches_from(argv).unwrap())
+        };
+        let upload = ["media", "upload", "--media-type", "video"];
+        let send = [
+            "conversions",
+            "send",
+            "--ad-account-id",
+            "1",
+            "--file",
+            video,
+        ];
+
+        for (denied, args) in [
+            (
+                "media create",
+                &[&upload[..], &["--register-only"]].concat(),
+            ),
+            ("POST", &[&upload[..], &["--register-only"]].concat()),
+            (
+                "media get",
+                &[&upload[..], &["--file", video, "--wait"]].concat(),
+            ),
+            ("events", &send[..].to_vec()),
+            ("events create", &send[..].to_vec()),
+        ] {
+            let err = run_denying(denied, args).unwrap_err();
+            assert!(
+                err.to_string().contains("blocked by denied_operations"),
+                "{denied}: {err}"
+            );
         }
-        return Err(err.into());
+        assert!(server.requests().is_empty());
+
+        run_denying("media get", &[&upload[..], &["--register-only"]].concat()).unwrap();
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn conversions_send_goes_to_every_ad_account() {
+        let tree = command_tree::load_command_tree();
+        let server = TestServer::start(|_| {
+            Response::json(
+                200,
+                &serde_json::json!({"num_events_received": 1, "events": []}),
+            )
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("events.ndjson");
+        std::fs::write(&file, "{\"event_name\": \"checkout\"}\n").unwrap();
+        let file = file.to_str().unwrap();
+
+        run_against(
+            &tree,
+            &server,
+            &[
+                "conversions",
+                "send",
+                "--ad-account-id",
+                "a1,a2",
+                "--file",
+                file,
+            ],
+        )
+        .unwrap();
+        let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths, ["/ad_accounts/a1/events", "/ad_accounts/a2/events"]);
+
+        let err = run_against(
+            &tree,
+            &server,
+            &[
+                "conversions",
+                "send",
+                "--ad-account-id",
+                "a1,a2",
+                "--file",
+                "-",
+            ],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("read only once"), "{err}");
+        let err =
+            run_against(&tree, &server, &["--ad-account-id", "a1,a2", "batch", file]).unwrap_err();
+        assert_eq!(err.to_string(), "batch takes a single --ad-account-id");
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn put_with_a_required_json_body() {
+        let tree = things_tree(serde_json::json!([{
+            "name": "replace", "method": "PUT", "path": "/things/{thing_id}",
+            "tags": [], "paginated": false, "security": [],
+            "params": [path_param("thing_id")],
+            "request_body": {"required": true, "content_types": ["application/json"]},
+        }]));
+        let server = TestServer::start(|_| Response::json(200, &serde_json::json!({"id": "1"})));
+        let args = ["things", "replace", "--thing-id", "1"];
+
+        let err = run_against(&tree, &server, &args).unwrap_err();
+        assert_eq!(err.to_string(), "--body required");
+        let err =
+            run_against(&tree, &server, &[&args[..], &["--form", "a=1"]].concat()).unwrap_err();
+        assert!(err.to_string().contains("takes a JSON body"), "{err}");
+        assert!(server.requests().is_empty());
+
+        run_against(
+            &tree,
+            &server,
+            &[&args[..], &["--body", r#"{"name": "x"}"#]].concat(),
+        )
+        .unwrap();
+        let [request] = server.requests().try_into().unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("PUT", "/things/1")
+        );
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(
+            serde_json::from_slice::<Value>(&request.body).unwrap(),
+            serde_json::json!({"name": "x"})
+        );
+    }
+
+    #[test]
+    fn required_header_params_are_sent_as_headers() {
+        let header = serde_json::json!({
+            "name": "X-Ad-Account-Region", "flag": "ad-account-region", "in": "header",
+            "required": true, "style": null, "explode": null, "schema_type": "string",
+            "items_type": null,
+        });
+        let tree = things_tree(serde_json::json!([{
+            "name": "get", "method": "GET", "path": "/things/{thing_id}",
+            "tags": [], "paginated": false, "security": [],
+            "params": [path_param("thing_id"), header],
+            "request_body": null,
+        }]));
+        let server = TestServer::start(|_| Response::json(200, &serde_json::json!({"id": "1"})));
+        let args = ["things", "get", "--thing-id", "1"];
+
+        let err = run_against(&tree, &server, &args).unwrap_err();
+        assert!(err.to_string().contains("--ad-account-region"), "{err}");
+        assert!(server.requests().is_empty());
+
+        run_against(
+            &tree,
+            &server,
+            &[&args[..], &["--ad-account-region", "eu"]].concat(),
+        )
+        .unwrap();
+        // --header replaces a header param of the same name.
+        let with_header = [
+            &args[..],
+            &["--ad-account-region", "eu"],
+            &["-H", "x-ad-account-region: us"],
+        ];
+        run_against(&tree, &server, &with_header.concat()).unwrap();
+        let [plain, overridden] = server.requests().try_into().unwrap();
+        assert_eq!(plain.path, "/things/1");
+        assert_eq!(plain.header("x-ad-account-region"), Some("eu"));
+        let sent: Vec<&str> = overridden
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("x-ad-account-region"))
+            .map(|(_, value)| value.as_str())
+            .collect();
+        assert_eq!(sent, ["us"]);
     }
-    Ok(())
 }
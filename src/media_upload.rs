@@ -1,27 +1,109 @@
 use anyhow::{Context, Result, anyhow};
+use reqwest::StatusCode;
 use reqwest::blocking::{Client, multipart};
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
 use serde_json::Value;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
+use url::Url;
 
-use crate::client::{Auth, Body, PinterestClient};
+use crate::cancel;
+use crate::client::{Auth, Body, HttpVersion, PinterestClient};
+use crate::s3;
 use crate::sources::SourceFile;
 
-pub fn upload_media(
+/// Redirects (307/308) followed when posting the upload form.
+const MAX_UPLOAD_REDIRECTS: usize = 3;
+
+/// What to do with `upload_parameters` values that aren't strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonStringParams {
+    /// Leave them out of the form (logged).
+    #[default]
+    Skip,
+    /// Send their JSON text (`null` as an empty field).
+    Stringify,
+    /// Fail the upload.
+    Strict,
+}
+
+impl NonStringParams {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "skip" => Ok(Self::Skip),
+            "stringify" => Ok(Self::Stringify),
+            "strict" => Ok(Self::Strict),
+            other => Err(anyhow!("invalid --upload-params {other}")),
+        }
+    }
+}
+
+/// What [`upload_media`] sends as the form's file part.
+#[derive(Debug, Clone, Copy)]
+pub enum MediaSource<'a> {
+    File(&'a SourceFile),
+    /// An `s3://` object piped into the upload as it is read, so a large video
+    /// never needs a local temp copy. Re-read from S3 if the upload is redirected.
+    S3 {
+        url: &'a str,
+        opts: &'a s3::S3Options,
+    },
+}
+
+impl<'a> From<&'a SourceFile> for MediaSource<'a> {
+    fn from(file: &'a SourceFile) -> Self {
+        Self::File(file)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    /// Statuses that end polling successfully; empty means don't wait.
+    pub wait_until: Vec<String>,
+    pub non_string: NonStringParams,
+    /// Extra fields for the `POST /media` registration payload.
+    pub register_fields: serde_json::Map<String, Value>,
+}
+
+/// Registers an upload (`POST /media`) and returns the response with its
+/// `media_id`, `upload_url` and `upload_parameters`, without uploading anything.
+pub fn register_media(
     api: &PinterestClient,
     auth: &Auth,
     media_type: &str,
-    file: &SourceFile,
-    wait: bool,
+    opts: &UploadOptions,
 ) -> Result<Value> {
-    let register_url = api.build_url("/media");
-    let register = api.request(
+    let mut payload = opts.register_fields.clone();
+    match payload.get("media_type") {
+        None => {}
+        Some(Value::String(value)) if value == media_type => {}
+        Some(other) => {
+            return Err(anyhow!(
+                "register body media_type {other} conflicts with --media-type {media_type}"
+            ));
+        }
+    }
+    payload.insert("media_type".to_string(), Value::from(media_type));
+    let register_url = api.build_url("/media")?;
+    api.request(
         "POST",
         &register_url,
         auth,
         &[],
-        Some(Body::Json(serde_json::json!({ "media_type": media_type }))),
-    )?;
+        Some(Body::Json(Value::Object(payload))),
+    )
+}
+
+pub fn upload_media<'a>(
+    api: &PinterestClient,
+    auth: &Auth,
+    media_type: &str,
+    source: impl Into<MediaSource<'a>>,
+    opts: &UploadOptions,
+) -> Result<Value> {
+    let source = source.into();
+    let register = register_media(api, auth, media_type, opts)?;
 
     let media_id = register
         .get("media_id")
@@ -38,64 +120,238 @@ pub fn upload_media(
         .and_then(|v| v.as_object())
         .ok_or_else(|| anyhow!("missing upload_parameters"))?;
 
-    upload_to_s3(&upload_url, params, file)?;
+    upload_to_s3(
+        &upload_url,
+        params,
+        source,
+        api.http_version(),
+        opts.non_string,
+    )?;
 
-    if !wait {
+    if opts.wait_until.is_empty() {
         return Ok(register);
     }
 
-    wait_for_processing(api, auth, &media_id, Duration::from_secs(180))
+    let resp = wait_for_processing(
+        api,
+        auth,
+        &media_id,
+        &opts.wait_until,
+        Duration::from_secs(180),
+    )?;
+    match resp.get("status").and_then(|v| v.as_str()) {
+        Some("succeeded") => Ok(media_summary(&resp, &media_id, media_type)),
+        _ => Ok(resp),
+    }
+}
+
+/// What a create needs from processed media: its id, type and status, plus
+/// any URL fields of the response under `urls`, keyed by dotted path.
+pub fn media_summary(resp: &Value, media_id: &str, media_type: &str) -> Value {
+    let field = |key: &str, default: &str| {
+        resp.get(key)
+            .cloned()
+            .unwrap_or_else(|| Value::from(default))
+    };
+    let mut summary = serde_json::Map::new();
+    summary.insert("media_id".to_string(), field("media_id", media_id));
+    summary.insert("media_type".to_string(), field("media_type", media_type));
+    summary.insert("status".to_string(), field("status", "succeeded"));
+    let mut urls = serde_json::Map::new();
+    collect_urls("", resp, &mut urls);
+    if !urls.is_empty() {
+        summary.insert("urls".to_string(), Value::Object(urls));
+    }
+    Value::Object(summary)
+}
+
+fn collect_urls(path: &str, value: &Value, out: &mut serde_json::Map<String, Value>) {
+    let child = |key: &str| match path {
+        "" => key.to_string(),
+        _ => format!("{path}.{key}"),
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                match v {
+                    Value::String(_) if key.to_ascii_lowercase().ends_with("url") => {
+                        out.insert(child(key), v.clone());
+                    }
+                    _ => collect_urls(&child(key), v, out),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, v) in items.iter().enumerate() {
+                collect_urls(&child(&index.to_string()), v, out);
+            }
+        }
+        _ => {}
+    }
 }
 
 fn upload_to_s3(
     upload_url: &str,
     params: &serde_json::Map<String, Value>,
-    file: &SourceFile,
+    source: MediaSource,
+    http_version: HttpVersion,
+    non_string: NonStringParams,
 ) -> Result<()> {
-    let http = Client::builder()
-        .user_agent("pinterest-ads-cli/0.1.0")
+    // Redirects are replayed by hand: the multipart body is consumed by the
+    // first send, so reqwest can't resend it itself.
+    let http = http_version
+        .apply(Client::builder().user_agent("pinterest-ads-cli/0.1.0"))
+        .redirect(Policy::none())
         .build()
         .context("build upload client")?;
 
+    let mut url = Url::parse(upload_url).context("invalid upload_url")?;
+    for _ in 0..=MAX_UPLOAD_REDIRECTS {
+        let resp = http
+            .post(url.clone())
+            .multipart(upload_form(params, source, non_string)?)
+            .send()
+            .context("upload media")?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let location = resp.headers().get(LOCATION).and_then(|v| v.to_str().ok());
+        if let (StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT, Some(location)) =
+            (status, location)
+        {
+            // e.g. S3 pointing a presigned POST at the bucket's regional endpoint.
+            let next = url
+                .join(location)
+                .with_context(|| format!("invalid upload redirect {location}"))?;
+            log::debug!("upload {status}: re-posting to {next}");
+            url = next;
+            continue;
+        }
+        let text = resp.text().unwrap_or_default();
+        return Err(anyhow!("upload failed (http {}): {}", status, text));
+    }
+    Err(anyhow!(
+        "upload redirected more than {MAX_UPLOAD_REDIRECTS} times; last target {url}"
+    ))
+}
+
+/// The presigned POST form; rebuilt for every attempt since sending consumes it.
+fn upload_form(
+    params: &serde_json::Map<String, Value>,
+    source: MediaSource,
+    non_string: NonStringParams,
+) -> Result<multipart::Form> {
+    // Fields go out in the order the register response listed them
+    // (serde_json preserve_order), since some stores check policy order.
     let mut form = multipart::Form::new();
     for (k, v) in params {
-        let Some(s) = v.as_str() else { continue };
-        form = form.text(k.clone(), s.to_string());
+        let text = match (v, non_string) {
+            (Value::String(s), _) => s.clone(),
+            (_, NonStringParams::Skip) => {
+                log::warn!("skipping non-string upload parameter {k}={v}");
+                continue;
+            }
+            (Value::Null, NonStringParams::Stringify) => String::new(),
+            (_, NonStringParams::Stringify) => v.to_string(),
+            (_, NonStringParams::Strict) => {
+                return Err(anyhow!("upload parameter {k} is not a string: {v}"));
+            }
+        };
+        log::debug!("upload field {k}");
+        form = form.text(k.clone(), text);
     }
 
     // S3 form uploads conventionally use "file" as the part name.
-    let part = multipart::Part::file(&file.path)
-        .with_context(|| format!("open file {}", file.path.display()))?
-        .file_name(file.file_name.clone());
-    form = form.part("file", part);
-
-    let resp = http
-        .post(upload_url)
-        .multipart(form)
-        .send()
-        .context("upload media")?;
-    let status = resp.status();
-    if status.is_success() {
-        return Ok(());
-    }
-    let text = resp.text().unwrap_or_default();
-    Err(anyhow!("upload failed (http {}): {}", status, text))
+    let part = match source {
+        MediaSource::File(file) => multipart::Part::file(&file.path)
+            .with_context(|| format!("open file {}", file.path.display()))?
+            .file_name(file.file_name.clone()),
+        MediaSource::S3 { url, opts } => {
+            let (bucket, key) = s3::parse_s3_url(url)?;
+            let reader = s3::open_object_blocking(&bucket, &key, opts)?;
+            log::debug!(
+                "streaming {url} ({} bytes) into the upload",
+                reader.content_length()
+            );
+            let file_name = std::path::Path::new(&key)
+                .file_name()
+                .and_then(|v| v.to_str())
+                .unwrap_or("s3-object")
+                .to_string();
+            let len = reader.content_length();
+            multipart::Part::reader_with_length(reader, len).file_name(file_name)
+        }
+    };
+    Ok(form.part("file", part))
+}
+
+/// Checks that every `media_id` referenced in a create body (e.g. a pin's
+/// `media_source`) has finished processing, so the create doesn't fail on it.
+pub fn verify_media_ids(api: &PinterestClient, auth: &Auth, body: &Value) -> Result<()> {
+    let mut ids = Vec::new();
+    collect_media_ids(body, &mut ids);
+    if ids.is_empty() {
+        log::warn!("--verify-media: no media_id found in the request body");
+    }
+    for media_id in ids {
+        let url = api.build_url(&format!("/media/{}", urlencoding::encode(media_id)))?;
+        let resp = api
+            .request("GET", &url, auth, &[], None)
+            .map_err(|err| anyhow!("check media {media_id}: {err}"))?;
+        match resp.get("status").and_then(|v| v.as_str()) {
+            Some("succeeded") => log::debug!("media {media_id} succeeded"),
+            Some(status) => {
+                return Err(anyhow!(
+                    "media {media_id} is {status}, not succeeded; wait for it (media upload --wait) before creating"
+                ));
+            }
+            None => return Err(anyhow!("media {media_id}: response has no status")),
+        }
+    }
+    Ok(())
+}
+
+fn collect_media_ids<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                match (key.as_str(), v) {
+                    ("media_id", Value::String(id)) if !out.contains(&id.as_str()) => out.push(id),
+                    _ => collect_media_ids(v, out),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_media_ids(v, out)),
+        _ => {}
+    }
 }
 
+/// Polls until the media reaches any status in `until`. `succeeded` always ends
+/// the wait (it's past every intermediate state); other unlisted states
+/// besides `registered`/`processing` are errors.
 fn wait_for_processing(
     api: &PinterestClient,
     auth: &Auth,
     media_id: &str,
+    until: &[String],
     timeout: Duration,
 ) -> Result<Value> {
     let start = Instant::now();
+    // Poll every 2s at first, backing off to the client's max delay (at most 10s).
+    let mut poll = api.retry_policy().derive(u32::MAX, Duration::from_secs(2));
+    poll.max_delay = poll.max_delay.min(Duration::from_secs(10));
+    let mut polls = 0;
     loop {
-        let url = api.build_url(&format!("/media/{}", media_id));
+        let url = api.build_url(&format!("/media/{}", media_id))?;
         let resp = api.request("GET", &url, auth, &[], None)?;
         let status = resp
             .get("status")
             .and_then(|v| v.as_str())
             .unwrap_or("unknown");
+        if until.iter().any(|s| s == status) {
+            return Ok(resp);
+        }
         match status {
             "succeeded" => return Ok(resp),
             "failed" => return Err(anyhow!("media status: failed")),
@@ -106,6 +362,84 @@ fn wait_for_processing(
         if start.elapsed() >= timeout {
             return Err(anyhow!("media processing timeout"));
         }
-        sleep(Duration::from_secs(2));
+        if cancel::is_cancelled() {
+            return Err(anyhow!("interrupted while waiting for media {media_id}"));
+        }
+        polls += 1;
+        let remaining = timeout.saturating_sub(start.elapsed());
+        sleep(poll.delay(polls, None).min(remaining));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{SourceOptions, resolve_source};
+    use crate::test_server::{Response, TestServer};
+    use serde_json::json;
+
+    fn params() -> serde_json::Map<String, Value> {
+        let Value::Object(map) = json!({"key": "uploads/v.mp4", "policy": "p0l1cy"}) else {
+            unreachable!()
+        };
+        map
+    }
+
+    #[test]
+    fn temporary_redirect_re_posts_the_whole_form() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("v.mp4");
+        std::fs::write(&path, b"not really a video").unwrap();
+        let file = resolve_source(path.to_str().unwrap(), &SourceOptions::default()).unwrap();
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/bucket" => Response::new(307, "").header("Location", "/regional/bucket"),
+            _ => Response::new(204, ""),
+        });
+
+        let upload_url = format!("{}/bucket", server.url());
+        upload_to_s3(
+            &upload_url,
+            &params(),
+            MediaSource::File(&file),
+            HttpVersion::Auto,
+            NonStringParams::Skip,
+        )
+        .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let (first, second) = (&requests[0], &requests[1]);
+        assert_eq!(second.method, "POST");
+        assert_eq!(second.path, "/regional/bucket");
+        let body = String::from_utf8_lossy(&second.body);
+        assert!(body.contains("name=\"key\"\r\n\r\nuploads/v.mp4"), "{body}");
+        assert!(body.contains("name=\"policy\"\r\n\r\np0l1cy"), "{body}");
+        assert!(body.contains("filename=\"v.mp4\""), "{body}");
+        assert!(body.contains("not really a video"), "{body}");
+        // Same form both times, apart from the multipart boundary.
+        assert_eq!(first.body.len(), second.body.len());
+    }
+
+    #[test]
+    fn endless_redirects_give_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("v.mp4");
+        std::fs::write(&path, b"video").unwrap();
+        let file = resolve_source(path.to_str().unwrap(), &SourceOptions::default()).unwrap();
+        let server = TestServer::start(|_| Response::new(307, "").header("Location", "/again"));
+
+        let err = upload_to_s3(
+            &format!("{}/bucket", server.url()),
+            &params(),
+            MediaSource::File(&file),
+            HttpVersion::Auto,
+            NonStringParams::Skip,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("redirected more than 3 times"),
+            "{err}"
+        );
+        assert_eq!(server.requests().len(), MAX_UPLOAD_REDIRECTS + 1);
     }
 }
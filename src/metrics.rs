@@ -0,0 +1,186 @@
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the request latency histogram's buckets; a
+/// last, unbounded bucket counts anything slower.
+pub const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Request counters for a run, shared by all clones of a client.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counts: Mutex<Snapshot>,
+}
+
+/// Totals recorded so far; every HTTP attempt counts, retries included.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Snapshot {
+    pub requests: u64,
+    /// Responses by HTTP status code.
+    pub statuses: BTreeMap<u16, u64>,
+    /// Attempts that got no response (connect errors, timeouts).
+    pub transport_errors: u64,
+    pub retries: u64,
+    /// Time slept before retries (`Retry-After` and backoff).
+    pub retry_wait_seconds: f64,
+    /// Time spent waiting on HTTP, summed over attempts.
+    pub request_seconds: f64,
+    /// Attempts per [`LATENCY_BUCKETS`] bucket (not cumulative), plus one for
+    /// slower ones; empty until something is recorded.
+    #[serde(skip)]
+    pub latency: Vec<u64>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, outcome: Result<StatusCode, &anyhow::Error>, duration: Duration) {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts.requests += 1;
+        counts.request_seconds += duration.as_secs_f64();
+        counts.latency.resize(LATENCY_BUCKETS.len() + 1, 0);
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|le| duration.as_secs_f64() <= *le)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        counts.latency[bucket] += 1;
+        match outcome {
+            Ok(status) => *counts.statuses.entry(status.as_u16()).or_default() += 1,
+            Err(_) => counts.transport_errors += 1,
+        }
+    }
+
+    /// One retry, about to be sent after sleeping `wait`.
+    pub fn record_retry(&self, wait: Duration) {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts.retries += 1;
+        counts.retry_wait_seconds += wait.as_secs_f64();
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.counts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl Snapshot {
+    /// Responses with a 4xx/5xx status.
+    pub fn error_responses(&self) -> u64 {
+        self.statuses
+            .iter()
+            .filter(|(status, _)| **status >= 400)
+            .map(|(_, n)| n)
+            .sum()
+    }
+
+    /// `(upper bound, count)` per latency bucket, `None` for the unbounded one.
+    pub fn latency_buckets(&self) -> impl Iterator<Item = (Option<f64>, u64)> + '_ {
+        self.latency
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (LATENCY_BUCKETS.get(i).copied(), *n))
+    }
+
+    /// Prometheus text exposition (node_exporter textfile collector format),
+    /// with the run's wall time and outcome alongside the request counters.
+    pub fn prometheus(&self, run: Duration, success: bool) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, String)]| {
+            let _ = writeln!(out, "# HELP pinterest_ads_{name} {help}");
+            let _ = writeln!(out, "# TYPE pinterest_ads_{name} {kind}");
+            for (labels, value) in samples {
+                let _ = writeln!(out, "pinterest_ads_{name}{labels} {value}");
+            }
+        };
+        let plain = |value: String| vec![(String::new(), value)];
+        metric(
+            "requests_total",
+            "counter",
+            "HTTP requests sent, retries included.",
+            &plain(self.requests.to_string()),
+        );
+        let statuses: Vec<_> = self
+            .statuses
+            .iter()
+            .map(|(status, n)| (format!("{{status=\"{status}\"}}"), n.to_string()))
+            .collect();
+        metric(
+            "responses_total",
+            "counter",
+            "HTTP responses by status code.",
+            &statuses,
+        );
+        metric(
+            "transport_errors_total",
+            "counter",
+            "HTTP requests that got no response.",
+            &plain(self.transport_errors.to_string()),
+        );
+        metric(
+            "retries_total",
+            "counter",
+            "Requests retried (429, 5xx, 404 and --retry-if).",
+            &plain(self.retries.to_string()),
+        );
+        metric(
+            "retry_wait_seconds_total",
+            "counter",
+            "Time slept before retries (Retry-After and backoff).",
+            &plain(format!("{:.3}", self.retry_wait_seconds)),
+        );
+        metric(
+            "request_duration_seconds_total",
+            "counter",
+            "Time spent on HTTP requests.",
+            &plain(format!("{:.3}", self.request_seconds)),
+        );
+        // Prometheus buckets are cumulative and end with `+Inf`.
+        let mut seen = 0;
+        let mut latency: Vec<_> = LATENCY_BUCKETS
+            .iter()
+            .enumerate()
+            .map(|(i, le)| {
+                seen += self.latency.get(i).copied().unwrap_or(0);
+                (format!("_bucket{{le=\"{le}\"}}"), seen.to_string())
+            })
+            .collect();
+        latency.push((
+            "_bucket{le=\"+Inf\"}".to_string(),
+            self.requests.to_string(),
+        ));
+        latency.push(("_sum".to_string(), format!("{:.3}", self.request_seconds)));
+        latency.push(("_count".to_string(), self.requests.to_string()));
+        metric(
+            "request_duration_seconds",
+            "histogram",
+            "Latency of each HTTP request attempt.",
+            &latency,
+        );
+        metric(
+            "run_duration_seconds",
+            "gauge",
+            "Wall time of the last run.",
+            &plain(format!("{:.3}", run.as_secs_f64())),
+        );
+        metric(
+            "run_success",
+            "gauge",
+            "1 if the last run succeeded, else 0.",
+            &plain(u8::from(success).to_string()),
+        );
+        metric(
+            "last_run_timestamp_seconds",
+            "gauge",
+            "Unix time the last run finished.",
+            &plain(chrono::Utc::now().timestamp().to_string()),
+        );
+        out
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{Result, anyhow};
+use reqwest::StatusCode;
+use reqwest::header::{CONTENT_LOCATION, HeaderName, LOCATION};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::cancel;
+use crate::client::{ApiResponse, Auth, PinterestClient};
+use crate::retry;
+
+/// How long [`wait_for_completion`] polls before giving up.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Body fields some APIs use for the status URL instead of a header.
+const BODY_FIELDS: &[&str] = &["monitor_url", "status_url", "location"];
+
+/// Body `status` values that mean the operation is still running.
+const PENDING: &[&str] = &["pending", "queued", "running", "processing", "in_progress"];
+
+/// Where to poll an accepted (202) operation: the `Location`,
+/// `Operation-Location` or `Content-Location` header, else a URL field in the
+/// body. `None` for any other status.
+pub fn monitor_url(resp: &ApiResponse) -> Option<String> {
+    if resp.status != StatusCode::ACCEPTED {
+        return None;
+    }
+    let operation_location = HeaderName::from_static("operation-location");
+    [LOCATION, operation_location, CONTENT_LOCATION]
+        .iter()
+        .find_map(|name| resp.headers.get(name)?.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| {
+            BODY_FIELDS
+                .iter()
+                .find_map(|field| resp.body.get(*field)?.as_str())
+                .map(str::to_string)
+        })
+}
+
+/// A monitor URL made absolute the way a browser would: resolved against
+/// `request_url`, the request that was answered with 202.
+pub fn resolve(request_url: &str, monitor_url: &str) -> Result<String> {
+    Url::parse(request_url)
+        .and_then(|base| base.join(monitor_url))
+        .map(String::from)
+        .map_err(|err| anyhow!("invalid monitor URL {monitor_url}: {err}"))
+}
+
+/// Polls absolute `url` (see [`resolve`]) until it stops answering 202 and its
+/// body `status` (if any) is no longer pending, honoring `Retry-After`. A
+/// `failed`/`error` status is an error; so is still being pending after
+/// `timeout`. `auth` is only sent if `url` is on the API's origin.
+pub fn wait_for_completion(
+    api: &PinterestClient,
+    auth: &Auth,
+    url: &str,
+    timeout: Duration,
+) -> Result<ApiResponse> {
+    let start = Instant::now();
+    // Same cadence as media processing: 2s at first, backing off to at most 10s.
+    let mut poll = api.retry_policy().derive(u32::MAX, Duration::from_secs(2));
+    poll.max_delay = poll.max_delay.min(Duration::from_secs(10));
+    Url::parse(url).map_err(|err| anyhow!("invalid monitor URL {url}: {err}"))?;
+    let api = if api.same_origin(url) {
+        api.clone()
+    } else {
+        log::debug!("{url} is off the API origin; polling it without credentials");
+        api.without_auth()
+    };
+    let mut polls = 0;
+    loop {
+        let resp = api.request_response("GET", url, auth, &[], None)?;
+        let status = resp
+            .body
+            .get("status")
+            .and_then(|v| v.as_str())
+            .map(str::to_ascii_lowercase);
+        let pending = resp.status == StatusCode::ACCEPTED
+            || status.as_deref().is_some_and(|s| PENDING.contains(&s));
+        if !pending {
+            if let Some(status @ ("failed" | "error")) = status.as_deref() {
+                return Err(anyhow!("async operation {status}: {}", resp.body));
+            }
+            return Ok(resp);
+        }
+
+        if start.elapsed() >= timeout {
+            return Err(anyhow!(
+                "async operation still pending after {}s: {url}",
+                timeout.as_secs()
+            ));
+        }
+        if cancel::is_cancelled() {
+            return Err(anyhow!("interrupted while waiting for {url}"));
+        }
+        polls += 1;
+        let remaining = timeout.saturating_sub(start.elapsed());
+        let delay = poll.delay(polls, retry::retry_after(&resp.headers));
+        log::debug!("{url} still pending; polling again in {delay:?}");
+        sleep(delay.min(remaining));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+    use serde_json::json;
+
+    #[test]
+    fn resolve_joins_relative_urls_and_keeps_absolute_ones() {
+        let request = "https://api.example.com/v5/ad_accounts/1/reports";
+        assert_eq!(
+            resolve(request, "reports/9").unwrap(),
+            "https://api.example.com/v5/ad_accounts/1/reports/9"
+        );
+        assert_eq!(
+            resolve(request, "/v5/operations/9").unwrap(),
+            "https://api.example.com/v5/operations/9"
+        );
+        assert_eq!(
+            resolve(request, "https://status.example.net/op/9?sig=x").unwrap(),
+            "https://status.example.net/op/9?sig=x"
+        );
+        assert!(resolve(request, "http://[bad").is_err());
+    }
+
+    fn done() -> TestServer {
+        TestServer::start(|_| Response::json(200, &json!({"status": "succeeded"})))
+    }
+
+    #[test]
+    fn polls_a_relative_monitor_url_with_credentials() {
+        let server = done();
+        let api = PinterestClient::new(format!("{}/v5", server.url())).unwrap();
+        let request = api.build_url("/ad_accounts/1/reports").unwrap();
+        let url = resolve(&request, "/v5/operations/9").unwrap();
+        let resp =
+            wait_for_completion(&api, &Auth::Bearer("t".into()), &url, DEFAULT_TIMEOUT).unwrap();
+        assert_eq!(resp.body["status"], "succeeded");
+        let [poll] = server.requests().try_into().unwrap();
+        assert_eq!(poll.path, "/v5/operations/9");
+        assert_eq!(poll.header("authorization"), Some("Bearer t"));
+    }
+
+    #[test]
+    fn polls_another_origin_without_credentials() {
+        let (api_server, status_server) = (done(), done());
+        let api = PinterestClient::builder(format!("{}/v5", api_server.url()))
+            .headers(
+                crate::client::parse_headers(&["Authorization: Bearer h".to_string()]).unwrap(),
+            )
+            .build()
+            .unwrap();
+        let url = resolve(
+            &api.build_url("/ad_accounts/1/reports").unwrap(),
+            &format!("{}/op/9", status_server.url()),
+        )
+        .unwrap();
+        wait_for_completion(&api, &Auth::Bearer("t".into()), &url, DEFAULT_TIMEOUT).unwrap();
+        assert!(api_server.requests().is_empty());
+        let [poll] = status_server.requests().try_into().unwrap();
+        assert_eq!(poll.path, "/op/9");
+        assert_eq!(poll.header("authorization"), None);
+    }
+}
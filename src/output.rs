@@ -0,0 +1,693 @@
+use anyhow::{Context, Result, anyhow};
+use handlebars::Handlebars;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::io::Write;
+use std::sync::Arc;
+
+use pinterest_ads::pagination::ItemPosition;
+use pinterest_ads::query::ClientFilter;
+
+/// How a command's result value is rendered to stdout.
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    pub pretty: bool,
+    /// Indent unit for pretty output (`--indent`, `--tab`); two spaces when unset.
+    pub indent: Option<String>,
+    /// Handlebars template text (already read from its source).
+    pub template: Option<String>,
+    /// Dotted paths (from `--redact`) whose values are masked before printing.
+    pub redact: Vec<String>,
+    /// Drop object keys whose value is null, at any depth.
+    pub strip_nulls: bool,
+    /// `--scale` factors: numbers (or numeric strings) at each dotted path
+    /// are multiplied by the factor, e.g. `1e-6` for micro-currency.
+    pub scale: Vec<(String, f64)>,
+    /// Path pattern like `out/{id}.json`; each array item goes to its own file.
+    pub output_template: Option<String>,
+    /// Residual `--filter` predicates; array items must match all of them.
+    pub filters: Vec<ClientFilter>,
+    /// `--sort-output` keys, most significant first: a dotted path and whether
+    /// it sorts descending.
+    pub sort: Vec<(String, bool)>,
+    /// Pretty-print, but keep arrays of scalars on one line.
+    pub compact_arrays: bool,
+    /// Dotted path of a single scalar to print bare, without JSON quoting.
+    pub raw_field: Option<String>,
+    /// Set under `--envelope`: the result is wrapped with the request that produced it.
+    pub envelope: Option<Envelope>,
+    /// Print as CSV instead of JSON.
+    pub csv: bool,
+    /// `--csv` columns from the response schema, in order; observed keys are
+    /// used when empty.
+    pub columns: Vec<String>,
+    /// `--output`: where results go instead of stdout.
+    pub sink: Option<Arc<Sink>>,
+}
+
+/// A destination for results other than stdout.
+#[derive(Debug)]
+pub enum Sink {
+    /// Replaced atomically with each result, so it always holds the latest.
+    File(String),
+    /// Each result is POSTed as the request body; a non-2xx reply is an error.
+    Webhook {
+        client: reqwest::blocking::Client,
+        url: String,
+        headers: reqwest::header::HeaderMap,
+    },
+}
+
+impl Sink {
+    /// An http(s) URL is a webhook; anything else is a file path.
+    pub fn parse(
+        target: &str,
+        client: reqwest::blocking::Client,
+        headers: reqwest::header::HeaderMap,
+    ) -> Self {
+        if target.starts_with("http://") || target.starts_with("https://") {
+            Self::Webhook {
+                client,
+                url: target.to_string(),
+                headers,
+            }
+        } else {
+            Self::File(target.to_string())
+        }
+    }
+
+    pub fn write(&self, text: &str, content_type: &str) -> Result<()> {
+        match self {
+            Self::File(path) => write_file_atomic(path, text.as_bytes()),
+            Self::Webhook {
+                client,
+                url,
+                headers,
+            } => {
+                let resp = client
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, content_type)
+                    .headers(headers.clone())
+                    .body(text.to_string())
+                    .send()
+                    .map_err(|err| anyhow!("post result to {url}: {err}"))?;
+                let status = resp.status();
+                if !status.is_success() {
+                    let body = resp.text().unwrap_or_default();
+                    let body = body.trim();
+                    return Err(anyhow!(
+                        "post result to {url}: http {status}{}{body}",
+                        if body.is_empty() { "" } else { ": " }
+                    ));
+                }
+                log::debug!("posted result to {url} ({status})");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The request behind a result, for `--envelope` output.
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    pub method: String,
+    /// Effective URL, query string included.
+    pub url: String,
+    pub status: u16,
+    /// Under `--show-bookmark`, the next page's bookmark (`null` on the last page).
+    pub bookmark: Option<Value>,
+}
+
+impl OutputOptions {
+    pub fn emit(&self, value: &Value) -> Result<()> {
+        let filtered;
+        let value = match value {
+            Value::Array(items) if !self.filters.is_empty() || !self.sort.is_empty() => {
+                let mut items: Vec<Value> = items
+                    .iter()
+                    .filter(|item| self.filters.iter().all(|f| f.matches(item)))
+                    .cloned()
+                    .collect();
+                sort_items(&mut items, &self.sort);
+                filtered = Value::Array(items);
+                &filtered
+            }
+            _ => {
+                if !self.sort.is_empty() {
+                    log::warn!("--sort-output only sorts arrays; the output isn't one");
+                }
+                value
+            }
+        };
+        let value = &*self.cleaned(value);
+        let wrapped;
+        let value = match &self.envelope {
+            Some(envelope) => {
+                let mut full = serde_json::json!({
+                    "request": { "method": envelope.method, "url": envelope.url },
+                    "status": envelope.status,
+                    "response": value,
+                });
+                if let Some(bookmark) = &envelope.bookmark {
+                    full["bookmark"] = bookmark.clone();
+                }
+                wrapped = full;
+                &wrapped
+            }
+            None => value,
+        };
+        if let Some(path) = &self.raw_field {
+            return self.write(&(raw_field(value, path)? + "\n"), "text/plain");
+        }
+        if self.csv {
+            return self.write(&to_csv(value, &self.columns)?, "text/csv");
+        }
+        if let Some(pattern) = &self.output_template {
+            return self.write_per_item(pattern, value);
+        }
+        if let Some(template) = &self.template {
+            return self.write(&render_template(template, value)?, "text/plain");
+        }
+        self.write(&(self.to_json_text(value)? + "\n"), "application/json")
+    }
+
+    fn write(&self, text: &str, content_type: &str) -> Result<()> {
+        match &self.sink {
+            Some(sink) => sink.write(text, content_type),
+            None => write_stdout(text),
+        }
+    }
+
+    /// Writes one item of a `--stream` run as a compact JSON line (or its
+    /// `--raw-field`) and flushes right away, so a pipeline sees it while later
+    /// pages are still being fetched. Items that fail `--filter` are skipped.
+    /// With a position (`--stream-position`) the item is prefixed with `_page`
+    /// and `_index`, and a non-object item is wrapped as `_value`.
+    pub fn emit_stream_item(&self, item: &Value, position: Option<ItemPosition>) -> Result<()> {
+        if !self.filters.iter().all(|f| f.matches(item)) {
+            return Ok(());
+        }
+        let item = &*self.cleaned(item);
+        let positioned;
+        let item = match position {
+            Some(position) => {
+                positioned = with_position(item, position);
+                &positioned
+            }
+            None => item,
+        };
+        let line = match &self.raw_field {
+            Some(path) => raw_field(item, path)?,
+            None => serde_json::to_string(item)?,
+        };
+        write_stdout_line(&line)?;
+        flush_stdout()
+    }
+
+    /// `value` with `--strip-nulls`, `--scale` and `--redact` applied.
+    fn cleaned<'a>(&self, value: &'a Value) -> Cow<'a, Value> {
+        if self.redact.is_empty() && !self.strip_nulls && self.scale.is_empty() {
+            return Cow::Borrowed(value);
+        }
+        let mut copy = value.clone();
+        if self.strip_nulls {
+            strip_nulls(&mut copy);
+        }
+        for (path, factor) in &self.scale {
+            let segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
+            at_path(&mut copy, &segments, &mut |v| scale(v, *factor));
+        }
+        for path in &self.redact {
+            let segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
+            redact(&mut copy, &segments);
+        }
+        Cow::Owned(copy)
+    }
+
+    fn to_json_text(&self, value: &Value) -> Result<String> {
+        let indent = self.indent.as_deref().unwrap_or("  ");
+        if self.compact_arrays {
+            let mut out = String::new();
+            write_compact_arrays(&mut out, value, indent, 0)?;
+            return Ok(out);
+        }
+        if self.pretty {
+            return to_string_indented(value, indent);
+        }
+        Ok(serde_json::to_string(value)?)
+    }
+
+    /// Writes each item (or the single object) to its own file and prints the
+    /// paths written, one per line.
+    fn write_per_item(&self, pattern: &str, value: &Value) -> Result<()> {
+        let items = match value {
+            Value::Array(items) => items.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        for item in items {
+            let path = interpolate_path(pattern, item)?;
+            if let Some(dir) = std::path::Path::new(&path).parent()
+                && !dir.as_os_str().is_empty()
+            {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("create {}", dir.display()))?;
+            }
+            let text = self.to_json_text(item)?;
+            std::fs::write(&path, text + "\n").with_context(|| format!("write {path}"))?;
+            write_stdout_line(&path)?;
+        }
+        Ok(())
+    }
+}
+
+/// One row per object. Columns are `columns` (the schema's order, so exports
+/// diff cleanly even when rows omit optional fields) followed by any other
+/// keys seen, sorted; without a schema, keys in the order first seen. Nested
+/// values are written as JSON, nulls as empty cells.
+fn to_csv(value: &Value, columns: &[String]) -> Result<String> {
+    let rows = match value {
+        Value::Array(items) => items.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let mut objects = Vec::with_capacity(rows.len());
+    for row in rows {
+        objects.push(
+            row.as_object()
+                .ok_or_else(|| anyhow!("--csv needs an object or an array of objects"))?,
+        );
+    }
+
+    let mut header: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let mut extra: Vec<&str> = Vec::new();
+    for key in objects.iter().flat_map(|o| o.keys()) {
+        if !header.contains(&key.as_str()) && !extra.contains(&key.as_str()) {
+            extra.push(key);
+        }
+    }
+    if !columns.is_empty() {
+        if !extra.is_empty() {
+            log::debug!(
+                "--csv: fields not in the response schema: {}",
+                extra.join(", ")
+            );
+        }
+        extra.sort_unstable();
+    }
+    header.extend(extra);
+
+    let mut out = String::new();
+    let line = |cells: Vec<String>| cells.join(",") + "\n";
+    out.push_str(&line(header.iter().map(|h| csv_cell(h)).collect()));
+    for object in objects {
+        let cells = header
+            .iter()
+            .map(|key| match object.get(*key) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => csv_cell(s),
+                Some(other) => csv_cell(&other.to_string()),
+            })
+            .collect();
+        out.push_str(&line(cells));
+    }
+    Ok(out)
+}
+
+/// Quotes a cell (RFC 4180) when it holds a comma, quote or line break.
+fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn write_json(value: &Value, pretty: bool) -> Result<()> {
+    if pretty {
+        write_stdout_line(&serde_json::to_string_pretty(value)?)
+    } else {
+        write_stdout_line(&serde_json::to_string(value)?)
+    }
+}
+
+/// Pretty JSON indented by `indent` per level (`to_string_pretty` uses two
+/// spaces).
+fn to_string_indented(value: &Value, indent: &str) -> Result<String> {
+    let mut out = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut ser = serde_json::Serializer::with_formatter(&mut out, formatter);
+    serde::Serialize::serialize(value, &mut ser)?;
+    Ok(String::from_utf8(out)?)
+}
+
+/// Pretty JSON (like [`to_string_indented`]) except that non-empty arrays
+/// holding only scalars stay on one line, e.g. daily metrics.
+fn write_compact_arrays(out: &mut String, value: &Value, unit: &str, depth: usize) -> Result<()> {
+    let indent = |n: usize| unit.repeat(n);
+    match value {
+        Value::Array(items) if items.is_empty() => out.push_str("[]"),
+        Value::Array(items) if items.iter().all(|v| !v.is_array() && !v.is_object()) => {
+            let parts: Vec<String> = items
+                .iter()
+                .map(serde_json::to_string)
+                .collect::<Result<_, _>>()?;
+            out.push('[');
+            out.push_str(&parts.join(", "));
+            out.push(']');
+        }
+        Value::Array(items) => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                out.push_str(&indent(depth + 1));
+                write_compact_arrays(out, item, unit, depth + 1)?;
+                out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&indent(depth));
+            out.push(']');
+        }
+        Value::Object(map) if map.is_empty() => out.push_str("{}"),
+        Value::Object(map) => {
+            out.push_str("{\n");
+            for (i, (key, item)) in map.iter().enumerate() {
+                out.push_str(&indent(depth + 1));
+                out.push_str(&serde_json::to_string(key)?);
+                out.push_str(": ");
+                write_compact_arrays(out, item, unit, depth + 1)?;
+                out.push_str(if i + 1 < map.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&indent(depth));
+            out.push('}');
+        }
+        scalar => out.push_str(&serde_json::to_string(scalar)?),
+    }
+    Ok(())
+}
+
+/// Fills `{field}` / `{a.b}` placeholders from `item`. Values are made safe as
+/// a single path component, so an id can't escape the target directory.
+pub fn interpolate_path(pattern: &str, item: &Value) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed '{{' in --output-template {pattern}"))?
+            + start;
+        let field = &rest[start + 1..end];
+        let value = lookup(item, field)
+            .ok_or_else(|| anyhow!("--output-template field {{{field}}} missing in item"))?;
+        let text = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let text: String = text
+            .chars()
+            .map(|c| if matches!(c, '/' | '\\') { '_' } else { c })
+            .collect();
+        if text.is_empty() || text == "." || text == ".." {
+            return Err(anyhow!(
+                "--output-template field {{{field}}} is not a usable file name"
+            ));
+        }
+        out.push_str(&text);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Sorts `items` by each key in turn (a stable sort, so ties keep their
+/// order). Numbers, and strings holding numbers, compare numerically and
+/// before other strings; items missing a key sort last in either direction.
+pub fn sort_items(items: &mut [Value], keys: &[(String, bool)]) {
+    if keys.is_empty() {
+        return;
+    }
+    items.sort_by(|a, b| {
+        keys.iter()
+            .map(|(path, descending)| {
+                let path = path.strip_prefix("$.").unwrap_or(path);
+                match (lookup(a, path), lookup(b, path)) {
+                    (Some(a), Some(b)) if *descending => compare_scalars(b, a),
+                    (Some(a), Some(b)) => compare_scalars(a, b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            })
+            .find(|order| order.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+fn compare_scalars(a: &Value, b: &Value) -> Ordering {
+    // Integers compare exactly: ids are past f64's 2^53.
+    let integer = |v: &Value| match v {
+        Value::Number(n) => n.to_string().parse::<i128>().ok(),
+        Value::String(s) => s.trim().parse::<i128>().ok(),
+        _ => None,
+    };
+    if let (Some(x), Some(y)) = (integer(a), integer(b)) {
+        return x.cmp(&y);
+    }
+    let number = |v: &Value| match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok().filter(|n| n.is_finite()),
+        _ => None,
+    };
+    match (number(a), number(b)) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => match (a, b) {
+            (Value::String(x), Value::String(y)) => x.cmp(y),
+            (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+            _ => a.to_string().cmp(&b.to_string()),
+        },
+    }
+}
+
+/// Follows a dotted path (numeric segments index arrays); `null` counts as missing.
+pub fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |v, key| match key.parse::<usize>() {
+            Ok(index) if v.is_array() => v.get(index),
+            _ => v.get(key),
+        })
+        .filter(|v| !v.is_null())
+}
+
+/// The scalar at `path` as plain text: strings unquoted, numbers and booleans as written.
+fn raw_field(value: &Value, path: &str) -> Result<String> {
+    let field = path.strip_prefix("$.").unwrap_or(path);
+    match lookup(value, field) {
+        None => Err(anyhow!("--raw-field {path} not found in response")),
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(Value::Object(_) | Value::Array(_)) => Err(anyhow!(
+            "--raw-field {path} is not a scalar; use a path to one of its fields"
+        )),
+        Some(scalar) => Ok(scalar.to_string()),
+    }
+}
+
+/// Removes null-valued keys from every object in `value`, recursively.
+/// Nulls inside arrays are kept so positions don't shift.
+pub fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, child| !child.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// Replaces the values at `path` with `"***"`. Arrays are walked implicitly, so
+/// `id` masks the id of every item in a list; `*` matches any object key.
+/// This is cosmetic scrubbing for sharing output, not a security boundary.
+pub fn redact(value: &mut Value, path: &[&str]) {
+    at_path(value, path, &mut |v| *v = Value::String("***".to_string()));
+}
+
+/// Multiplies a number, or a string holding one, by `factor`. The result is
+/// rounded to 12 significant digits so `1234560000 * 1e-6` prints as
+/// `1234.56`, and is an integer when whole. Other values, including `"NaN"`,
+/// `"inf"` and results that overflow, are left alone.
+pub fn scale(value: &mut Value, factor: f64) {
+    let number = match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    };
+    let Some(number) = number.filter(|n| n.is_finite()) else {
+        return;
+    };
+    let scaled: f64 = format!("{:.11e}", number * factor)
+        .parse()
+        .unwrap_or(number * factor);
+    let Some(scaled) = serde_json::Number::from_f64(scaled) else {
+        return;
+    };
+    *value = match scaled.as_f64() {
+        Some(n) if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 => Value::from(n as i64),
+        _ => Value::Number(scaled),
+    };
+}
+
+/// Calls `f` on each value at the dotted `path`, walking arrays implicitly;
+/// `*` matches any object key.
+fn at_path(value: &mut Value, path: &[&str], f: &mut dyn FnMut(&mut Value)) {
+    if let Value::Array(items) = value {
+        for item in items {
+            at_path(item, path, f);
+        }
+        return;
+    }
+    let Some((head, rest)) = path.split_first() else {
+        return;
+    };
+    let Value::Object(map) = value else {
+        return;
+    };
+    for (key, child) in map.iter_mut() {
+        if *head != "*" && key != head {
+            continue;
+        }
+        if rest.is_empty() {
+            f(child);
+        } else {
+            at_path(child, rest, f);
+        }
+    }
+}
+
+/// Renders a Handlebars template over the parsed response. This is a client-side
+/// transform: the output is whatever text the template produces, not JSON.
+pub fn render_template(template: &str, value: &Value) -> Result<String> {
+    let mut hb = Handlebars::new();
+    hb.register_escape_fn(handlebars::no_escape);
+    hb.render_template(template, value)
+        .context("render --template")
+}
+
+/// Writes via a sibling temp file and rename, so an interrupted run never
+/// leaves a half-written file behind.
+pub fn write_file_atomic(path: &str, data: &[u8]) -> Result<()> {
+    let dir = match std::path::Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    let write = || -> Result<()> {
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        file.write_all(data)?;
+        file.persist(path)?;
+        Ok(())
+    };
+    write().map_err(|err| anyhow!("write {path}: {err}"))
+}
+
+pub fn write_stdout(value: &str) -> Result<()> {
+    exit_on_broken_pipe(std::io::stdout().lock().write_all(value.as_bytes()))
+}
+
+pub fn flush_stdout() -> Result<()> {
+    exit_on_broken_pipe(std::io::stdout().lock().flush())
+}
+
+/// A closed reader (`| head`) ends the run quietly instead of erroring.
+fn exit_on_broken_pipe(result: std::io::Result<()>) -> Result<()> {
+    if let Err(err) = result {
+        if err.kind() == std::io::ErrorKind::BrokenPipe {
+            std::process::exit(0);
+        }
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+pub fn write_stdout_line(value: &str) -> Result<()> {
+    write_stdout(value)?;
+    write_stdout("\n")
+}
+
+/// The item with `_page` and `_index` keys first; an item that already has
+/// one of them keeps its own value, with a warning.
+fn with_position(item: &Value, position: ItemPosition) -> Value {
+    let mut out = serde_json::Map::new();
+    out.insert("_page".to_string(), position.page.into());
+    out.insert("_index".to_string(), position.index.into());
+    match item {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                if out.insert(key.clone(), value.clone()).is_some() {
+                    static WARNED: std::sync::Once = std::sync::Once::new();
+                    WARNED.call_once(|| {
+                        log::warn!("item field {key} overrides the --stream-position key")
+                    });
+                }
+            }
+        }
+        other => {
+            out.insert("_value".to_string(), other.clone());
+        }
+    }
+    Value::Object(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sort_compares_large_ids_exactly() {
+        // Both round to the same f64.
+        let mut items = vec![
+            json!({"id": "9007199254740993"}),
+            json!({"id": "9007199254740992"}),
+            json!({"id": 549755813888123457u64}),
+            json!({"id": 549755813888123456u64}),
+        ];
+        sort_items(&mut items, &[("id".to_string(), false)]);
+        let ids: Vec<String> = items.iter().map(|i| i["id"].to_string()).collect();
+        assert_eq!(
+            ids,
+            [
+                "\"9007199254740992\"",
+                "\"9007199254740993\"",
+                "549755813888123456",
+                "549755813888123457"
+            ]
+        );
+    }
+
+    #[test]
+    fn scale_leaves_non_finite_values_alone() {
+        for text in ["NaN", "inf", "-Infinity"] {
+            let mut value = json!(text);
+            scale(&mut value, 1e-6);
+            assert_eq!(value, json!(text));
+        }
+        let mut value = json!("1e308");
+        scale(&mut value, 1e10);
+        assert_eq!(value, json!("1e308"));
+        let mut value = json!("1234560000");
+        scale(&mut value, 1e-6);
+        assert_eq!(value, json!(1234.56));
+        let mut value = json!(5000000);
+        scale(&mut value, 1e-6);
+        assert_eq!(value, json!(5));
+    }
+
+    #[test]
+    fn sort_mixes_integers_and_decimals() {
+        let mut items = vec![json!({"v": "2.5"}), json!({"v": 2}), json!({"v": "10"})];
+        sort_items(&mut items, &[("v".to_string(), true)]);
+        assert_eq!(
+            items,
+            [json!({"v": "10"}), json!({"v": "2.5"}), json!({"v": 2})]
+        );
+    }
+}
@@ -1,17 +1,214 @@
 use anyhow::{Result, anyhow};
+use reqwest::header::{HeaderMap, LINK};
 use serde_json::Value;
 
+use crate::cancel;
 use crate::client::{Auth, PinterestClient};
 
+/// Caps for [`paginate_all`]; `0` means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PageLimits {
+    pub max_pages: u64,
+    pub max_items: u64,
+    /// When a page after the first fails, end the run there with a gap marker
+    /// instead of failing it (see [`failed_page`]). The pages after it can't be
+    /// fetched: only the failed page's body carries the cursor to them.
+    pub continue_on_error: bool,
+}
+
+/// A page that failed under [`PageLimits::continue_on_error`].
+struct Gap {
+    /// 0-based, like [`ItemPosition::page`].
+    page: u64,
+    error: String,
+}
+
+impl Gap {
+    /// The item recorded where the failed page's items would have been.
+    fn marker(&self) -> Value {
+        serde_json::json!({ "_gap": { "page": self.page, "error": self.error } })
+    }
+
+    fn error(&self) -> anyhow::Error {
+        anyhow!(
+            "page {} failed, so the output stops before it (--continue-on-error): {}",
+            self.page + 1,
+            self.error
+        )
+    }
+}
+
+/// The page that ended a [`paginate_all`] result early under
+/// `continue_on_error`, as an error to report once the items are written.
+/// Such a result ends with a `{"_gap": {"page", "error"}}` item.
+pub fn failed_page(result: &Value) -> Option<anyhow::Error> {
+    let gap = result.get("items")?.as_array()?.last()?.get("_gap")?;
+    Some(
+        Gap {
+            page: gap.get("page")?.as_u64()?,
+            error: gap.get("error")?.as_str()?.to_string(),
+        }
+        .error(),
+    )
+}
+
+/// Where an item sat in the paginated response, both 0-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemPosition {
+    pub page: u64,
+    /// Index within its page.
+    pub index: u64,
+}
+
+/// Fetches every page of a GET list endpoint and returns `{"items": [...]}`.
+///
+/// Follows the `bookmark` cursor, or a full next-page URL (`next` body field or
+/// `Link: rel="next"` header) when the server hands one out. Stops early, with
+/// the items fetched so far, once a limit is hit or the run is cancelled.
 pub fn paginate_all(
     client: &PinterestClient,
     method: &str,
     url: &str,
     auth: &Auth,
     query: &[(String, String)],
-    max_pages: u64,
-    max_items: u64,
+    limits: PageLimits,
 ) -> Result<Value> {
+    paginate_all_with(client, method, url, auth, query, limits, |_, _| Ok(()))
+}
+
+/// Like [`paginate_all`], but also hands each page's body to `on_page` as it
+/// arrives, with its 0-based number. Pages are passed whole, even when
+/// `max_items` stops partway through one.
+pub fn paginate_all_with(
+    client: &PinterestClient,
+    method: &str,
+    url: &str,
+    auth: &Auth,
+    query: &[(String, String)],
+    limits: PageLimits,
+    mut on_page: impl FnMut(&Value, u64) -> Result<()>,
+) -> Result<Value> {
+    let mut items: Vec<Value> = Vec::new();
+    let mut pages = 0u64;
+    let gap = walk_pages(client, method, url, auth, query, limits, |page| {
+        let page_items = page_items(page)?;
+        on_page(page, pages)?;
+        pages += 1;
+        for item in page_items {
+            items.push(item.clone());
+            if limits.max_items > 0 && items.len() as u64 >= limits.max_items {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    })?;
+    if let Some(gap) = gap {
+        items.push(gap.marker());
+    }
+    Ok(serde_json::json!({ "items": items }))
+}
+
+/// Like [`paginate_all`], but hands each item to `on_item` as its page
+/// arrives instead of collecting them, along with its position. Returns the
+/// number of items seen. A page that failed under `continue_on_error` is
+/// handed over as its gap marker, and then fails the call.
+pub fn for_each_item(
+    client: &PinterestClient,
+    method: &str,
+    url: &str,
+    auth: &Auth,
+    query: &[(String, String)],
+    limits: PageLimits,
+    mut on_item: impl FnMut(&Value, ItemPosition) -> Result<()>,
+) -> Result<u64> {
+    let mut seen = 0u64;
+    let mut pages = 0u64;
+    let gap = walk_pages(client, method, url, auth, query, limits, |page| {
+        let page_number = pages;
+        pages += 1;
+        for (index, item) in page_items(page)?.iter().enumerate() {
+            let position = ItemPosition {
+                page: page_number,
+                index: index as u64,
+            };
+            on_item(item, position)?;
+            seen += 1;
+            if limits.max_items > 0 && seen >= limits.max_items {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    })?;
+    if let Some(gap) = gap {
+        let position = ItemPosition {
+            page: gap.page,
+            index: 0,
+        };
+        on_item(&gap.marker(), position)?;
+        return Err(gap.error());
+    }
+    Ok(seen)
+}
+
+/// Counts the items of a GET list endpoint. A `total` (or `page_metadata.count`)
+/// on the first page is returned as-is; otherwise every page is fetched.
+pub fn count_all(
+    client: &PinterestClient,
+    method: &str,
+    url: &str,
+    auth: &Auth,
+    query: &[(String, String)],
+    limits: PageLimits,
+) -> Result<u64> {
+    let cap = |n: u64| match limits.max_items {
+        0 => n,
+        max => n.min(max),
+    };
+    let mut count = 0u64;
+    let mut first = true;
+    let gap = walk_pages(client, method, url, auth, query, limits, |page| {
+        if first && let Some(total) = reported_total(page) {
+            log::debug!("using the total the server reported ({total})");
+            count = total;
+            return Ok(true);
+        }
+        first = false;
+        count += page_items(page)?.len() as u64;
+        Ok(limits.max_items > 0 && count >= limits.max_items)
+    })?;
+    // A partial count is no count.
+    if let Some(gap) = gap {
+        return Err(gap.error());
+    }
+    Ok(cap(count))
+}
+
+fn reported_total(page: &Value) -> Option<u64> {
+    page.get("total")
+        .or_else(|| page.pointer("/page_metadata/count"))
+        .and_then(Value::as_u64)
+}
+
+fn page_items(page: &Value) -> Result<&Vec<Value>> {
+    page.get("items")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("expected paginated response with items[]"))
+}
+
+/// Calls `on_page` with each page's body until it returns `true`, the pages run
+/// out, `max_pages` (0 = unlimited) is reached or the run is cancelled. Under
+/// `continue_on_error`, a failed page after the first also ends the walk and
+/// is returned.
+/// Next-page URLs off the API's origin are fetched without credentials.
+fn walk_pages(
+    client: &PinterestClient,
+    method: &str,
+    url: &str,
+    auth: &Auth,
+    query: &[(String, String)],
+    limits: PageLimits,
+    mut on_page: impl FnMut(&Value) -> Result<bool>,
+) -> Result<Option<Gap>> {
     if method != "GET" {
         return Err(anyhow!("--all only supported for GET"));
     }
@@ -27,42 +224,231 @@ pub fn paginate_all(
     }
 
     let mut pages = 0u64;
-    let mut items: Vec<Value> = Vec::new();
+    // Set when the server hands out a full next-page URL (Link header or a
+    // `next` body field) instead of a bookmark; it already carries the query.
+    let mut next_url: Option<String> = None;
 
     loop {
+        if cancel::is_cancelled() {
+            log::warn!("interrupted after {pages} pages; returning what was fetched so far");
+            break;
+        }
         pages += 1;
-        if max_pages > 0 && pages > max_pages {
+        if limits.max_pages > 0 && pages > limits.max_pages {
             break;
         }
 
-        let mut q = base_query.clone();
-        if let Some(b) = &bookmark {
-            q.push(("bookmark".to_string(), b.clone()));
+        let (page_url, q) = match &next_url {
+            Some(next) => (next.as_str(), Vec::new()),
+            None => {
+                let mut q = base_query.clone();
+                if let Some(b) = &bookmark {
+                    q.push(("bookmark".to_string(), b.clone()));
+                }
+                (url, q)
+            }
+        };
+
+        // A next-page URL can point anywhere; the token only goes to the API.
+        let off_origin;
+        let page_client = if client.same_origin(page_url) {
+            client
+        } else {
+            log::debug!("{page_url} is off the API origin; fetching it without credentials");
+            off_origin = client.without_auth();
+            &off_origin
+        };
+        let (resp, headers) =
+            match page_client.request_with_headers("GET", page_url, auth, &q, None) {
+                Ok(page) => page,
+                Err(err) if limits.continue_on_error && pages > 1 => {
+                    log::error!(
+                        "page {pages} failed; keeping the {} pages before it: {err:#}",
+                        pages - 1
+                    );
+                    return Ok(Some(Gap {
+                        page: pages - 1,
+                        error: format!("{err:#}"),
+                    }));
+                }
+                Err(err) => return Err(err),
+            };
+        if on_page(&resp)? {
+            break;
         }
 
-        let resp = client.request("GET", url, auth, &q, None)?;
-        let data = resp
-            .get("items")
-            .and_then(|v| v.as_array())
-            .ok_or_else(|| anyhow!("expected paginated response with items[]"))?;
+        bookmark = next_bookmark(&resp).map(str::to_string);
 
-        for item in data {
-            items.push(item.clone());
-            if max_items > 0 && items.len() as u64 >= max_items {
-                return Ok(serde_json::json!({ "items": items }));
-            }
+        if bookmark.is_some() {
+            next_url = None;
+            continue;
         }
-
-        bookmark = resp
-            .get("bookmark")
+        let next = resp
+            .get("next")
             .and_then(|v| v.as_str())
+            .filter(|v| v.starts_with("http://") || v.starts_with("https://"))
             .map(|v| v.to_string())
-            .filter(|v| !v.is_empty());
-
-        if bookmark.is_none() {
+            .or_else(|| link_next(&headers, page_url));
+        if next.is_none() {
             break;
         }
+        next_url = next;
     }
+    Ok(None)
+}
 
-    Ok(serde_json::json!({ "items": items }))
+/// The cursor for the page after `page`; `None` on the last page.
+pub fn next_bookmark(page: &Value) -> Option<&str> {
+    page.get("bookmark")
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+}
+
+/// The `rel="next"` target of an RFC 8288 `Link` header, resolved against
+/// the URL of the page that carried it.
+pub fn link_next(headers: &HeaderMap, page_url: &str) -> Option<String> {
+    let target = headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|mut rest| {
+            // Each link is `<target>; params`. Targets are found by their
+            // brackets rather than by splitting on `,`, which a URL may hold.
+            loop {
+                let start = rest.find('<')?;
+                let end = start + rest[start..].find('>')?;
+                let params_end = rest[end..].find('<').map_or(rest.len(), |at| end + at);
+                let is_next = rest[end + 1..params_end].split([';', ',']).any(|param| {
+                    param.trim().strip_prefix("rel=").is_some_and(|rel| {
+                        rel.trim_matches('"')
+                            .split_whitespace()
+                            .any(|r| r.eq_ignore_ascii_case("next"))
+                    })
+                });
+                if is_next {
+                    return Some(&rest[start + 1..end]);
+                }
+                rest = &rest[params_end..];
+            }
+        })?;
+    let base = url::Url::parse(page_url).ok()?;
+    base.join(target).ok().map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+
+    fn links(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(LINK, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn link_next_picks_the_next_relation() {
+        let page = "https://api.example.com/v5/items?page=1";
+        let headers = links(&[
+            r#"<https://api.example.com/v5/items?page=0>; rel="prev", <https://api.example.com/v5/items?page=2>; rel="next""#,
+        ]);
+        assert_eq!(
+            link_next(&headers, page).as_deref(),
+            Some("https://api.example.com/v5/items?page=2")
+        );
+        let headers = links(&[
+            r#"</v5/items?page=0>; rel=prev"#,
+            r#"<?page=2>; rel="last next""#,
+        ]);
+        assert_eq!(
+            link_next(&headers, page).as_deref(),
+            Some("https://api.example.com/v5/items?page=2")
+        );
+        assert_eq!(link_next(&links(&[r#"<?page=0>; rel="prev""#]), page), None);
+        assert_eq!(link_next(&HeaderMap::new(), page), None);
+    }
+
+    #[test]
+    fn link_next_keeps_commas_inside_the_target() {
+        let headers = links(&[
+            r#"<items?fields=id,name&page=2>; rel="next"; title="a, b", <items?page=9>; rel="last""#,
+        ]);
+        assert_eq!(
+            link_next(&headers, "https://api.example.com/v5/items").as_deref(),
+            Some("https://api.example.com/v5/items?fields=id,name&page=2")
+        );
+    }
+
+    #[test]
+    fn paginate_all_follows_link_headers() {
+        let server = TestServer::start(|request| {
+            let page = request.path.rsplit_once("page=").map_or("0", |(_, p)| p);
+            let body = json!({"items": [{"id": page}]});
+            match page {
+                "0" => Response::json(200, &body)
+                    .header("Link", r#"<items?fields=id,name&page=1>; rel="next""#),
+                "1" => Response::json(200, &body).header(
+                    "Link",
+                    r#"</v5/items?page=0>; rel="first", </v5/items?fields=id,name&page=2>; rel="next""#,
+                ),
+                _ => Response::json(200, &body),
+            }
+        });
+        let client = PinterestClient::new(format!("{}/v5", server.url())).unwrap();
+        let url = client.build_url("/items").unwrap();
+        let query = [("fields".to_string(), "id,name".to_string())];
+        let result = paginate_all(
+            &client,
+            "GET",
+            &url,
+            &Auth::Bearer("t".into()),
+            &query,
+            PageLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            json!({"items": [{"id": "0"}, {"id": "1"}, {"id": "2"}]})
+        );
+        let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "/v5/items?fields=id%2Cname",
+                "/v5/items?fields=id,name&page=1",
+                "/v5/items?fields=id,name&page=2",
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_another_origin_without_credentials() {
+        let other = TestServer::start(|_| Response::json(200, &json!({"items": [{"id": "2"}]})));
+        let next = format!("{}/elsewhere?page=2", other.url());
+        let api = TestServer::start(move |_| {
+            Response::json(200, &json!({"items": [{"id": "1"}], "next": next}))
+        });
+        let client = PinterestClient::new(format!("{}/v5", api.url())).unwrap();
+        let url = client.build_url("/items").unwrap();
+        let result = paginate_all(
+            &client,
+            "GET",
+            &url,
+            &Auth::Bearer("t".into()),
+            &[],
+            PageLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(result, json!({"items": [{"id": "1"}, {"id": "2"}]}));
+        let first = &api.requests()[0];
+        assert_eq!(first.header("authorization"), Some("Bearer t"));
+        let requests = other.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/elsewhere?page=2");
+        assert_eq!(requests[0].header("authorization"), None);
+    }
 }
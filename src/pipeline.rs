@@ -0,0 +1,144 @@
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::output;
+
+/// A `run-pipeline` file: operations run in order, later steps reading
+/// earlier results through `${steps.N.path}` placeholders.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    pub steps: Vec<Step>,
+    /// Keep going after a failed step (a step can override this).
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// `resource op`, as on the command line (e.g. `campaigns create`).
+    pub op: String,
+    /// Path and query params by name.
+    #[serde(default)]
+    pub params: Map<String, Value>,
+    /// JSON request body.
+    pub body: Option<Value>,
+    /// urlencoded request body, as a JSON object.
+    pub form: Option<Map<String, Value>>,
+    pub continue_on_error: Option<bool>,
+}
+
+/// One entry of a `batch` run: an independent call, no placeholders.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchCall {
+    pub resource: String,
+    pub op: String,
+    /// Path and query params by name.
+    #[serde(default)]
+    pub params: Map<String, Value>,
+    /// JSON request body.
+    pub body: Option<Value>,
+    /// urlencoded request body, as a JSON object.
+    pub form: Option<Map<String, Value>>,
+}
+
+/// Parses a `batch` input: a JSON array of calls.
+pub fn parse_batch(text: &str) -> Result<Vec<BatchCall>> {
+    let calls: Vec<BatchCall> =
+        serde_json::from_str(text).map_err(|err| anyhow!("invalid batch: {err}"))?;
+    if calls.is_empty() {
+        return Err(anyhow!("batch has no calls"));
+    }
+    Ok(calls)
+}
+
+/// Parses a pipeline from JSON or YAML (YAML being a superset of JSON).
+pub fn parse(text: &str) -> Result<Pipeline> {
+    let pipeline: Pipeline =
+        serde_yaml::from_str(text).map_err(|err| anyhow!("invalid pipeline: {err}"))?;
+    if pipeline.steps.is_empty() {
+        return Err(anyhow!("pipeline has no steps"));
+    }
+    Ok(pipeline)
+}
+
+/// Replaces `${steps.N.path}` placeholders in every string of `value` with the
+/// results of earlier steps (`None` for ones that failed). A string that is
+/// only a placeholder takes the referenced value as-is, keeping its JSON type.
+pub fn interpolate(value: &Value, results: &[Option<Value>]) -> Result<Value> {
+    Ok(match value {
+        Value::String(text) => interpolate_str(text, results)?,
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| interpolate(v, results))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), interpolate(v, results)?)))
+                .collect::<Result<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+fn interpolate_str(text: &str, results: &[Option<Value>]) -> Result<Value> {
+    if let Some(expr) = text.strip_prefix("${").and_then(|t| t.strip_suffix('}'))
+        && !expr.contains('}')
+    {
+        return resolve(expr, results).cloned();
+    }
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed '${{' in {text:?}"))?
+            + start;
+        match resolve(&rest[start + 2..end], results)? {
+            Value::String(s) => out.push_str(s),
+            Value::Object(_) | Value::Array(_) => {
+                return Err(anyhow!(
+                    "${{{}}} is not a scalar and can't be embedded in {text:?}",
+                    &rest[start + 2..end]
+                ));
+            }
+            scalar => out.push_str(&scalar.to_string()),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(Value::String(out))
+}
+
+fn resolve<'a>(expr: &str, results: &'a [Option<Value>]) -> Result<&'a Value> {
+    let placeholder = || format!("${{{expr}}}");
+    let rest = expr
+        .strip_prefix("steps.")
+        .ok_or_else(|| anyhow!("{}: placeholders start with steps.N", placeholder()))?;
+    let (index, path) = rest.split_once('.').unwrap_or((rest, ""));
+    let index: usize = index
+        .parse()
+        .map_err(|_| anyhow!("{}: invalid step number {index:?}", placeholder()))?;
+    let result = match results.get(index) {
+        Some(Some(result)) => result,
+        Some(None) => return Err(anyhow!("{}: step {index} failed", placeholder())),
+        None => {
+            return Err(anyhow!("{}: step {index} has not run yet", placeholder()));
+        }
+    };
+    if path.is_empty() {
+        return Ok(result);
+    }
+    output::lookup(result, path).ok_or_else(|| {
+        anyhow!(
+            "{}: {path} not found in step {index}'s result",
+            placeholder()
+        )
+    })
+}
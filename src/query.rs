@@ -0,0 +1,214 @@
+use anyhow::{Result, anyhow};
+use serde_json::{Map, Value};
+
+use crate::command_tree::ParamDef;
+
+/// Query string encoding shared by every request.
+///
+/// Keys and values are percent-encoded per RFC 3986 (everything but
+/// `A-Z a-z 0-9 - . _ ~`, spaces become `%20`). The one exception is `[`/`]` in
+/// keys, which stay literal so deepObject params read as `filter[field]=value`.
+pub fn encode_pairs(pairs: &[(String, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", encode_key(k), urlencoding::encode(v)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+pub fn encode_key(key: &str) -> String {
+    urlencoding::encode(key)
+        .replace("%5B", "[")
+        .replace("%5D", "]")
+}
+
+/// Appends encoded pairs to a URL that may already carry a query string.
+pub fn append_to_url(url: &str, pairs: &[(String, String)]) -> String {
+    if pairs.is_empty() {
+        return url.to_string();
+    }
+    let sep = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{sep}{}", encode_pairs(pairs))
+}
+
+/// Folds query pairs back into a JSON object for `--params-in-body`: bracketed keys
+/// nest (`filter[a]` -> `{"filter": {"a": ..}}`), repeated keys become arrays, and
+/// values that parse as JSON numbers/booleans keep that type.
+pub fn pairs_to_json(pairs: &[(String, String)]) -> Value {
+    let mut root = Map::new();
+    for (key, raw) in pairs {
+        let value = match serde_json::from_str::<Value>(raw) {
+            Ok(v @ (Value::Number(_) | Value::Bool(_))) => v,
+            _ => Value::String(raw.clone()),
+        };
+        let mut parts = key.split('[').map(|p| p.trim_end_matches(']'));
+        let first = parts.next().unwrap_or_default().to_string();
+        let rest: Vec<&str> = parts.collect();
+        insert_path(&mut root, &first, &rest, value);
+    }
+    Value::Object(root)
+}
+
+fn insert_path(map: &mut Map<String, Value>, key: &str, rest: &[&str], value: Value) {
+    if let Some((next, tail)) = rest.split_first() {
+        let entry = map
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(child) = entry {
+            insert_path(child, next, tail, value);
+        }
+        return;
+    }
+    match map.get_mut(key) {
+        Some(Value::Array(items)) => items.push(value),
+        Some(existing) => {
+            let prev = existing.take();
+            *existing = Value::Array(vec![prev, value]);
+        }
+        None => {
+            map.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// A `--filter field=value` the endpoint can't apply; checked against each
+/// output item instead.
+#[derive(Debug, Clone)]
+pub struct ClientFilter {
+    pub path: String,
+    pub value: String,
+}
+
+/// `--filter` split into query params the op accepts (pushed to the server)
+/// and residual predicates applied client-side.
+#[derive(Debug, Default)]
+pub struct FilterPlan {
+    pub server: Vec<(String, String)>,
+    pub client: Vec<ClientFilter>,
+}
+
+pub fn plan_filters(filters: &[String], params: &[ParamDef]) -> Result<FilterPlan> {
+    let mut server = Vec::new();
+    let mut client = Vec::new();
+    for filter in filters {
+        let (field, value) = filter
+            .split_once('=')
+            .ok_or_else(|| anyhow!("invalid --filter {filter:?}; expected field=value"))?;
+        if params
+            .iter()
+            .any(|p| p.location == "query" && p.name == field)
+        {
+            log::debug!("filter {field}: sent as query param");
+            server.push((field.to_string(), value.to_string()));
+        } else {
+            log::debug!("filter {field}: applied client-side");
+            client.push(ClientFilter {
+                path: field.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+    Ok(FilterPlan { server, client })
+}
+
+impl ClientFilter {
+    /// Parses `path=value`; a leading JSONPath `$.` is accepted and dropped.
+    pub fn parse(spec: &str, flag: &str) -> Result<Self> {
+        let (path, value) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow!("invalid {flag} {spec:?}; expected path=value"))?;
+        let path = path.strip_prefix("$.").unwrap_or(path);
+        Ok(ClientFilter {
+            path: path.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Matches when the value at the dotted path equals the filter value
+    /// (compared as text), or for arrays, when any element does.
+    pub fn matches(&self, item: &Value) -> bool {
+        let Some(found) = self.path.split('.').try_fold(item, |v, key| v.get(key)) else {
+            return false;
+        };
+        let equals = |v: &Value| match v {
+            Value::String(s) => *s == self.value,
+            Value::Number(n) => n.to_string() == self.value,
+            Value::Bool(b) => b.to_string() == self.value,
+            _ => false,
+        };
+        match found {
+            Value::Array(items) => items.iter().any(equals),
+            other => equals(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn deep_object_keys_keep_brackets_and_encode_the_rest() {
+        let encoded = encode_pairs(&pairs(&[
+            ("filter[campaign name]", "Spring sale"),
+            ("filter[a&b=c]", "x"),
+            ("filter[tags][#1/2]", "100%"),
+            ("filter[café]", "ü"),
+        ]));
+        assert_eq!(
+            encoded,
+            "filter[campaign%20name]=Spring%20sale\
+             &filter[a%26b%3Dc]=x\
+             &filter[tags][%231%2F2]=100%25\
+             &filter[caf%C3%A9]=%C3%BC"
+        );
+    }
+
+    #[test]
+    fn values_are_fully_encoded() {
+        assert_eq!(
+            encode_pairs(&pairs(&[("q", "a+b c&d=e[f]"), ("empty", "")])),
+            "q=a%2Bb%20c%26d%3De%5Bf%5D&empty="
+        );
+    }
+
+    #[test]
+    fn append_to_url_extends_an_existing_query() {
+        let query = pairs(&[("filter[a b]", "1")]);
+        assert_eq!(
+            append_to_url("https://x.test/v5/items?bookmark=b1", &query),
+            "https://x.test/v5/items?bookmark=b1&filter[a%20b]=1"
+        );
+        assert_eq!(
+            append_to_url("https://x.test/v5/items", &[]),
+            "https://x.test/v5/items"
+        );
+    }
+
+    #[test]
+    fn pairs_to_json_nests_deep_object_keys() {
+        let query = pairs(&[
+            ("filter[campaign name]", "Spring"),
+            ("filter[ids]", "1"),
+            ("filter[ids]", "2"),
+            ("page_size", "25"),
+            ("flag", "true"),
+        ]);
+        assert_eq!(
+            pairs_to_json(&query),
+            json!({
+                "filter": {"campaign name": "Spring", "ids": [1, 2]},
+                "page_size": 25,
+                "flag": true,
+            })
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{Result, anyhow};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::query;
+
+/// Query keys whose values never reach the log.
+const SECRET_KEYS: &[&str] = &["token", "secret", "password", "code"];
+
+/// Appends one JSON object per HTTP request to `--log-file`, as an audit trail
+/// separate from stderr logging. Auth headers and bodies are never written.
+pub struct RequestLog {
+    file: Mutex<File>,
+}
+
+impl RequestLog {
+    pub fn open(path: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| anyhow!("open log file {path}: {err}"))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(
+        &self,
+        method: &str,
+        url: &str,
+        query: &[(String, String)],
+        outcome: Result<StatusCode, &anyhow::Error>,
+        duration: Duration,
+    ) {
+        let mut record = json!({
+            "ts": chrono::Utc::now().to_rfc3339(),
+            "method": method,
+            "url": redact_url(url, query),
+            "duration_ms": duration.as_millis() as u64,
+        });
+        match outcome {
+            Ok(status) => record["status"] = json!(status.as_u16()),
+            Err(err) => record["error"] = json!(format!("{err:#}")),
+        }
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(err) = writeln!(file, "{record}") {
+            log::warn!("write log file: {err}");
+        }
+    }
+}
+
+fn redact_url(url: &str, query: &[(String, String)]) -> String {
+    let redacted: Vec<(String, String)> = query
+        .iter()
+        .map(|(k, v)| {
+            let lower = k.to_ascii_lowercase();
+            if SECRET_KEYS.iter().any(|s| lower.contains(s)) {
+                (k.clone(), "REDACTED".to_string())
+            } else {
+                (k.clone(), v.clone())
+            }
+        })
+        .collect();
+    let mut base = url.to_string();
+    if let Ok(mut parsed) = url::Url::parse(url) {
+        let _ = parsed.set_username("");
+        let _ = parsed.set_password(None);
+        base = parsed.into();
+    }
+    query::append_to_url(&base, &redacted)
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+use crate::client::ApiResponse;
+
+/// Successful GET responses saved on disk and replayed while younger than a
+/// TTL, for callers re-running the same reads (dashboards, cron jobs). Unlike
+/// HTTP conditional caching this needs nothing from the server: a fresh entry
+/// is returned without a request. A write through the same client drops the
+/// entries for that resource for the rest of the process.
+#[derive(Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    /// Paths written to since this cache was created.
+    written: Mutex<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    /// Unix seconds.
+    stored_at: u64,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Value,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            written: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// File name for a request; `parts` identify it (URL with query, auth,
+    /// headers), and are hashed so no token ends up on disk.
+    pub fn key(parts: &[&str]) -> String {
+        let mut hash = Sha256::new();
+        for part in parts {
+            hash.update(part.as_bytes());
+            hash.update([0]);
+        }
+        hash.finalize().iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// The cached response, unless missing, expired, unreadable or written to.
+    pub fn get(&self, key: &str, url: &str) -> Option<ApiResponse> {
+        if self.is_written(url) {
+            return None;
+        }
+        let text = std::fs::read_to_string(self.path(key)).ok()?;
+        let entry: Entry = match serde_json::from_str(&text) {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::debug!("ignoring unreadable cache entry for {url}: {err}");
+                return None;
+            }
+        };
+        let age = now().saturating_sub(entry.stored_at);
+        if entry.url != url || age >= self.ttl.as_secs() {
+            return None;
+        }
+        let mut headers = HeaderMap::new();
+        for (name, value) in &entry.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        log::debug!("GET {url}: served from cache ({age}s old)");
+        Some(ApiResponse {
+            status: StatusCode::from_u16(entry.status).ok()?,
+            headers,
+            body: entry.body,
+        })
+    }
+
+    /// Saves a 2xx response; failing to write only logs a warning.
+    pub fn put(&self, key: &str, url: &str, resp: &ApiResponse) {
+        if !resp.status.is_success() {
+            return;
+        }
+        let entry = Entry {
+            url: url.to_string(),
+            stored_at: now(),
+            status: resp.status.as_u16(),
+            headers: resp
+                .headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.into())))
+                .collect(),
+            body: resp.body.clone(),
+        };
+        if let Err(err) = self.write(key, &entry) {
+            log::warn!("cache {url}: {err:#}");
+        }
+    }
+
+    /// Drops cached reads of the resource at `url` and anything under or above
+    /// it (a write to `/campaigns/1` also stales the `/campaigns` listing).
+    pub fn invalidate(&self, url: &str) {
+        self.written
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(resource_path(url));
+    }
+
+    fn is_written(&self, url: &str) -> bool {
+        let path = resource_path(url);
+        let related = |a: &str, b: &str| {
+            a.strip_prefix(b)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        };
+        self.written
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .any(|written| related(&path, written) || related(written, &path))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    fn write(&self, key: &str, entry: &Entry) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("create {}", self.dir.display()))?;
+        // Written whole then renamed, so a concurrent run never reads half an entry.
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir).context("create temp file")?;
+        serde_json::to_writer(&mut file, entry).context("write cache entry")?;
+        file.flush().context("write cache entry")?;
+        file.persist(self.path(key)).context("save cache entry")?;
+        Ok(())
+    }
+}
+
+fn resource_path(url: &str) -> String {
+    let path = Url::parse(url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_else(|_| url.split('?').next().unwrap_or(url).to_string());
+    path.trim_end_matches('/').to_string()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
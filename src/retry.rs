@@ -0,0 +1,276 @@
+use anyhow::{Result, anyhow};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Upper bound on a computed backoff delay.
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Upper bound on a single wait for a server's `Retry-After`.
+pub const DEFAULT_RETRY_AFTER_CAP: Duration = Duration::from_secs(300);
+
+/// Exponential backoff shared by every retry loop: 429s, gateway errors,
+/// `--retry-404`, `--retry-if` and media processing polls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; `0` disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles with each one after.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Longest a `Retry-After` is honored for; longer asks wait this long.
+    pub retry_after_cap: Duration,
+    /// Randomize each delay between half and all of its value, so parallel
+    /// clients don't retry in lockstep.
+    pub jitter: bool,
+    /// Methods retried after a failure the server may have half-processed.
+    pub methods: RetryMethods,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            max_delay: DEFAULT_MAX_DELAY,
+            retry_after_cap: DEFAULT_RETRY_AFTER_CAP,
+            jitter: true,
+            methods: RetryMethods::default(),
+        }
+    }
+
+    /// Same delay settings with a different base delay and retry count.
+    pub fn derive(self, max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            ..self
+        }
+    }
+
+    /// Whether retry number `retry` (1-based) is still allowed.
+    pub fn allows(&self, retry: u32) -> bool {
+        retry <= self.max_retries
+    }
+
+    /// Delay before retry number `retry` (1-based). A server `Retry-After`
+    /// is honored as given, never shortened by jitter or the max delay, up
+    /// to `retry_after_cap` (with a warning when it is cut short).
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(delay) = retry_after {
+            if delay > self.retry_after_cap {
+                log::warn!(
+                    "server asked to wait {}s (Retry-After); waiting {}s (--retry-after-cap)",
+                    delay.as_secs(),
+                    self.retry_after_cap.as_secs()
+                );
+                return self.retry_after_cap;
+            }
+            return delay;
+        }
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+        let half = delay / 2;
+        half + half.mul_f64(random_fraction())
+    }
+}
+
+/// The HTTP methods safe to send again (`--retry-methods`); GET and HEAD by
+/// default, since other methods may not be idempotent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryMethods(u8);
+
+const METHODS: [&str; 6] = ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"];
+
+impl Default for RetryMethods {
+    fn default() -> Self {
+        Self(0b11)
+    }
+}
+
+impl RetryMethods {
+    /// A comma-separated list like `GET,PUT,DELETE` (case-insensitive).
+    pub fn parse(list: &str) -> Result<Self> {
+        let mut bits = 0;
+        for name in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let index = METHODS
+                .iter()
+                .position(|m| m.eq_ignore_ascii_case(name))
+                .ok_or_else(|| {
+                    anyhow!("unknown method {name:?}; expected {}", METHODS.join(", "))
+                })?;
+            bits |= 1 << index;
+        }
+        if bits == 0 {
+            return Err(anyhow!("no methods given"));
+        }
+        Ok(Self(bits))
+    }
+
+    pub fn contains(self, method: &str) -> bool {
+        METHODS
+            .iter()
+            .position(|m| *m == method)
+            .is_some_and(|index| self.0 & (1 << index) != 0)
+    }
+}
+
+/// Total retries allowed across a whole run (e.g. every row of a bulk job),
+/// shared by all clones of a client. Once spent, failures are returned at once.
+#[derive(Debug)]
+pub struct RetryBudget {
+    remaining: AtomicU64,
+    exhausted: AtomicBool,
+}
+
+impl RetryBudget {
+    pub fn new(retries: u64) -> Self {
+        Self {
+            remaining: AtomicU64::new(retries),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Takes one retry from the budget; `false` (with a one-time warning) when empty.
+    pub fn try_spend(&self) -> bool {
+        let spent = self
+            .remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if !spent && !self.exhausted.swap(true, Ordering::SeqCst) {
+            log::warn!("retry budget exhausted; failing further requests without retrying");
+        }
+        spent
+    }
+}
+
+/// Parses `Retry-After` as delta-seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let at = SystemTime::from(at);
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// A value in `[0, 1)`; std's randomly keyed hasher is random enough for jitter.
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn fixed(base: Duration) -> RetryPolicy {
+        RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::new(10, base)
+        }
+    }
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+
+    #[test]
+    fn delay_doubles_up_to_max_delay() {
+        let policy = fixed(Duration::from_secs(1));
+        let delays: Vec<u64> = (1..=7).map(|n| policy.delay(n, None).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(policy.delay(200, None), DEFAULT_MAX_DELAY);
+    }
+
+    #[test]
+    fn retry_after_is_honored_up_to_the_cap() {
+        let policy = RetryPolicy {
+            retry_after_cap: Duration::from_secs(60),
+            ..RetryPolicy::new(3, Duration::from_secs(1))
+        };
+        // Longer than max_delay and not jittered.
+        let asked = Duration::from_secs(45);
+        assert_eq!(policy.delay(1, Some(asked)), asked);
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(600))),
+            Duration::from_secs(60)
+        );
+        assert_eq!(policy.delay(1, Some(Duration::ZERO)), Duration::ZERO);
+    }
+
+    #[test]
+    fn jitter_stays_between_half_and_all_of_the_delay() {
+        let policy = RetryPolicy::new(10, Duration::from_secs(4));
+        for retry in 1..=4 {
+            let full = fixed(Duration::from_secs(4)).delay(retry, None);
+            for _ in 0..50 {
+                let delay = policy.delay(retry, None);
+                assert!(delay >= full / 2 && delay <= full, "{delay:?} for {full:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn retry_after_parses_seconds_and_http_dates() {
+        assert_eq!(retry_after(&headers("120")), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(&headers(" 0 ")), Some(Duration::ZERO));
+
+        let at = chrono::Utc::now() + chrono::Duration::seconds(90);
+        let date = at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let wait = retry_after(&headers(&date)).unwrap();
+        assert!(
+            wait > Duration::from_secs(85) && wait <= Duration::from_secs(90),
+            "{wait:?}"
+        );
+        // A date already past means retry now.
+        assert_eq!(
+            retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+
+        for garbage in ["soon", "-5", "1.5", ""] {
+            assert_eq!(retry_after(&headers(garbage)), None, "{garbage:?}");
+        }
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn retry_methods_parse() {
+        let methods = RetryMethods::parse("get, put,Delete,").unwrap();
+        assert!(methods.contains("GET") && methods.contains("PUT") && methods.contains("DELETE"));
+        assert!(!methods.contains("POST") && !methods.contains("HEAD"));
+
+        let default = RetryMethods::default();
+        assert!(default.contains("GET") && default.contains("HEAD"));
+        assert!(!default.contains("POST"));
+
+        let err = RetryMethods::parse("GET,FETCH").unwrap_err();
+        assert!(
+            err.to_string().starts_with("unknown method \"FETCH\""),
+            "{err}"
+        );
+        assert_eq!(
+            RetryMethods::parse(" , ").unwrap_err().to_string(),
+            "no methods given"
+        );
+    }
+
+    #[test]
+    fn budget_runs_out() {
+        let budget = RetryBudget::new(2);
+        assert!(budget.try_spend());
+        assert!(budget.try_spend());
+        assert!(!budget.try_spend());
+        assert!(!budget.try_spend());
+    }
+}
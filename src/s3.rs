@@ -1,7 +1,45 @@
 use anyhow::{Context, Result, anyhow};
+use aws_config::retry::RetryConfig;
+use aws_config::sts::AssumeRoleProvider;
+use aws_config::timeout::TimeoutConfig;
 use aws_config::{BehaviorVersion, SdkConfig};
 use aws_sdk_s3::Client;
-use std::io::Write;
+use aws_sdk_s3::error::{DisplayErrorContext, SdkError};
+use aws_sdk_s3::primitives::ByteStream;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use crate::cancel;
+use crate::retry::RetryPolicy;
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Objects at least this large report download progress on stderr.
+const PROGRESS_MIN_BYTES: u64 = 64 * 1024 * 1024;
+
+/// S3 access settings layered over the default AWS credential chain.
+#[derive(Debug, Clone)]
+pub struct S3Options {
+    /// Role assumed via STS (using the base credentials) before reading objects.
+    pub role_arn: Option<String>,
+    pub external_id: Option<String>,
+    /// Attempts per request (SDK retries) and per download (resumed from the last byte written).
+    pub max_attempts: u32,
+    /// Limit for each S3 call to return response headers; body streaming is bounded by
+    /// the SDK's stalled-stream protection instead, so large objects are not cut off.
+    pub timeout: Duration,
+}
+
+impl Default for S3Options {
+    fn default() -> Self {
+        Self {
+            role_arn: None,
+            external_id: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
 
 fn build_runtime() -> Result<tokio::runtime::Runtime> {
     tokio::runtime::Builder::new_multi_thread()
@@ -10,8 +48,37 @@ fn build_runtime() -> Result<tokio::runtime::Runtime> {
         .context("create tokio runtime")
 }
 
-async fn load_config() -> Result<SdkConfig> {
-    Ok(aws_config::load_defaults(BehaviorVersion::latest()).await)
+async fn load_config(opts: &S3Options) -> Result<SdkConfig> {
+    let retry = RetryConfig::standard().with_max_attempts(opts.max_attempts.max(1));
+    let timeout = TimeoutConfig::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .operation_attempt_timeout(opts.timeout)
+        .build();
+    Ok(aws_config::defaults(BehaviorVersion::latest())
+        .retry_config(retry)
+        .timeout_config(timeout)
+        .load()
+        .await)
+}
+
+async fn build_client(opts: &S3Options) -> Result<Client> {
+    let config = load_config(opts).await?;
+    let Some(role_arn) = &opts.role_arn else {
+        return Ok(Client::new(&config));
+    };
+
+    let mut provider = AssumeRoleProvider::builder(role_arn)
+        .session_name("pinterest-ads-cli")
+        .configure(&config);
+    if let Some(external_id) = &opts.external_id {
+        provider = provider.external_id(external_id);
+    }
+    let provider = provider.build().await;
+
+    let s3_config = aws_sdk_s3::config::Builder::from(&config)
+        .credentials_provider(provider)
+        .build();
+    Ok(Client::from_conf(s3_config))
 }
 
 pub fn parse_s3_url(url: &str) -> Result<(String, String)> {
@@ -27,23 +94,223 @@ pub fn parse_s3_url(url: &str) -> Result<(String, String)> {
     Ok((bucket, key))
 }
 
-pub fn download_object_blocking(bucket: &str, key: &str, out: &mut impl Write) -> Result<()> {
-    let bucket = bucket.to_string();
-    let key = key.to_string();
+pub fn download_object_blocking(
+    bucket: &str,
+    key: &str,
+    out: &mut impl Write,
+    opts: &S3Options,
+) -> Result<()> {
+    let mut reader = open_object_blocking(bucket, key, opts)?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        out.write_all(&buf[..n]).context("write s3 object")?;
+    }
+}
+
+/// Backoff between attempts at one object, on top of the SDK's own retries
+/// of each request: 1s, doubling up to 32s, for `max_attempts` in all.
+fn resume_policy(max_attempts: u32) -> RetryPolicy {
+    let mut policy = RetryPolicy::new(max_attempts.max(1) - 1, Duration::from_secs(1));
+    policy.max_delay = Duration::from_secs(32);
+    policy
+}
+
+/// Opens an object for reading without downloading it first; see [`ObjectReader`].
+pub fn open_object_blocking(bucket: &str, key: &str, opts: &S3Options) -> Result<ObjectReader> {
     let rt = build_runtime()?;
-    rt.block_on(async move {
-        let config = load_config().await?;
-        let client = Client::new(&config);
-        let resp = client
-            .get_object()
-            .bucket(bucket)
-            .key(key)
-            .send()
-            .await
-            .context("get s3 object")?;
-        let bytes = resp.body.collect().await?.into_bytes();
-        out.write_all(&bytes).context("write s3 object")?;
-        Ok::<_, anyhow::Error>(())
-    })?;
-    Ok(())
+    let client = rt.block_on(build_client(opts))?;
+    let mut reader = ObjectReader {
+        rt,
+        client,
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        etag: None,
+        len: 0,
+        body: ByteStream::default(),
+        chunk: Vec::new(),
+        offset: 0,
+        read: 0,
+        retry: resume_policy(opts.max_attempts),
+        retries: 0,
+        progress: Progress::default(),
+    };
+    reader.open()?;
+    Ok(reader)
+}
+
+/// A blocking reader over an object's body, for downloading it or streaming
+/// it straight into another request. After a transient failure, mid-body or
+/// opening, it resumes with a ranged GET from the last byte read; resumed
+/// GETs pin the first response's ETag so a replaced object is not spliced
+/// onto the partial copy.
+pub struct ObjectReader {
+    rt: tokio::runtime::Runtime,
+    client: Client,
+    bucket: String,
+    key: String,
+    etag: Option<String>,
+    len: u64,
+    body: ByteStream,
+    /// The chunk being read out, and how much of it is already consumed.
+    chunk: Vec<u8>,
+    offset: usize,
+    read: u64,
+    retry: RetryPolicy,
+    /// Retries so far, across the whole object.
+    retries: u32,
+    progress: Progress,
+}
+
+impl ObjectReader {
+    /// The object's size from the first response's `Content-Length`.
+    pub fn content_length(&self) -> u64 {
+        self.len
+    }
+
+    fn url(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.key)
+    }
+
+    /// Sends the GET, ranged from the last byte read when resuming, until it
+    /// answers or the retries run out.
+    fn open(&mut self) -> Result<()> {
+        loop {
+            let mut request = self.client.get_object().bucket(&self.bucket).key(&self.key);
+            if self.read > 0 {
+                request = request.range(format!("bytes={}-", self.read));
+            }
+            if let Some(etag) = &self.etag {
+                request = request.if_match(etag);
+            }
+            let err = match self.rt.block_on(request.send()) {
+                Ok(resp) => {
+                    if self.etag.is_none() {
+                        self.etag = resp.e_tag().map(str::to_string);
+                        self.len = resp
+                            .content_length()
+                            .and_then(|n| u64::try_from(n).ok())
+                            .ok_or_else(|| anyhow!("get {}: no Content-Length", self.url()))?;
+                    }
+                    self.body = resp.body;
+                    return Ok(());
+                }
+                // The SDK already retried throttling and 5xx; errors like
+                // NoSuchKey, AccessDenied or a failed If-Match will not change
+                // on another attempt.
+                Err(err @ SdkError::ServiceError(_)) => {
+                    return Err(anyhow!("get {}: {}", self.url(), DisplayErrorContext(&err)));
+                }
+                Err(err) => anyhow!("get {}: {}", self.url(), DisplayErrorContext(&err)),
+            };
+            self.back_off(err)?;
+        }
+    }
+
+    /// Waits before the next attempt under the retry policy, or gives up
+    /// with `err` once it is spent.
+    fn back_off(&mut self, err: anyhow::Error) -> Result<()> {
+        self.retries += 1;
+        if !self.retry.allows(self.retries) || cancel::is_cancelled() {
+            return Err(err);
+        }
+        let delay = self.retry.delay(self.retries, None);
+        log::warn!(
+            "{err}; retrying from byte {} in {delay:.1?} (attempt {}/{})",
+            self.read,
+            self.retries + 1,
+            self.retry.max_retries + 1
+        );
+        std::thread::sleep(delay);
+        Ok(())
+    }
+}
+
+impl Read for ObjectReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.offset == self.chunk.len() {
+            match self.rt.block_on(self.body.try_next()) {
+                Ok(Some(chunk)) => {
+                    self.chunk = chunk.to_vec();
+                    self.offset = 0;
+                }
+                Ok(None) => return Ok(0),
+                Err(err) => {
+                    let err = anyhow!("read {}: {err}", self.url());
+                    self.back_off(err)
+                        .and_then(|()| self.open())
+                        .map_err(std::io::Error::other)?;
+                }
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.offset);
+        buf[..n].copy_from_slice(&self.chunk[self.offset..self.offset + n]);
+        self.offset += n;
+        self.read += n as u64;
+        let url = self.url();
+        self.progress.report(&url, self.read, Some(self.len));
+        Ok(n)
+    }
+}
+
+/// Prints a stderr line at each 10% of a large download.
+#[derive(Default)]
+struct Progress {
+    reported: u64,
+}
+
+impl Progress {
+    fn report(&mut self, url: &str, written: u64, total: Option<u64>) {
+        let Some(total) = total.filter(|t| *t >= PROGRESS_MIN_BYTES) else {
+            return;
+        };
+        let tenth = written * 10 / total;
+        if tenth <= self.reported {
+            return;
+        }
+        self.reported = tenth;
+        eprintln!(
+            "{url}: {} / {} MiB ({}%)",
+            written / (1024 * 1024),
+            total / (1024 * 1024),
+            tenth * 10
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_policy_doubles_from_one_second_up_to_32() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..resume_policy(10)
+        };
+        let delays: Vec<u64> = (1..=9).map(|n| policy.delay(n, None).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 32, 32, 32]);
+    }
+
+    #[test]
+    fn resume_policy_allows_max_attempts_in_all() {
+        let policy = resume_policy(3);
+        assert!(policy.allows(2));
+        assert!(!policy.allows(3));
+        // At least the first attempt, even with a zero setting.
+        assert!(!resume_policy(0).allows(1));
+    }
+
+    #[test]
+    fn jittered_resume_delays_stay_within_half_to_full() {
+        let policy = resume_policy(10);
+        for retry in 1..=8 {
+            let full = Duration::from_secs(1 << (retry - 1).min(5));
+            let delay = policy.delay(retry, None);
+            assert!(delay >= full / 2 && delay <= full, "{retry}: {delay:?}");
+        }
+    }
 }
@@ -0,0 +1,591 @@
+use serde_json::{Map, Value};
+
+use pinterest_ads::command_tree::CommandTree;
+
+const REF_PREFIX: &str = "#/components/schemas/";
+
+/// Inlines `$ref`s against `tree.schemas`; recursive references are left as `$ref`.
+pub fn resolve_refs(tree: &CommandTree, schema: &Value) -> Value {
+    fn walk(tree: &CommandTree, value: &Value, stack: &mut Vec<String>) -> Value {
+        match value {
+            Value::Object(map) => {
+                if let Some(name) = map
+                    .get("$ref")
+                    .and_then(|v| v.as_str())
+                    .and_then(|r| r.strip_prefix(REF_PREFIX))
+                    && !stack.iter().any(|s| s == name)
+                    && let Some(target) = tree.schemas.get(name)
+                {
+                    stack.push(name.to_string());
+                    let resolved = walk(tree, target, stack);
+                    stack.pop();
+                    return resolved;
+                }
+                let mut out = Map::new();
+                for (k, v) in map {
+                    out.insert(k.clone(), walk(tree, v, stack));
+                }
+                Value::Object(out)
+            }
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| walk(tree, v, stack)).collect())
+            }
+            other => other.clone(),
+        }
+    }
+
+    walk(tree, schema, &mut Vec::new())
+}
+
+#[derive(Debug)]
+pub struct FieldInfo {
+    pub path: String,
+    pub schema_type: String,
+    pub required: bool,
+    pub enum_values: Vec<String>,
+}
+
+/// Flattens a resolved schema into dotted field paths (`[]` marks array items).
+pub fn list_fields(schema: &Value) -> Vec<FieldInfo> {
+    let mut out = Vec::new();
+    walk_fields(schema, "", true, &mut out);
+    out
+}
+
+fn walk_fields(schema: &Value, path: &str, required: bool, out: &mut Vec<FieldInfo>) {
+    let schema = merge_all_of(schema);
+    let schema_type = schema_type(&schema);
+
+    if !path.is_empty() {
+        out.push(FieldInfo {
+            path: path.to_string(),
+            schema_type: schema_type.clone(),
+            required,
+            enum_values: enum_values(&schema),
+        });
+    }
+
+    match schema_type.as_str() {
+        "array" => {
+            if let Some(items) = schema.get("items") {
+                walk_fields(items, &format!("{path}[]"), required, out);
+            }
+        }
+        "object" => {
+            let required_fields: Vec<&str> = schema
+                .get("required")
+                .and_then(|v| v.as_array())
+                .map(|v| v.iter().filter_map(|f| f.as_str()).collect())
+                .unwrap_or_default();
+            if let Some(props) = schema.get("properties").and_then(|v| v.as_object()) {
+                for (name, prop) in props {
+                    let child = if path.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{path}.{name}")
+                    };
+                    walk_fields(prop, &child, required_fields.contains(&name.as_str()), out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Folds `allOf` (and the first `oneOf`/`anyOf` branch) into a single schema object.
+pub fn merge_all_of(schema: &Value) -> Value {
+    let Value::Object(map) = schema else {
+        return schema.clone();
+    };
+    let mut out = map.clone();
+    let mut parts: Vec<Value> = Vec::new();
+    if let Some(Value::Array(all)) = out.remove("allOf") {
+        parts.extend(all);
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(Value::Array(mut any)) = out.remove(key)
+            && !any.is_empty()
+        {
+            parts.push(any.remove(0));
+        }
+    }
+
+    for part in parts {
+        let Value::Object(part) = merge_all_of(&part) else {
+            continue;
+        };
+        for (k, v) in part {
+            match (k.as_str(), out.get_mut(&k), v) {
+                ("properties", Some(Value::Object(existing)), Value::Object(props)) => {
+                    existing.extend(props);
+                }
+                ("required", Some(Value::Array(existing)), Value::Array(fields)) => {
+                    existing.extend(fields);
+                }
+                (_, Some(_), _) => {}
+                (_, None, v) => {
+                    out.insert(k, v);
+                }
+            }
+        }
+    }
+    Value::Object(out)
+}
+
+pub fn schema_type(schema: &Value) -> String {
+    if let Some(t) = schema.get("type").and_then(|v| v.as_str()) {
+        return t.to_string();
+    }
+    if schema.get("properties").is_some() {
+        return "object".to_string();
+    }
+    if schema.get("items").is_some() {
+        return "array".to_string();
+    }
+    "any".to_string()
+}
+
+/// Builds a placeholder value for a resolved schema: schema examples/defaults when
+/// present, otherwise a typed placeholder. Objects keep only required properties
+/// (or every property when none are required).
+pub fn example_value(schema: &Value) -> Value {
+    let schema = merge_all_of(schema);
+    if let Some(example) = schema.get("example") {
+        return example.clone();
+    }
+    if let Some(default) = schema.get("default") {
+        return default.clone();
+    }
+    if let Some(first) = schema
+        .get("enum")
+        .and_then(|v| v.as_array())
+        .and_then(|v| v.first())
+    {
+        return first.clone();
+    }
+
+    match schema_type(&schema).as_str() {
+        "string" => Value::String("string".to_string()),
+        "integer" | "number" => Value::from(0),
+        "boolean" => Value::Bool(false),
+        "array" => match schema.get("items") {
+            Some(items) => Value::Array(vec![example_value(items)]),
+            None => Value::Array(Vec::new()),
+        },
+        "object" => {
+            let props = schema.get("properties").and_then(|v| v.as_object());
+            let required: Vec<&str> = schema
+                .get("required")
+                .and_then(|v| v.as_array())
+                .map(|v| v.iter().filter_map(|f| f.as_str()).collect())
+                .unwrap_or_default();
+            let mut out = Map::new();
+            for (name, prop) in props.into_iter().flatten() {
+                if required.is_empty() || required.contains(&name.as_str()) {
+                    out.insert(name.clone(), example_value(prop));
+                }
+            }
+            Value::Object(out)
+        }
+        _ => Value::Null,
+    }
+}
+
+/// Builds a value for a resolved schema from answers to `ask(prompt)`, one per
+/// required leaf field (every field, skippable, when an object requires none).
+/// Arrays of objects are built one element at a time, a top-level array body
+/// included. Other arrays and free-form objects are answered as JSON; arrays
+/// of scalars may also be comma-separated.
+pub fn prompt_value(
+    schema: &Value,
+    path: &str,
+    ask: &mut dyn FnMut(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<Value> {
+    Ok(prompt_field(schema, path, true, ask)?.unwrap_or(Value::Null))
+}
+
+fn prompt_field(
+    schema: &Value,
+    path: &str,
+    required: bool,
+    ask: &mut dyn FnMut(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<Option<Value>> {
+    let schema = merge_all_of(schema);
+    let schema_type = schema_type(&schema);
+    if let (true, Some(props)) = (
+        schema_type == "object",
+        schema.get("properties").and_then(|v| v.as_object()),
+    ) {
+        let required_fields: Vec<&str> = schema
+            .get("required")
+            .and_then(|v| v.as_array())
+            .map(|v| v.iter().filter_map(|f| f.as_str()).collect())
+            .unwrap_or_default();
+        let mut out = Map::new();
+        for (name, prop) in props {
+            let field_required = required_fields.contains(&name.as_str());
+            if !field_required && !required_fields.is_empty() {
+                continue;
+            }
+            let child = if path.is_empty() {
+                name.clone()
+            } else {
+                format!("{path}.{name}")
+            };
+            if let Some(value) = prompt_field(prop, &child, field_required, ask)? {
+                out.insert(name.clone(), value);
+            }
+        }
+        return Ok((required || !out.is_empty()).then_some(Value::Object(out)));
+    }
+    if schema_type == "array" {
+        let items = merge_all_of(schema.get("items").unwrap_or(&Value::Null));
+        if items.get("properties").is_some_and(Value::is_object) {
+            return prompt_items(&items, path, required, ask);
+        }
+    }
+
+    let mut prompt = format!(
+        "{} ({schema_type}",
+        if path.is_empty() { "body" } else { path }
+    );
+    let enum_values = enum_values(&schema);
+    if !enum_values.is_empty() {
+        prompt.push_str(&format!(": {}", enum_values.join("|")));
+    }
+    prompt.push_str(if required { "): " } else { ", optional): " });
+    loop {
+        let answer = ask(&prompt)?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            if !required {
+                return Ok(None);
+            }
+            eprintln!("{path} is required");
+            continue;
+        }
+        match parse_answer(&schema, &schema_type, answer) {
+            Ok(value) => return Ok(Some(value)),
+            Err(err) => eprintln!("{err}"),
+        }
+    }
+}
+
+/// Prompts an array of objects element by element, asking before each one
+/// whether to add it; a required array gets its first element unasked.
+fn prompt_items(
+    items: &Value,
+    path: &str,
+    required: bool,
+    ask: &mut dyn FnMut(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<Option<Value>> {
+    let label = if path.is_empty() { "body" } else { path };
+    let mut out = Vec::new();
+    loop {
+        if !(required && out.is_empty()) {
+            let which = if out.is_empty() { "an" } else { "another" };
+            let answer = ask(&format!("add {which} item to {label}? (y/N): "))?;
+            if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+                break;
+            }
+        }
+        let element = format!("{label}[{}]", out.len());
+        out.push(prompt_field(items, &element, true, ask)?.unwrap_or(Value::Null));
+    }
+    Ok((required || !out.is_empty()).then_some(Value::Array(out)))
+}
+
+fn enum_values(schema: &Value) -> Vec<String> {
+    schema
+        .get("enum")
+        .and_then(|v| v.as_array())
+        .map(|values| {
+            values
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| v.to_string())
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_answer(schema: &Value, kind: &str, answer: &str) -> Result<Value, String> {
+    if let Some(values) = schema.get("enum").and_then(|v| v.as_array()) {
+        return values
+            .iter()
+            .find(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string) == answer)
+            .cloned()
+            .ok_or_else(|| format!("expected one of {}", enum_values(schema).join("|")));
+    }
+    match kind {
+        "string" => Ok(Value::String(answer.to_string())),
+        "integer" => answer
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| format!("expected an integer, got {answer:?}")),
+        "number" => match serde_json::from_str::<Value>(answer) {
+            Ok(value @ Value::Number(_)) => Ok(value),
+            _ => Err(format!("expected a number, got {answer:?}")),
+        },
+        "boolean" => match answer.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "y" => Ok(Value::Bool(true)),
+            "false" | "no" | "n" => Ok(Value::Bool(false)),
+            _ => Err(format!("expected true or false, got {answer:?}")),
+        },
+        "array" => {
+            if let Ok(value @ Value::Array(_)) = serde_json::from_str::<Value>(answer) {
+                return Ok(value);
+            }
+            let items = merge_all_of(schema.get("items").unwrap_or(&Value::Null));
+            let items_type = schema_type(&items);
+            if matches!(items_type.as_str(), "array" | "object" | "any") {
+                return Err("expected a JSON array".to_string());
+            }
+            answer
+                .split(',')
+                .map(|item| parse_answer(&items, &items_type, item.trim()))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array)
+        }
+        _ => serde_json::from_str(answer).map_err(|err| format!("expected JSON: {err}")),
+    }
+}
+
+/// Where `value` departs from a resolved response `schema`: type mismatches,
+/// values outside an `enum`, missing required fields and fields the schema
+/// doesn't list. An issue repeated across array items is reported once, with
+/// a count. `null` passes anywhere, since specs seldom mark nullable fields.
+pub fn check_value(schema: &Value, value: &Value) -> Vec<String> {
+    let mut issues = Vec::new();
+    check(schema, value, "", &mut issues);
+    issues
+        .into_iter()
+        .map(|(issue, n)| match n {
+            1 => issue,
+            n => format!("{issue} ({n} times)"),
+        })
+        .collect()
+}
+
+fn check(schema: &Value, value: &Value, path: &str, issues: &mut Vec<(String, u64)>) {
+    if value.is_null() {
+        return;
+    }
+    // One of several shapes: fine if any fits, else report the closest.
+    let branches = ["oneOf", "anyOf"]
+        .iter()
+        .find_map(|key| schema.get(key)?.as_array().filter(|b| !b.is_empty()));
+    if let Some(branches) = branches {
+        let mut rest = schema.as_object().cloned().unwrap_or_default();
+        rest.remove("oneOf");
+        rest.remove("anyOf");
+        let mut closest: Option<Vec<(String, u64)>> = None;
+        for branch in branches {
+            let combined = serde_json::json!({ "allOf": [rest, branch] });
+            let mut found = Vec::new();
+            check(&combined, value, path, &mut found);
+            if found.is_empty() {
+                return;
+            }
+            if closest.as_ref().is_none_or(|c| found.len() < c.len()) {
+                closest = Some(found);
+            }
+        }
+        for (issue, n) in closest.unwrap_or_default() {
+            add_issue(issues, issue, n);
+        }
+        return;
+    }
+
+    let schema = merge_all_of(schema);
+    let here = if path.is_empty() { "<root>" } else { path };
+    let types: Vec<String> = match schema.get("type") {
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(|t| t.as_str().map(str::to_string))
+            .collect(),
+        _ => vec![schema_type(&schema)],
+    };
+    if !types.iter().any(|t| fits_type(t, value)) {
+        let issue = format!(
+            "{here}: expected {}, got {}",
+            types.join(" or "),
+            json_type(value)
+        );
+        add_issue(issues, issue, 1);
+        return;
+    }
+    if let Some(values) = schema.get("enum").and_then(|v| v.as_array())
+        && !values.contains(value)
+    {
+        add_issue(
+            issues,
+            format!("{here}: {value} is not a documented value"),
+            1,
+        );
+    }
+
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match value {
+        Value::Object(map) => {
+            let props = schema.get("properties").and_then(|v| v.as_object());
+            for field in schema
+                .get("required")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|f| f.as_str())
+            {
+                if !map.contains_key(field) {
+                    add_issue(issues, format!("{}: missing (required)", child(field)), 1);
+                }
+            }
+            let additional = schema.get("additionalProperties");
+            for (key, item) in map {
+                match (props.and_then(|p| p.get(key)), additional) {
+                    (Some(prop), _) => check(prop, item, &child(key), issues),
+                    (None, Some(extra @ Value::Object(_))) => {
+                        check(extra, item, &child(key), issues)
+                    }
+                    (None, Some(Value::Bool(true))) => {}
+                    (None, _) if props.is_some_and(|p| !p.is_empty()) => {
+                        add_issue(issues, format!("{}: not in the schema", child(key)), 1);
+                    }
+                    (None, _) => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items {
+                    check(item_schema, item, &format!("{path}[]"), issues);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn add_issue(issues: &mut Vec<(String, u64)>, issue: String, n: u64) {
+    match issues.iter_mut().find(|(seen, _)| *seen == issue) {
+        Some((_, count)) => *count += n,
+        None => issues.push((issue, n)),
+    }
+}
+
+fn fits_type(schema_type: &str, value: &Value) -> bool {
+    match schema_type {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Answers prompts in order, recording each prompt.
+    fn answer(schema: &Value, answers: &[&str]) -> (Value, Vec<String>) {
+        let mut answers = answers.iter();
+        let mut prompts = Vec::new();
+        let value = prompt_value(schema, "", &mut |prompt| {
+            prompts.push(prompt.to_string());
+            Ok(answers.next().expect("no answer left").to_string())
+        })
+        .unwrap();
+        (value, prompts)
+    }
+
+    #[test]
+    fn array_body_prompts_required_fields_per_element() {
+        let schema = json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "required": ["name", "status"],
+                "properties": {
+                    "name": {"type": "string"},
+                    "status": {"type": "string", "enum": ["ACTIVE", "PAUSED"]},
+                    "notes": {"type": "string"},
+                },
+            },
+        });
+        let (value, prompts) = answer(&schema, &["Spring", "PAUSED", "y", "Fall", "ACTIVE", "n"]);
+        assert_eq!(
+            value,
+            json!([
+                {"name": "Spring", "status": "PAUSED"},
+                {"name": "Fall", "status": "ACTIVE"},
+            ])
+        );
+        assert_eq!(
+            prompts,
+            [
+                "body[0].name (string): ",
+                "body[0].status (string: ACTIVE|PAUSED): ",
+                "add another item to body? (y/N): ",
+                "body[1].name (string): ",
+                "body[1].status (string: ACTIVE|PAUSED): ",
+                "add another item to body? (y/N): ",
+            ]
+        );
+    }
+
+    #[test]
+    fn optional_nested_object_arrays_may_stay_empty() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "targeting": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["key"],
+                        "properties": {"key": {"type": "string"}},
+                    },
+                },
+            },
+        });
+        let (value, prompts) = answer(&schema, &[""]);
+        assert_eq!(value, json!({}));
+        assert_eq!(prompts, ["add an item to targeting? (y/N): "]);
+        let (value, _) = answer(&schema, &["y", "AGE", "no"]);
+        assert_eq!(value, json!({"targeting": [{"key": "AGE"}]}));
+    }
+
+    #[test]
+    fn scalar_arrays_are_still_answered_in_one_line() {
+        let schema = json!({"type": "array", "items": {"type": "integer"}});
+        let (value, prompts) = answer(&schema, &["1, 2,3"]);
+        assert_eq!(value, json!([1, 2, 3]));
+        assert_eq!(prompts, ["body (array): "]);
+    }
+}
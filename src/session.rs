@@ -0,0 +1,235 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use pinterest_ads::client::{Auth, Body, PinterestClient};
+
+/// Refresh this long before the recorded expiry so a request doesn't race it.
+const EXPIRY_SKEW_SECS: i64 = 60;
+
+/// Tokens saved by `auth login`, used when no access token is configured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// RFC 3339; `None` when the token's lifetime is unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl Session {
+    /// From an `/oauth/token` response (`expires_in` is in seconds).
+    pub fn from_token_response(resp: &Value) -> Result<Self> {
+        let access_token = resp
+            .get("access_token")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("token response has no access_token"))?;
+        let expires_at = resp
+            .get("expires_in")
+            .and_then(|v| v.as_i64())
+            .map(expires_in);
+        Ok(Self {
+            access_token: access_token.to_string(),
+            refresh_token: resp
+                .get("refresh_token")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            expires_at,
+            scope: resp
+                .get("scope")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+        })
+    }
+
+    /// The granted scopes, split on commas or spaces; `None` when unknown.
+    pub fn scopes(&self) -> Option<Vec<&str>> {
+        let scope = self.scope.as_deref()?;
+        Some(scope.split([',', ' ']).filter(|s| !s.is_empty()).collect())
+    }
+
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        let at = self.expires_at.as_deref()?;
+        DateTime::parse_from_rfc3339(at)
+            .ok()
+            .map(|at| at.with_timezone(&Utc))
+    }
+
+    pub fn expired(&self) -> bool {
+        self.expires_at()
+            .is_some_and(|at| at - Duration::seconds(EXPIRY_SKEW_SECS) <= Utc::now())
+    }
+}
+
+/// The RFC 3339 time `secs` seconds from now.
+pub fn expires_in(secs: i64) -> String {
+    (Utc::now() + Duration::seconds(secs)).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// `session.json` (or `session-PROFILE.json`) next to the default config file.
+pub fn session_path(profile: Option<&str>) -> Option<PathBuf> {
+    let name = match profile {
+        Some(profile) => format!("session-{profile}.json"),
+        None => "session.json".to_string(),
+    };
+    ProjectDirs::from("", "", "pinterest-ads").map(|dirs| dirs.config_dir().join(name))
+}
+
+pub fn load(path: &Path) -> Result<Option<Session>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(anyhow!("read session {}: {err}", path.display())),
+    };
+    let session = serde_json::from_str(&text)
+        .map_err(|err| anyhow!("invalid session {}: {err}", path.display()))?;
+    Ok(Some(session))
+}
+
+/// Writes the session readable by the owner only.
+pub fn save(path: &Path, session: &Session) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|err| anyhow!("write session {}: {err}", path.display()))?;
+    serde_json::to_writer_pretty(&mut file, session)
+        .map_err(|err| anyhow!("write session {}: {err}", path.display()))
+}
+
+/// Deletes the session; `false` when there was none.
+pub fn remove(path: &Path) -> Result<bool> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(anyhow!("remove session {}: {err}", path.display())),
+    }
+}
+
+/// Trades a grant (`authorization_code` or `refresh_token`) for tokens at
+/// `/oauth/token`, authenticating with the app's client id and secret.
+pub fn token_request(
+    client: &PinterestClient,
+    config: &Config,
+    form: Vec<(String, String)>,
+) -> Result<Session> {
+    app_token_request(
+        client,
+        config.client_id.as_deref(),
+        config.client_secret.as_deref(),
+        form,
+    )
+}
+
+fn app_token_request(
+    client: &PinterestClient,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+    form: Vec<(String, String)>,
+) -> Result<Session> {
+    let auth = Auth::Basic {
+        username: client_id
+            .ok_or_else(|| anyhow!("PINTEREST_CLIENT_ID missing"))?
+            .to_string(),
+        password: client_secret
+            .ok_or_else(|| anyhow!("PINTEREST_CLIENT_SECRET missing"))?
+            .to_string(),
+    };
+    let url = client.build_url("/oauth/token")?;
+    let resp = client.request("POST", &url, &auth, &[], Some(Body::Form(form)))?;
+    Session::from_token_response(&resp)
+}
+
+/// Trades `refresh_token` for new tokens and saves them to `path`.
+fn refresh_session(
+    client: &PinterestClient,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+    path: &Path,
+    refresh_token: String,
+) -> Result<Session> {
+    let form = vec![
+        ("grant_type".to_string(), "refresh_token".to_string()),
+        ("refresh_token".to_string(), refresh_token.clone()),
+    ];
+    let mut refreshed = app_token_request(client, client_id, client_secret, form)
+        .map_err(|err| anyhow!("refresh session {}: {err:#}", path.display()))?;
+    // Pinterest only sometimes rotates the refresh token.
+    refreshed.refresh_token.get_or_insert(refresh_token);
+    save(path, &refreshed)?;
+    log::debug!("refreshed session {}", path.display());
+    Ok(refreshed)
+}
+
+/// Refreshes the session the access token came from once it has expired,
+/// saving the new tokens. Sessions without a refresh token just warn.
+pub fn refresh_if_expired(client: &PinterestClient, config: &mut Config) -> Result<()> {
+    let Some((path, session)) = &config.session else {
+        return Ok(());
+    };
+    if !session.expired() {
+        return Ok(());
+    }
+    let Some(refresh_token) = session.refresh_token.clone() else {
+        log::warn!(
+            "session {} has expired; run `auth login` again",
+            path.display()
+        );
+        return Ok(());
+    };
+    let refreshed = refresh_session(
+        client,
+        config.client_id.as_deref(),
+        config.client_secret.as_deref(),
+        path,
+        refresh_token,
+    )?;
+    config.access_token = Some(refreshed.access_token.clone());
+    config.session = Some((path.clone(), refreshed));
+    Ok(())
+}
+
+/// Readies `client` for the configured session: refreshes it if expired, and
+/// when the token in use is the session's own and it has a refresh token,
+/// arms a one-time refresh for a 401 (a token revoked or expired early).
+pub fn prepare(client: &PinterestClient, config: &mut Config) -> Result<PinterestClient> {
+    refresh_if_expired(client, config)?;
+    let Some((path, session)) = &config.session else {
+        return Ok(client.clone());
+    };
+    if session.refresh_token.is_none()
+        || config.access_token.as_deref() != Some(session.access_token.as_str())
+    {
+        return Ok(client.clone());
+    }
+    let (token_client, path) = (client.clone(), path.clone());
+    let (client_id, client_secret) = (config.client_id.clone(), config.client_secret.clone());
+    let renew = move || -> Result<String> {
+        // Re-read in case another run rotated the refresh token meanwhile.
+        let saved = load(&path)?.ok_or_else(|| anyhow!("session {} is gone", path.display()))?;
+        let refresh_token = saved
+            .refresh_token
+            .ok_or_else(|| anyhow!("session {} has no refresh token", path.display()))?;
+        let refreshed = refresh_session(
+            &token_client,
+            client_id.as_deref(),
+            client_secret.as_deref(),
+            &path,
+            refresh_token,
+        )?;
+        Ok(refreshed.access_token)
+    };
+    Ok(client.with_reauthorize(session.access_token.clone(), renew))
+}
@@ -1,12 +1,67 @@
 use anyhow::{Context, Result, anyhow};
 use reqwest::blocking::Client;
+use reqwest::header::{AUTHORIZATION, CONTENT_ENCODING, HeaderValue};
+use reqwest::redirect::Policy;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
 
+use crate::client::HttpVersion;
 use crate::s3;
 
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Settings for fetching remote sources, kept separate from Pinterest API auth.
+#[derive(Debug, Clone)]
+pub struct SourceOptions {
+    /// Authorization header value sent with http(s) downloads.
+    pub auth_header: Option<String>,
+    /// Redirect hops followed before an http(s) download fails.
+    pub max_redirects: usize,
+    pub http_version: HttpVersion,
+    pub s3: s3::S3Options,
+    /// Gunzip `.gz` sources (and gzip-encoded downloads) before use.
+    pub decompress: bool,
+    /// How `--body`, `--params` and `--form` text is parsed.
+    pub input_format: InputFormat,
+}
+
+/// Syntax of structured request input. `.yaml`/`.yml` sources are YAML
+/// whatever this says.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+impl InputFormat {
+    /// The format for `value` (inline text or a source).
+    pub fn for_source(self, value: &str) -> Self {
+        let path = value.split(['?', '#']).next().unwrap_or(value);
+        let path = path.strip_suffix(".gz").unwrap_or(path);
+        if looks_like_source(value) && (path.ends_with(".yaml") || path.ends_with(".yml")) {
+            Self::Yaml
+        } else {
+            self
+        }
+    }
+}
+
+impl Default for SourceOptions {
+    fn default() -> Self {
+        Self {
+            auth_header: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            http_version: HttpVersion::default(),
+            s3: s3::S3Options::default(),
+            decompress: true,
+            input_format: InputFormat::default(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SourceFile {
     pub path: PathBuf,
@@ -14,6 +69,15 @@ pub struct SourceFile {
     _temp: Option<tempfile::TempPath>,
 }
 
+impl SourceFile {
+    /// Size on disk, sent as `Content-Length` when the file is streamed as a body.
+    pub fn size(&self) -> Result<u64> {
+        let meta = std::fs::metadata(&self.path)
+            .map_err(|err| anyhow!("stat {}: {err}", self.path.display()))?;
+        Ok(meta.len())
+    }
+}
+
 pub fn looks_like_source(value: &str) -> bool {
     value.starts_with('@')
         || value.starts_with("file://")
@@ -23,12 +87,53 @@ pub fn looks_like_source(value: &str) -> bool {
         || Path::new(value).exists()
 }
 
-pub fn resolve_source(value: &str) -> Result<SourceFile> {
+/// An `s3://` source that can be streamed as stored: no `.gz` that
+/// [`resolve_source`] would gunzip into a temp file first.
+pub fn is_streamable_s3(value: &str, opts: &SourceOptions) -> bool {
+    value.starts_with("s3://") && !(opts.decompress && value.ends_with(".gz"))
+}
+
+pub fn is_glob(value: &str) -> bool {
+    if is_remote(value) || local_path(value).exists() {
+        return false;
+    }
+    value.contains(['*', '?', '['])
+}
+
+/// Resolves a source that may expand to several local files (glob patterns).
+pub fn resolve_sources(value: &str, opts: &SourceOptions) -> Result<Vec<SourceFile>> {
+    if is_glob(value) {
+        return expand_glob(value)?
+            .into_iter()
+            .map(|file| maybe_decompress(file, false, opts))
+            .collect();
+    }
+    Ok(vec![resolve_source(value, opts)?])
+}
+
+pub fn resolve_source(value: &str, opts: &SourceOptions) -> Result<SourceFile> {
+    let (file, gzip_encoded) = resolve_raw(value, opts)?;
+    maybe_decompress(file, gzip_encoded, opts)
+}
+
+/// The source as stored, plus whether it was downloaded with
+/// `Content-Encoding: gzip` (reqwest is built without transparent decoding).
+fn resolve_raw(value: &str, opts: &SourceOptions) -> Result<(SourceFile, bool)> {
+    if is_glob(value) {
+        let mut files = expand_glob(value)?;
+        if files.len() > 1 {
+            return Err(anyhow!(
+                "glob {value} matched {} files; expected exactly one",
+                files.len()
+            ));
+        }
+        return Ok((files.remove(0), false));
+    }
     if value.starts_with("s3://") {
-        return download_s3(value);
+        return Ok((download_s3(value, opts)?, false));
     }
     if value.starts_with("http://") || value.starts_with("https://") {
-        return download_http(value);
+        return download_http(value, opts);
     }
 
     let local = local_path(value);
@@ -38,48 +143,194 @@ pub fn resolve_source(value: &str) -> Result<SourceFile> {
             .and_then(|v| v.to_str())
             .unwrap_or("input")
             .to_string();
-        return Ok(SourceFile {
+        let file = SourceFile {
             path: local,
             file_name,
             _temp: None,
-        });
+        };
+        return Ok((file, false));
     }
 
     Err(anyhow!("file not found: {value}"))
 }
 
-pub fn read_source_to_string(value: &str) -> Result<String> {
-    let file = resolve_source(value)?;
+/// Gunzips a gzip-encoded download, or a `name.ext.gz` file whose bytes are
+/// gzip, into a temp file named `name.ext`. Archives that are meant to stay
+/// compressed (`.tar.gz`, `.tgz`, a bare `name.gz`) are passed through.
+fn maybe_decompress(
+    file: SourceFile,
+    gzip_encoded: bool,
+    opts: &SourceOptions,
+) -> Result<SourceFile> {
+    if !opts.decompress {
+        return Ok(file);
+    }
+    let file_name = if gzip_encoded {
+        file.file_name.clone()
+    } else {
+        let Some(inner) = file.file_name.strip_suffix(".gz") else {
+            return Ok(file);
+        };
+        let keep = match inner.rsplit_once('.') {
+            Some((stem, ext)) => stem.is_empty() || ext.eq_ignore_ascii_case("tar"),
+            None => true,
+        };
+        if keep || !has_gzip_magic(&file.path)? {
+            return Ok(file);
+        }
+        inner.to_string()
+    };
+
+    let input = File::open(&file.path).with_context(|| format!("open {}", file.path.display()))?;
+    let mut decoder = flate2::read::MultiGzDecoder::new(input);
+    let mut out = NamedTempFile::new().context("create temp file")?;
+    std::io::copy(&mut decoder, &mut out)
+        .map_err(|err| anyhow!("decompress {}: {err}", file.file_name))?;
+    log::debug!("decompressed {} to {file_name}", file.file_name);
+    let temp_path = out.into_temp_path();
+    Ok(SourceFile {
+        path: temp_path.to_path_buf(),
+        file_name,
+        _temp: Some(temp_path),
+    })
+}
+
+fn has_gzip_magic(path: &Path) -> Result<bool> {
+    let mut magic = [0u8; 2];
+    let mut f = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let n = f.read(&mut magic).context("read source")?;
+    Ok(n == 2 && magic == [0x1f, 0x8b])
+}
+
+pub fn read_source_to_string(value: &str, opts: &SourceOptions) -> Result<String> {
+    let file = resolve_source(value, opts)?;
     let mut f = File::open(&file.path).with_context(|| format!("open {}", file.path.display()))?;
     let mut buf = String::new();
     f.read_to_string(&mut buf).context("read source")?;
     Ok(buf)
 }
 
-fn download_http(url: &str) -> Result<SourceFile> {
-    let client = Client::new();
-    let mut resp = client.get(url).send().context("download url")?;
+fn is_remote(value: &str) -> bool {
+    value.starts_with("s3://") || value.starts_with("http://") || value.starts_with("https://")
+}
+
+fn expand_glob(value: &str) -> Result<Vec<SourceFile>> {
+    let pattern = local_path(value);
+    let pattern = pattern
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid glob pattern: {value}"))?;
+    let mut files = Vec::new();
+    for entry in glob::glob(pattern).with_context(|| format!("invalid glob pattern: {value}"))? {
+        let path = entry.context("read glob match")?;
+        if !path.is_file() {
+            continue;
+        }
+        let file_name = path
+            .file_name()
+            .and_then(|v| v.to_str())
+            .unwrap_or("input")
+            .to_string();
+        files.push(SourceFile {
+            path,
+            file_name,
+            _temp: None,
+        });
+    }
+    if files.is_empty() {
+        return Err(anyhow!("no files match: {value}"));
+    }
+    Ok(files)
+}
+
+fn download_http(url: &str, opts: &SourceOptions) -> Result<(SourceFile, bool)> {
+    let client = opts
+        .http_version
+        .apply(Client::builder())
+        .redirect(redirect_policy(opts.max_redirects))
+        .build()
+        .context("build download client")?;
+    let request = match &opts.auth_header {
+        Some(header) => {
+            // An explicit header replaces any user:pass@ credentials in the URL.
+            let mut parsed = reqwest::Url::parse(url).context("invalid source url")?;
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            let value = HeaderValue::from_str(header).context("invalid source auth header")?;
+            client.get(parsed).header(AUTHORIZATION, value)
+        }
+        // reqwest turns user:pass@ credentials embedded in the URL into Basic auth.
+        None => client.get(url),
+    };
+    let resp = request.send().map_err(|err| {
+        // Surface the policy's message (which names the chain) instead of reqwest's wrapper.
+        match std::error::Error::source(&err) {
+            Some(reason) if err.is_redirect() => anyhow!("download {url}: {reason}"),
+            _ => anyhow::Error::new(err).context("download url"),
+        }
+    })?;
+    let mut resp = resp.error_for_status().context("download url")?;
+    let gzip_encoded = resp
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("gzip"));
+    let final_url = resp.url().clone();
+    if final_url.as_str() != url {
+        log::debug!("download {url} resolved to {final_url}");
+    }
     let mut file = NamedTempFile::new().context("create temp file")?;
     resp.copy_to(&mut file).context("write temp file")?;
     let temp_path = file.into_temp_path();
     let path = temp_path.to_path_buf();
-    let file_name = url
-        .split('/')
-        .last()
+    let file_name = final_url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
         .filter(|v| !v.is_empty())
         .unwrap_or("download")
         .to_string();
-    Ok(SourceFile {
+    let file = SourceFile {
         path,
         file_name,
         _temp: Some(temp_path),
+    };
+    Ok((file, gzip_encoded))
+}
+
+fn redirect_policy(max_redirects: usize) -> Policy {
+    Policy::custom(move |attempt| {
+        let chain = || {
+            attempt
+                .previous()
+                .iter()
+                .chain(std::iter::once(attempt.url()))
+                .map(|u| u.as_str())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        };
+        if attempt.previous().contains(attempt.url()) {
+            let message = format!("redirect loop: {}", chain());
+            return attempt.error(message);
+        }
+        if attempt.previous().len() > max_redirects {
+            let message = format!("too many redirects (max {max_redirects}): {}", chain());
+            return attempt.error(message);
+        }
+        if let Some(from) = attempt.previous().last() {
+            log::debug!(
+                "redirect {} ({}) -> {}",
+                from,
+                attempt.status(),
+                attempt.url()
+            );
+        }
+        attempt.follow()
     })
 }
 
-fn download_s3(url: &str) -> Result<SourceFile> {
+fn download_s3(url: &str, opts: &SourceOptions) -> Result<SourceFile> {
     let (bucket, key) = s3::parse_s3_url(url)?;
     let mut file = NamedTempFile::new().context("create temp file")?;
-    s3::download_object_blocking(&bucket, &key, &mut file)?;
+    s3::download_object_blocking(&bucket, &key, &mut file, &opts.s3)?;
     let temp_path = file.into_temp_path();
     let path = temp_path.to_path_buf();
     let file_name = Path::new(&key)
@@ -103,3 +354,78 @@ fn local_path(value: &str) -> PathBuf {
     }
     PathBuf::from(value)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    const EVENTS: &str = "{\"event_name\":\"checkout\"}\n{\"event_name\":\"lead\"}\n";
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn no_decompress() -> SourceOptions {
+        SourceOptions {
+            decompress: false,
+            ..SourceOptions::default()
+        }
+    }
+
+    fn read(file: &SourceFile) -> Vec<u8> {
+        std::fs::read(&file.path).unwrap()
+    }
+
+    #[test]
+    fn gz_files_are_gunzipped_unless_told_not_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.ndjson.gz");
+        std::fs::write(&path, gzip(EVENTS)).unwrap();
+        let value = path.to_str().unwrap();
+
+        let file = resolve_source(value, &SourceOptions::default()).unwrap();
+        assert_eq!(file.file_name, "events.ndjson");
+        assert_eq!(read(&file), EVENTS.as_bytes());
+
+        let file = resolve_source(value, &no_decompress()).unwrap();
+        assert_eq!(file.file_name, "events.ndjson.gz");
+        assert_eq!(read(&file), gzip(EVENTS));
+    }
+
+    #[test]
+    fn archives_and_misnamed_files_are_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["bundle.tar.gz", "bundle.gz"] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, gzip(EVENTS)).unwrap();
+            let file = resolve_source(path.to_str().unwrap(), &SourceOptions::default()).unwrap();
+            assert_eq!((file.file_name.as_str(), read(&file)), (name, gzip(EVENTS)));
+        }
+        // Named .gz but not gzip.
+        let path = dir.path().join("plain.json.gz");
+        std::fs::write(&path, EVENTS).unwrap();
+        let file = resolve_source(path.to_str().unwrap(), &SourceOptions::default()).unwrap();
+        assert_eq!(read(&file), EVENTS.as_bytes());
+    }
+
+    #[test]
+    fn downloads_are_gunzipped_by_name_or_content_encoding() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/events.ndjson.gz" => Response::new(200, gzip(EVENTS)),
+            _ => Response::new(200, gzip(EVENTS)).header("Content-Encoding", "gzip"),
+        });
+        for path in ["/events.ndjson.gz", "/export?format=ndjson"] {
+            let url = format!("{}{path}", server.url());
+            let file = resolve_source(&url, &SourceOptions::default()).unwrap();
+            assert_eq!(read(&file), EVENTS.as_bytes(), "{path}");
+            let file = resolve_source(&url, &no_decompress()).unwrap();
+            assert_eq!(read(&file), gzip(EVENTS), "{path}");
+        }
+    }
+}
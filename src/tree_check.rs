@@ -0,0 +1,235 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::command_tree::{CommandTree, Operation};
+
+/// Methods the client can send.
+const METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD"];
+
+/// Security schemes the CLI knows how to authenticate.
+const SECURITY_SCHEMES: &[&str] = &[
+    "pinterest_oauth2",
+    "client_credentials",
+    "conversion_token",
+    "basic",
+];
+
+const PARAM_LOCATIONS: &[&str] = &["path", "query", "header"];
+
+/// A structural problem in a command tree.
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    /// 1-based line of the resource or operation in the file, when known.
+    pub line: Option<usize>,
+    pub resource: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub op: Option<String>,
+    pub message: String,
+}
+
+/// Parses the command tree at `path` and [`check`]s it. A file that doesn't
+/// parse is an error (serde reports its line and column).
+pub fn check_file(path: &Path) -> Result<Vec<Problem>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow!("read command tree {}: {err}", path.display()))?;
+    let tree: CommandTree = serde_json::from_str(&text)
+        .map_err(|err| anyhow!("invalid command tree {}: {err}", path.display()))?;
+    Ok(check(&tree, &text))
+}
+
+/// Checks what the CLI relies on but serde can't: unique resource and
+/// operation names (and flags within an operation), known methods, param
+/// locations and security schemes, and that every `{param}` of a path
+/// template is a declared path param and vice versa. `text` is the tree's
+/// JSON, used only to find each problem's line.
+pub fn check(tree: &CommandTree, text: &str) -> Vec<Problem> {
+    let starts = object_starts(text);
+    let line = |offset: Option<&usize>| offset.map(|&at| text[..at].matches('\n').count() + 1);
+    let mut problems = Vec::new();
+    let mut resources = HashSet::new();
+    for (r, resource) in tree.resources.iter().enumerate() {
+        let resource_line = line(starts.get(r).map(|(at, _)| at));
+        if resource.name.is_empty() {
+            problems.push(Problem {
+                line: resource_line,
+                resource: format!("resources[{r}]"),
+                op: None,
+                message: "resource has no name".to_string(),
+            });
+        } else if !resources.insert(resource.name.as_str()) {
+            problems.push(Problem {
+                line: resource_line,
+                resource: resource.name.clone(),
+                op: None,
+                message: "duplicate resource name".to_string(),
+            });
+        }
+        let mut ops = HashSet::new();
+        for (o, op) in resource.ops.iter().enumerate() {
+            let mut messages = check_op(op);
+            if !ops.insert(op.name.as_str()) {
+                messages.insert(0, "duplicate operation name".to_string());
+            }
+            let op_line = line(starts.get(r).and_then(|(_, ops)| ops.get(o)));
+            problems.extend(messages.into_iter().map(|message| Problem {
+                line: op_line,
+                resource: resource.name.clone(),
+                op: Some(op.name.clone()),
+                message,
+            }));
+        }
+    }
+    problems
+}
+
+fn check_op(op: &Operation) -> Vec<String> {
+    let mut messages = Vec::new();
+    if op.name.is_empty() {
+        messages.push("operation has no name".to_string());
+    }
+    if !METHODS.contains(&op.method.as_str()) {
+        messages.push(format!(
+            "method {:?} is not one of {}",
+            op.method,
+            METHODS.join(", ")
+        ));
+    }
+
+    let mut flags = HashSet::new();
+    for param in &op.params {
+        if !PARAM_LOCATIONS.contains(&param.location.as_str()) {
+            messages.push(format!(
+                "param {} is in {:?}, not one of {}",
+                param.name,
+                param.location,
+                PARAM_LOCATIONS.join(", ")
+            ));
+        }
+        if !flags.insert(param.flag.as_str()) {
+            messages.push(format!("flag --{} is used by two params", param.flag));
+        }
+    }
+
+    match template_params(&op.path) {
+        Some(names) => {
+            for name in &names {
+                match op.params.iter().find(|p| p.name == *name) {
+                    None => messages.push(format!("path param {{{name}}} is missing from params")),
+                    Some(param) if param.location != "path" => messages.push(format!(
+                        "path param {{{name}}} is declared in {:?}, not \"path\"",
+                        param.location
+                    )),
+                    Some(_) => {}
+                }
+            }
+            for param in op.params.iter().filter(|p| p.location == "path") {
+                if !names.contains(&param.name.as_str()) {
+                    messages.push(format!(
+                        "param {} is in \"path\" but {} has no {{{}}}",
+                        param.name, op.path, param.name
+                    ));
+                }
+            }
+        }
+        None => messages.push(format!("path {} has unbalanced braces", op.path)),
+    }
+
+    for scheme in op.security.iter().flat_map(|req| req.keys()) {
+        if !SECURITY_SCHEMES.contains(&scheme.as_str()) {
+            messages.push(format!(
+                "unknown security scheme {scheme:?} (known: {})",
+                SECURITY_SCHEMES.join(", ")
+            ));
+        }
+    }
+    messages
+}
+
+/// The `{name}`s of a path template; `None` if its braces don't pair up.
+fn template_params(path: &str) -> Option<Vec<&str>> {
+    let mut names = Vec::new();
+    let mut rest = path;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return None;
+        }
+        let end = rest[start..].find('}')? + start;
+        let name = &rest[start + 1..end];
+        if name.is_empty() || name.contains('{') {
+            return None;
+        }
+        names.push(name);
+        rest = &rest[end + 1..];
+    }
+    Some(names)
+}
+
+/// Byte offsets at which each `resources[i]` object, and each of its
+/// `ops[j]`, opens in valid JSON `text`.
+fn object_starts(text: &str) -> Vec<(usize, Vec<usize>)> {
+    enum Frame {
+        /// The key of the value being read (the last key seen).
+        Object(String),
+        Array,
+    }
+    let mut starts: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    // Whether the next string in an object is a key rather than a value.
+    let mut expect_key = false;
+    let mut chars = text.char_indices();
+    while let Some((at, c)) = chars.next() {
+        match c {
+            '"' => {
+                let mut key = String::new();
+                let mut escaped = false;
+                for (_, c) in chars.by_ref() {
+                    match (escaped, c) {
+                        (false, '\\') => escaped = true,
+                        (false, '"') => break,
+                        _ => {
+                            escaped = false;
+                            key.push(c);
+                        }
+                    }
+                }
+                if expect_key && let Some(Frame::Object(current)) = stack.last_mut() {
+                    *current = key;
+                    expect_key = false;
+                }
+            }
+            '{' | '[' => {
+                if c == '{' {
+                    match stack.as_slice() {
+                        [Frame::Object(key), Frame::Array] if key == "resources" => {
+                            starts.push((at, Vec::new()));
+                        }
+                        [
+                            Frame::Object(key),
+                            Frame::Array,
+                            Frame::Object(ops),
+                            Frame::Array,
+                        ] if key == "resources" && ops == "ops" => {
+                            if let Some((_, ops)) = starts.last_mut() {
+                                ops.push(at);
+                            }
+                        }
+                        _ => {}
+                    }
+                    stack.push(Frame::Object(String::new()));
+                    expect_key = true;
+                } else {
+                    stack.push(Frame::Array);
+                }
+            }
+            '}' | ']' => {
+                stack.pop();
+                expect_key = false;
+            }
+            ',' => expect_key = matches!(stack.last(), Some(Frame::Object(_))),
+            _ => {}
+        }
+    }
+    starts
+}
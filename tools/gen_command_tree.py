@@ -6,6 +6,32 @@ from collections import defaultdict
 from typing import Any, Dict, List, Optional, Tuple
 
 
+IGNORED_HEADER_PARAMS = {"accept", "content-type", "authorization"}
+
+# Default request timeout (seconds) for slow endpoints: media registration and
+# processing checks, and report creation/fetches. An op's `x-timeout` wins.
+LONG_TIMEOUT = 300
+LONG_TIMEOUT_PATH_MARKERS = ("/media", "/reports", "/mmm_reports")
+
+
+def success_codes(op: Dict[str, Any]) -> List[int]:
+    """The 2xx statuses the spec documents for an operation, in numeric order."""
+    codes: List[int] = []
+    for code in op.get("responses") or {}:
+        code = str(code)
+        if len(code) == 3 and code.startswith("2") and code.isdigit():
+            codes.append(int(code))
+    return sorted(codes)
+
+
+def op_timeout(path: str, op: Dict[str, Any]) -> Optional[int]:
+    if "x-timeout" in op:
+        return int(op["x-timeout"])
+    if any(marker in path for marker in LONG_TIMEOUT_PATH_MARKERS):
+        return LONG_TIMEOUT
+    return None
+
+
 def to_kebab(value: str) -> str:
     # Handles snake_case and simple CamelCase.
     value = value.replace("_", "-")
@@ -49,19 +75,43 @@ def schema_type(doc: Dict[str, Any], schema: Dict[str, Any]) -> Tuple[str, Optio
     return "array", (items.get("type") or "string")
 
 
+def object_fields(doc: Dict[str, Any], schema: Dict[str, Any]) -> Optional[List[str]]:
+    # Top-level property names across oneOf/anyOf/allOf branches; None when
+    # the object accepts arbitrary keys.
+    if "$ref" in schema:
+        schema = resolve_ref(doc, schema["$ref"])
+    if schema.get("additionalProperties"):
+        return None
+    fields = list(schema.get("properties") or {})
+    for key in ("oneOf", "anyOf", "allOf"):
+        for branch in schema.get(key) or []:
+            sub = object_fields(doc, branch)
+            if sub is None:
+                return None
+            fields.extend(f for f in sub if f not in fields)
+    return fields
+
+
 def parse_param(doc: Dict[str, Any], param: Dict[str, Any]) -> Dict[str, Any]:
     schema = param.get("schema") or {}
     typ, items_typ = schema_type(doc, schema)
-    return {
+    out = {
         "name": param["name"],
-        "flag": param["name"].replace("_", "-"),
+        "flag": param["name"].replace("_", "-").lower() if param["in"] == "header" else param["name"].replace("_", "-"),
         "in": param["in"],
         "required": bool(param.get("required", False)),
         "style": param.get("style"),
         "explode": param.get("explode"),
         "schema_type": typ,
         "items_type": items_typ,
+        "default": schema.get("default"),
+        "minimum": schema.get("minimum"),
+        "maximum": schema.get("maximum"),
+        "example": param.get("example", schema.get("example")),
     }
+    if param.get("style") == "deepObject":
+        out["fields"] = object_fields(doc, schema) or None
+    return out
 
 
 def parse_request_body(doc: Dict[str, Any], request_body: Any) -> Optional[Dict[str, Any]]:
@@ -70,12 +120,35 @@ def parse_request_body(doc: Dict[str, Any], request_body: Any) -> Optional[Dict[
     if isinstance(request_body, dict) and "$ref" in request_body:
         request_body = resolve_ref(doc, request_body["$ref"])
     content = request_body.get("content") or {}
+    content_types = sorted(content.keys())
+    primary = "application/json" if "application/json" in content else (content_types or [None])[0]
+    schema = (content.get(primary) or {}).get("schema") if primary else None
     return {
         "required": bool(request_body.get("required", False)),
-        "content_types": sorted(content.keys()),
+        "content_types": content_types,
+        "schema": schema,
     }
 
 
+def collect_schema_refs(doc: Dict[str, Any], value: Any, out: Dict[str, Any]) -> None:
+    # Gathers every components/schemas entry reachable from value (transitively).
+    if isinstance(value, list):
+        for item in value:
+            collect_schema_refs(doc, item, out)
+        return
+    if not isinstance(value, dict):
+        return
+    ref = value.get("$ref")
+    prefix = "#/components/schemas/"
+    if isinstance(ref, str) and ref.startswith(prefix):
+        name = ref[len(prefix):]
+        if name not in out:
+            out[name] = resolve_ref(doc, ref)
+            collect_schema_refs(doc, out[name], out)
+    for item in value.values():
+        collect_schema_refs(doc, item, out)
+
+
 def pick_response_schema(doc: Dict[str, Any], op: Dict[str, Any]) -> Optional[Dict[str, Any]]:
     responses = op.get("responses") or {}
     for code in ("200", "201", "202"):
@@ -102,6 +175,69 @@ def is_paginated(doc: Dict[str, Any], schema: Optional[Dict[str, Any]]) -> bool:
     return False
 
 
+def find_list_key(doc: Dict[str, Any], schema: Optional[Dict[str, Any]], depth: int = 0) -> Optional[str]:
+    """Name of the top-level array property holding the results, if any."""
+    if not schema or depth > 8:
+        return None
+    if "$ref" in schema:
+        return find_list_key(doc, resolve_ref(doc, schema["$ref"]), depth + 1)
+    for part in schema.get("allOf") or []:
+        key = find_list_key(doc, part, depth + 1)
+        if key:
+            return key
+    props = schema.get("properties") or {}
+    for key in ("items", "data"):
+        prop = props.get(key)
+        if prop is None:
+            continue
+        if "$ref" in prop:
+            prop = resolve_ref(doc, prop["$ref"])
+        if prop.get("type") == "array":
+            return key
+    return None
+
+
+def property_order(doc: Dict[str, Any], schema: Optional[Dict[str, Any]], depth: int = 0) -> List[str]:
+    """Property names in spec order, across allOf/oneOf/anyOf branches."""
+    if not schema or depth > 8:
+        return []
+    if "$ref" in schema:
+        schema = resolve_ref(doc, schema["$ref"])
+    fields = list(schema.get("properties") or {})
+    for key in ("allOf", "oneOf", "anyOf"):
+        for branch in schema.get(key) or []:
+            fields.extend(f for f in property_order(doc, branch, depth + 1) if f not in fields)
+    return fields
+
+
+def find_properties(doc: Dict[str, Any], schema: Optional[Dict[str, Any]], name: str, depth: int = 0) -> List[Dict[str, Any]]:
+    """Every definition of property `name`, including ones in allOf parts."""
+    if not schema or depth > 8:
+        return []
+    if "$ref" in schema:
+        schema = resolve_ref(doc, schema["$ref"])
+    found = []
+    prop = (schema.get("properties") or {}).get(name)
+    if prop is not None:
+        found.append(resolve_ref(doc, prop["$ref"]) if "$ref" in prop else prop)
+    for part in schema.get("allOf") or []:
+        found.extend(find_properties(doc, part, name, depth + 1))
+    return found
+
+
+def response_fields(doc: Dict[str, Any], schema: Optional[Dict[str, Any]], list_key: Optional[str]) -> Optional[List[str]]:
+    # Columns for tabular output: the list items' properties for list ops,
+    # else the response object's own.
+    if not list_key:
+        return property_order(doc, schema) or None
+    # allOf parts can refine a generic list (Paginated's `items: object`).
+    for prop in find_properties(doc, schema, list_key):
+        fields = property_order(doc, prop.get("items"))
+        if fields:
+            return fields
+    return None
+
+
 def main() -> None:
     parser = argparse.ArgumentParser()
     parser.add_argument("--openapi", required=True, help="path to openapi.json")
@@ -139,6 +275,9 @@ def main() -> None:
             for param in (path_item.get("parameters") or []) + (op.get("parameters") or []):
                 if "$ref" in param:
                     param = resolve_ref(doc, param["$ref"])
+                # OpenAPI ignores these as header params; the client sets them itself.
+                if param["in"] == "header" and param["name"].lower() in IGNORED_HEADER_PARAMS:
+                    continue
                 params.append(parse_param(doc, param))
 
             # Stable ordering: path params first, then query params, then by name.
@@ -147,24 +286,39 @@ def main() -> None:
             rb = parse_request_body(doc, op.get("requestBody"))
             resp_schema = pick_response_schema(doc, op)
             paginated = is_paginated(doc, resp_schema)
+            list_key = find_list_key(doc, resp_schema)
 
             security = op.get("security")
             if security is None:
                 security = global_security
 
-            resources[res_name].append(
-                {
-                    "name": op_name,
-                    "method": method.upper(),
-                    "path": path,
-                    "summary": op.get("summary"),
-                    "tags": tags,
-                    "paginated": paginated,
-                    "security": security,
-                    "params": params,
-                    "request_body": rb,
-                }
-            )
+            entry = {
+                "name": op_name,
+                "method": method.upper(),
+                "path": path,
+                "summary": op.get("summary"),
+                "tags": tags,
+                "paginated": paginated,
+                "list_key": list_key,
+                "deprecated": bool(op.get("deprecated", False)),
+                "destructive": bool(op.get("x-destructive", False)),
+                "security": security,
+                "params": params,
+                "request_body": rb,
+            }
+            fields = response_fields(doc, resp_schema, list_key)
+            if fields:
+                entry["response_fields"] = fields
+            timeout = op_timeout(path, op)
+            if timeout is not None:
+                entry["timeout"] = timeout
+            codes = success_codes(op)
+            if codes:
+                entry["success_codes"] = codes
+            # For --validate-response; its $refs are collected into "schemas" below.
+            if resp_schema:
+                entry["response_schema"] = resp_schema
+            resources[res_name].append(entry)
 
     out_resources = []
     for res_name in sorted(resources.keys()):
@@ -182,11 +336,20 @@ def main() -> None:
         ops.sort(key=lambda o: o["name"])
         out_resources.append({"name": res_name, "ops": ops})
 
+    schemas: Dict[str, Any] = {}
+    for res in out_resources:
+        for op in res["ops"]:
+            if op["request_body"]:
+                collect_schema_refs(doc, op["request_body"]["schema"], schemas)
+            if op.get("response_schema"):
+                collect_schema_refs(doc, op["response_schema"], schemas)
+
     out = {
         "version": 1,
         "api_version": api_version,
         "base_url": base_url,
         "resources": out_resources,
+        "schemas": {name: schemas[name] for name in sorted(schemas)},
     }
 
     with open(args.out, "w", encoding="utf-8") as f: